@@ -0,0 +1,33 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+/// Generate a sorted ECO code to opening name table from the bundled TSV.
+fn main() {
+    let tsv_path = "data/eco.tsv";
+    println!("cargo:rerun-if-changed={}", tsv_path);
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let tsv = fs::read_to_string(tsv_path).expect("ECO table should be readable");
+    let mut entries = tsv
+        .lines()
+        .skip(1)
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let mut columns = l.splitn(2, '\t');
+            let eco = columns.next().expect("ECO code column").trim();
+            let name = columns.next().expect("opening name column").trim();
+            (eco.to_owned(), name.to_owned())
+        })
+        .collect::<Vec<(String, String)>>();
+    entries.sort();
+
+    let mut table = String::from("static ECO_NAMES: &[(&str, &str)] = &[\n");
+    for (eco, name) in entries.iter() {
+        table.push_str(&format!("    ({:?}, {:?}),\n", eco, name));
+    }
+    table.push_str("];\n");
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("eco.rs"), table).expect("ECO table should be writable");
+}
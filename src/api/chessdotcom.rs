@@ -1,15 +1,25 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
 
-use chrono::serde::ts_seconds::deserialize as from_ts;
-use chrono::serde::ts_seconds_option::deserialize as from_ts_option;
 use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use reqwest::Url;
+use serde::{de, Deserialize, Deserializer, Serialize};
 use serde_json;
-use shakmaty::{fen::Fen, CastlingMode, Chess, Color, Setup};
+use shakmaty::{CastlingMode, Chess};
 
-use super::{ChessGame, ChessPlayer, DisplayableChessGame};
+use super::timestamp::{deserialize as from_ts, deserialize_option as from_ts_option};
+use super::{
+    ChessGame, ChessPlayer, Color, DisplayableChessGame, MoveClock, Outcome, Speed, TimeClass,
+    TimestampUnit,
+};
 
-use crate::utils::next_move;
+use crate::openings::opening_of_moves;
+use crate::utils::{
+    chess_com_message_termination, chess_com_termination, decode_next_move, eco_name, fen_after,
+    next_move, parse_fen, pgn_moves, pgn_tag, pgn_tags, san_to_uci, DecodedMove, MoveDecodeError,
+    Termination,
+};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all(deserialize = "camelCase"))]
@@ -35,6 +45,14 @@ impl ChessPlayer for Player {
     }
 
     fn url(&self) -> Option<String> {
+        Some(member_url(&self.username))
+    }
+
+    fn profile_url(&self) -> Option<String> {
+        self.url()
+    }
+
+    fn api_url(&self) -> Option<String> {
         Some(self.id.clone())
     }
 
@@ -43,41 +61,67 @@ impl ChessPlayer for Player {
     }
 }
 
+/// A player of a live game. Fields are also read by the names they're serialized with, so that
+/// serialized players can be read back.
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct LivePlayer {
     pub username: String,
     pub rating: u32,
     pub id: u64,
+    #[serde(alias = "is_content_hidden")]
     pub is_content_hidden: bool,
+    #[serde(alias = "avatar_url")]
     pub avatar_url: String,
+    #[serde(alias = "country_id")]
     pub country_id: i32,
+    #[serde(alias = "is_enabled")]
     pub is_enabled: bool,
+    #[serde(alias = "can_win_on_time")]
     pub can_win_on_time: bool,
+    #[serde(alias = "chess_title")]
     pub chess_title: Option<String>,
-    pub color: String,
+    pub color: Color,
+    #[serde(alias = "country_name")]
     pub country_name: String,
+    #[serde(alias = "default_tab")]
     pub default_tab: i32,
+    #[serde(alias = "has_moved_at_least_once")]
     pub has_moved_at_least_once: bool,
+    #[serde(alias = "is_drawable")]
     pub is_drawable: bool,
+    #[serde(alias = "is_online")]
     pub is_online: bool,
+    #[serde(alias = "is_in_live_chess")]
     pub is_in_live_chess: Option<bool>,
+    #[serde(alias = "is_touch_move")]
     pub is_touch_move: bool,
+    #[serde(alias = "is_vacation")]
     pub is_vacation: bool,
+    #[serde(alias = "is_white_on_bottom")]
     pub is_white_on_bottom: bool,
     #[serde(deserialize_with = "from_ts_option")]
     #[serde(default)]
+    #[serde(alias = "last_login_date")]
     pub last_login_date: Option<DateTime<Utc>>,
     pub location: Option<String>,
+    #[serde(alias = "membership_level")]
     pub membership_level: Option<i32>,
+    #[serde(alias = "membership_code")]
     pub membership_code: Option<String>,
     #[serde(deserialize_with = "from_ts_option")]
     #[serde(default)]
+    #[serde(alias = "member_since")]
     pub member_since: Option<DateTime<Utc>>,
+    #[serde(alias = "post_move_action")]
     pub post_move_action: String,
+    #[serde(alias = "turn_time_remaining")]
     pub turn_time_remaining: String,
+    #[serde(alias = "flair_code")]
     pub flair_code: String,
+    #[serde(alias = "vacation_remaining")]
     pub vacation_remaining: String,
+    #[serde(alias = "games_in_progress")]
     pub games_in_progress: i32,
 }
 
@@ -98,10 +142,11 @@ impl ChessPlayer for LivePlayer {
     }
 
     fn url(&self) -> Option<String> {
-        Some(format!(
-            "https://www.chess.com/member/{}",
-            self.username.clone()
-        ))
+        Some(member_url(&self.username))
+    }
+
+    fn profile_url(&self) -> Option<String> {
+        Some(member_url(&self.username))
     }
 
     fn result(&self) -> Option<String> {
@@ -109,6 +154,11 @@ impl ChessPlayer for LivePlayer {
     }
 }
 
+/// URL of the profile page of a chess.com member.
+fn member_url(username: &str) -> String {
+    format!("https://www.chess.com/member/{}", username)
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LivePlayers {
     pub top: LivePlayer,
@@ -134,16 +184,18 @@ pub struct Game {
     pub r#match: Option<String>,
 }
 
-impl ChessGame for Game {
-    type PlayerType = Player;
-
-    fn to_json_pretty(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string_pretty(self)
+impl Game {
+    /// Name the opening from the moves of games played from the starting position.
+    fn opening_by_moves(&self) -> Option<&'static str> {
+        if pgn_tag(&self.pgn, "SetUp").as_deref() == Some("1") {
+            return None;
+        }
+        opening_of_moves(&pgn_moves(&self.pgn))
     }
+}
 
-    fn to_json(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string(self)
-    }
+impl ChessGame for Game {
+    type PlayerType = Player;
 
     fn pgn(&mut self) -> String {
         self.pgn.clone()
@@ -164,13 +216,302 @@ impl ChessGame for Game {
     fn end_time(&self) -> DateTime<Utc> {
         self.end_time.clone()
     }
+
+    fn start_time(&self) -> Option<DateTime<Utc>> {
+        self.start_time
+    }
+
+    fn eco(&self) -> Option<String> {
+        pgn_tag(&self.pgn, "ECO")
+    }
+
+    fn opening(&self) -> Option<String> {
+        // chess.com's eco field is an openings URL ending in a slug of the opening name
+        let from_url = self
+            .eco
+            .as_ref()
+            .and_then(|url| url.rsplit('/').next())
+            .filter(|slug| !slug.is_empty())
+            .map(|slug| slug.replace('-', " "));
+
+        match from_url {
+            Some(name) => Some(name),
+            None => self
+                .eco()
+                .and_then(|eco| eco_name(&eco))
+                .or_else(|| self.opening_by_moves())
+                .map(|name| name.to_string()),
+        }
+    }
+
+    fn moves_with_evals(&self) -> Option<String> {
+        None
+    }
+
+    fn speed(&self) -> Speed {
+        Speed::from_time_control(&self.time_control)
+    }
+
+    fn time_class(&self) -> Option<TimeClass> {
+        TimeClass::from_time_control(&self.time_control)
+    }
+
+    fn variant(&self) -> String {
+        match self.rules.as_str() {
+            "chess" => "standard".to_string(),
+            rules => rules.to_string(),
+        }
+    }
+
+    fn moves(&mut self) -> Vec<String> {
+        pgn_moves(&self.pgn)
+    }
+
+    fn result_for(&mut self, username: &str) -> Option<Outcome> {
+        let username = username.to_lowercase();
+        if self.white.username.to_lowercase() == username {
+            Outcome::from_result_code(&self.white.result)
+        } else if self.black.username.to_lowercase() == username {
+            Outcome::from_result_code(&self.black.result)
+        } else {
+            None
+        }
+    }
+
+    fn rating_change(&mut self, _username: &str) -> Option<i32> {
+        // Archives only have the ratings after the game
+        None
+    }
+
+    fn clocks(&mut self) -> Vec<MoveClock> {
+        MoveClock::from_pgn(&self.pgn)
+    }
+
+    fn is_checkmate(&self) -> bool {
+        // Only the loser's result tells how the game ended
+        self.white.result == "checkmated" || self.black.result == "checkmated"
+    }
+
+    fn is_stalemate(&self) -> bool {
+        self.white.result == "stalemate"
+    }
+
+    fn termination(&self) -> Termination {
+        chess_com_termination(&self.white.result, &self.black.result)
+    }
 }
 
 impl DisplayableChessGame for Game {}
 
+/// A month of games. Games are parsed as T, which may be a looser type than Game to parse each
+/// game separately.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Games<T = Game> {
+    pub games: Vec<T>,
+}
+
+/// A daily game that is still being played, as returned by the player's current games endpoint.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OngoingGame {
+    /// URL of the white player's profile in the API
+    pub white: String,
+    /// URL of the black player's profile in the API
+    pub black: String,
+    pub url: String,
+    pub fen: String,
+    pub pgn: String,
+    pub turn: Color,
+    #[serde(deserialize_with = "from_ts")]
+    pub move_by: DateTime<Utc>,
+    pub time_control: String,
+}
+
+impl OngoingGame {
+    pub fn white_name(&self) -> String {
+        username_from_profile_url(&self.white)
+    }
+
+    pub fn black_name(&self) -> String {
+        username_from_profile_url(&self.black)
+    }
+}
+
+/// Player profile URLs in the API end with the player's username.
+fn username_from_profile_url(url: &str) -> String {
+    url.trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(url)
+        .to_string()
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OngoingGames {
+    pub games: Vec<OngoingGame>,
+}
+
+/// A tournament, as returned by the tournament endpoint. Its rounds are the URLs of their
+/// endpoints in the API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tournament {
+    pub name: String,
+    pub url: String,
+    pub status: String,
+    #[serde(default, deserialize_with = "from_ts_option")]
+    pub finish_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub players: Vec<TournamentPlayer>,
+    #[serde(default)]
+    pub rounds: Vec<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct TournamentPlayer {
+    pub username: String,
+    pub status: String,
+}
+
+/// A round of a tournament, with the points each player scored in it. Its groups are the URLs of
+/// their endpoints in the API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TournamentRound {
+    #[serde(default)]
+    pub players: Vec<RoundStanding>,
+    #[serde(default)]
+    pub groups: Vec<String>,
+}
+
+impl TournamentRound {
+    /// Players of the round from most to fewest points.
+    pub fn standings(&self) -> Vec<&RoundStanding> {
+        let mut standings: Vec<&RoundStanding> = self.players.iter().collect();
+        standings.sort_by(|a, b| b.points.partial_cmp(&a.points).unwrap_or(Ordering::Equal));
+        standings
+    }
+}
+
 #[derive(Deserialize, Debug, Clone)]
-pub struct Games {
-    pub games: Vec<Game>,
+pub struct RoundStanding {
+    pub username: String,
+    #[serde(default)]
+    pub points: f32,
+    pub is_advancing: Option<bool>,
+}
+
+/// A tournament a player took part in or registered for, as listed by the player's tournaments
+/// endpoint. Only finished tournaments have results.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PlayerTournament {
+    pub url: String,
+    #[serde(rename = "@id")]
+    pub api_url: String,
+    pub status: String,
+    pub wins: Option<u32>,
+    pub losses: Option<u32>,
+    pub draws: Option<u32>,
+    pub placement: Option<u32>,
+    pub total_players: Option<u32>,
+}
+
+impl PlayerTournament {
+    /// ID of the tournament in the API, the last segment of its endpoint's URL, e.g.
+    /// titled-tuesday-blitz-january-02-2024-4528654.
+    pub fn id(&self) -> String {
+        self.api_url
+            .trim_end_matches('/')
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    /// Name of the tournament, taken from its ID without the trailing number, e.g.
+    /// titled tuesday blitz january 02 2024.
+    pub fn name(&self) -> String {
+        let id = self.id();
+        let name = match id.rsplit_once('-') {
+            Some((name, number)) if number.chars().all(|c| c.is_ascii_digit()) => name,
+            _ => id.as_str(),
+        };
+        name.replace('-', " ")
+    }
+}
+
+/// Whether a player is online, as returned by the member callback, with the same fields as the
+/// players of live games. Either may be missing, e.g. for players who hide their status.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all(deserialize = "camelCase"))]
+pub struct PlayerStatus {
+    pub username: String,
+    #[serde(alias = "is_online")]
+    pub is_online: Option<bool>,
+    #[serde(alias = "is_in_live_chess")]
+    pub is_in_live_chess: Option<bool>,
+}
+
+impl PlayerStatus {
+    /// A line describing the status, e.g. "Hikaru is online, playing live chess".
+    pub fn describe(&self) -> String {
+        match (self.is_online, self.is_in_live_chess) {
+            (None, None) => format!("The status of {} isn't available", self.username),
+            (Some(false), _) => format!("{} is offline", self.username),
+            (_, Some(true)) => format!("{} is online, playing live chess", self.username),
+            (Some(true), Some(false)) => format!("{} is online, not playing", self.username),
+            (Some(true), None) => format!("{} is online", self.username),
+            (None, Some(false)) => format!("{} is not playing", self.username),
+        }
+    }
+}
+
+/// Tournaments of a player, as returned by the player's tournaments endpoint.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PlayerTournaments {
+    #[serde(default)]
+    pub finished: Vec<PlayerTournament>,
+    #[serde(default)]
+    pub in_progress: Vec<PlayerTournament>,
+    #[serde(default)]
+    pub registered: Vec<PlayerTournament>,
+}
+
+impl PlayerTournaments {
+    /// Every tournament of the player: registered first, then in progress, then finished.
+    pub fn all(self) -> Vec<PlayerTournament> {
+        let mut all = self.registered;
+        all.extend(self.in_progress);
+        all.extend(self.finished);
+        all
+    }
+}
+
+/// Profile of a player. The username is always in lowercase, while the URL of the profile has
+/// the casing the player chose.
+#[derive(Deserialize, Debug)]
+pub struct Profile {
+    pub username: String,
+    pub url: String,
+}
+
+impl Profile {
+    /// The username with the casing the player chose, taken from the URL of the profile.
+    pub fn display_username(&self) -> String {
+        match self.url.rsplit('/').next() {
+            Some(name) if name.eq_ignore_ascii_case(&self.username) => name.to_string(),
+            _ => self.username.clone(),
+        }
+    }
+}
+
+/// The (year, month) of an archive URL, taken from the two path segments after "games".
+fn archive_month(url: &str) -> Option<(u32, u32)> {
+    let url = Url::parse(url).ok()?;
+    let mut segments = url.path_segments()?.skip_while(|s| *s != "games").skip(1);
+    let year = segments.next()?.parse::<u32>().ok()?;
+    let month = segments.next()?.parse::<u32>().ok()?;
+    if segments.next().is_some() || !(1..=12).contains(&month) {
+        return None;
+    }
+    Some((year, month))
 }
 
 #[derive(Deserialize, Debug)]
@@ -178,6 +519,60 @@ pub struct GameArchives {
     pub archives: Vec<String>,
 }
 
+impl GameArchives {
+    /// The (year, month) of each archive, oldest first, from URLs like
+    /// https://api.chess.com/pub/player/{username}/games/{year}/{month}. URLs that can't be
+    /// parsed are skipped with a warning.
+    pub fn months(&self) -> Vec<(u32, u32)> {
+        self.archives
+            .iter()
+            .filter_map(|url| {
+                let month = archive_month(url);
+                if month.is_none() {
+                    log::warn!("Skipping archive with an unexpected URL: {}", url);
+                }
+                month
+            })
+            .collect()
+    }
+
+    /// Whether the archives are hidden by the player's privacy settings: chess.com lists no
+    /// archives for them even though their stats record games played.
+    pub fn is_restricted(&self, stats: &PlayerStats) -> bool {
+        self.archives.is_empty() && stats.recorded_games() > 0
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+pub struct Record {
+    #[serde(default)]
+    pub win: u64,
+    #[serde(default)]
+    pub loss: u64,
+    #[serde(default)]
+    pub draw: u64,
+}
+
+/// Stats of a player by category, e.g. chess_blitz or tactics. Only the categories of games
+/// have a record.
+#[derive(Deserialize, Debug)]
+pub struct PlayerStats {
+    #[serde(flatten)]
+    pub categories: HashMap<String, serde_json::Value>,
+}
+
+impl PlayerStats {
+    /// Number of games in the records of every category.
+    pub fn recorded_games(&self) -> u64 {
+        self.categories
+            .values()
+            .filter_map(|category| category.get("record"))
+            .filter_map(|record| serde_json::from_value::<Record>(record.clone()).ok())
+            .map(|record| record.win + record.loss + record.draw)
+            .sum()
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Arena {
     name: String,
@@ -204,6 +599,9 @@ pub struct PGNHeaders {
     #[serde(rename(deserialize = "FEN"))]
     pub fen: String,
     pub variant: Option<String>,
+    /// Round of tournament games, sent as either a number or a string.
+    #[serde(default, deserialize_with = "from_string_or_number")]
+    pub round: Option<String>,
 }
 
 impl PGNHeaders {
@@ -212,6 +610,12 @@ impl PGNHeaders {
         headers.push_str(&format!("[Event \"{}\"]\n", self.event));
         headers.push_str(&format!("[Site \"{}\"]\n", self.site));
         headers.push_str(&format!("[Date \"{}\"]\n", self.date));
+        // Games outside of tournaments have no round, written as "-" by convention
+        let round = match self.round.as_deref() {
+            Some(round) if !round.is_empty() => round,
+            _ => "-",
+        };
+        headers.push_str(&format!("[Round \"{}\"]\n", round));
         headers.push_str(&format!("[White \"{}\"]\n", self.white));
         headers.push_str(&format!("[Black \"{}\"]\n", self.black));
         headers.push_str(&format!("[Result \"{}\"]\n", self.result));
@@ -235,7 +639,7 @@ impl PGNHeaders {
 pub struct LiveGame {
     pub can_send_trophy: bool,
     pub changes_players_rating: i32,
-    pub color_of_winner: Option<String>,
+    pub color_of_winner: Option<Color>,
     pub id: u64,
     pub initial_setup: String,
     pub is_live_game: bool,
@@ -255,7 +659,7 @@ pub struct LiveGame {
     #[serde(deserialize_with = "from_ts")]
     pub end_time: DateTime<Utc>,
     pub arena: Option<Arena>,
-    pub turn_color: String,
+    pub turn_color: Color,
     pub r#type: String,
     pub type_name: String,
     pub allow_vacation: bool,
@@ -272,14 +676,133 @@ pub struct CallbackLiveGame {
 }
 
 impl CallbackLiveGame {
-    pub fn get_result_code(&self, color: &str) -> String {
-        let base_player = if self.players.top.color.as_str() == color {
+    /// Why a response of the live game callback isn't a live game, if it isn't one. The
+    /// endpoint also answers for the IDs of daily games, and with an error message for unknown
+    /// IDs, neither of which deserializes into a live game. Responses that aren't JSON are left
+    /// for deserialization to report.
+    pub fn not_live_reason(text: &str) -> Option<String> {
+        let response: serde_json::Value = serde_json::from_str(text).ok()?;
+        let game = match response.get("game") {
+            Some(game) if game.is_object() => game,
+            _ => {
+                return Some(match response.get("message").and_then(|m| m.as_str()) {
+                    Some(message) => format!("chess.com answered {:?}", message),
+                    None => "chess.com answered without a game".to_string(),
+                })
+            }
+        };
+        let is_live = game.get("isLiveGame").and_then(|l| l.as_bool());
+        if is_live == Some(false) || game.get("daysPerTurn").is_some() {
+            Some("it is a daily game".to_string())
+        } else {
+            None
+        }
+    }
+
+    /// The player playing with the given color. Either player may be on top, so players are
+    /// matched by their color rather than by is_white_on_bottom, which is only the board
+    /// orientation each player prefers.
+    pub fn player(&self, color: Color) -> &LivePlayer {
+        if self.players.top.color == color {
             &self.players.top
         } else {
             &self.players.bottom
+        }
+    }
+
+    /// Replay the moves from the given FEN instead of the one sent with the game, for games whose
+    /// stored FEN is wrong or malformed.
+    pub fn seed_position(&mut self, fen: &str) {
+        self.game.pgn_headers.fen = fen.to_string();
+        self.game.initial_setup = fen.to_string();
+    }
+
+    /// Decode the moves of the game in SAN, failing on the first move that can't be decoded.
+    pub fn decode_moves(&self) -> Result<Vec<String>, MoveDecodeError> {
+        let setup = parse_fen(&self.game.pgn_headers.fen).map_err(MoveDecodeError::InvalidSetup)?;
+        let mut position: Chess = setup
+            .position(CastlingMode::Standard)
+            .expect("the setup is a legal position");
+        let mut encoded: Vec<char> = self.game.move_list.chars().rev().collect();
+
+        let mut moves = Vec::new();
+        while let Some(m) = next_move(&mut encoded, &mut position)? {
+            moves.push(m);
+        }
+        Ok(moves)
+    }
+
+    /// Every move as it is decoded from the move list, to troubleshoot the decoder. Moves are
+    /// decoded up to the first that fails, which is returned along with them.
+    pub fn explain_moves(&self) -> (Vec<DecodedMove>, Option<MoveDecodeError>) {
+        let setup = match parse_fen(&self.game.pgn_headers.fen) {
+            Ok(setup) => setup,
+            Err(e) => return (Vec::new(), Some(MoveDecodeError::InvalidSetup(e))),
         };
+        let mut position: Chess = setup
+            .position(CastlingMode::Standard)
+            .expect("the setup is a legal position");
+        let mut encoded: Vec<char> = self.game.move_list.chars().rev().collect();
+
+        let mut moves = Vec::new();
+        loop {
+            match decode_next_move(&mut encoded, &mut position) {
+                Ok(Some(m)) => moves.push(m),
+                Ok(None) => return (moves, None),
+                Err(e) => return (moves, Some(e)),
+            }
+        }
+    }
+
+    /// The decoded moves, or none if any of them can't be decoded.
+    fn decoded_moves_or_none(&self) -> Vec<String> {
+        self.decode_moves().unwrap_or_else(|e| {
+            log::warn!("Could not decode the moves of game {}: {}", self.game.id, e);
+            Vec::new()
+        })
+    }
+
+    /// The chess.com timestamps of the moves, in whatever unit they were sent.
+    fn move_timestamps(&self) -> Vec<u32> {
+        self.game
+            .move_timestamps
+            .split(',')
+            .filter_map(|s| s.parse::<u32>().ok())
+            .collect()
+    }
+
+    /// Unit of the move timestamps, detected from the time control of the game. None if they
+    /// fit no unit.
+    pub fn timestamp_unit(&self) -> Option<TimestampUnit> {
+        TimestampUnit::detect(
+            &self.move_timestamps(),
+            self.game.base_time_1.max(0) as u32,
+            self.game.time_increment_1.max(0) as u32,
+        )
+    }
+
+    /// Decode the moves of the game in SAN, each with the chess.com timestamp of the clock of the
+    /// player who made it, in tenths of a second. Timestamps sent in other units are rescaled,
+    /// and moves have no timestamp when their unit can't be told.
+    pub fn timed_moves(&self) -> Vec<(String, Option<u32>)> {
+        let unit = self.timestamp_unit();
+        if unit.is_none() {
+            log::warn!(
+                "The move timestamps of game {} don't fit its time control, leaving out its clocks",
+                self.game.id
+            );
+        }
+        self.decoded_moves_or_none()
+            .into_iter()
+            .zip(self.move_timestamps())
+            .map(|(m, ts)| (m, unit.map(|unit| unit.to_tenths(ts))))
+            .collect()
+    }
 
-        if let Some(c) = &self.game.color_of_winner {
+    pub fn get_result_code(&self, color: Color) -> String {
+        let base_player = self.player(color);
+
+        if let Some(c) = self.game.color_of_winner {
             // Somebody won and somebody lost
             if c == color {
                 "win".to_string()
@@ -313,29 +836,15 @@ impl CallbackLiveGame {
 impl ChessGame for CallbackLiveGame {
     type PlayerType = LivePlayer;
 
-    fn to_json_pretty(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string_pretty(self)
-    }
-
-    fn to_json(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string(self)
-    }
-
     fn pgn(&mut self) -> String {
-        let setup: Fen = self.game.pgn_headers.fen.parse().unwrap();
-        let mut position: Chess = setup.position(CastlingMode::Standard).unwrap();
-
-        let mut counter = 1;
+        // Moves are numbered from the setup position, which may have black to move and start
+        // past the first move. Games whose setup can't be read have no decoded moves, so the
+        // numbering doesn't matter for them.
+        let (mut counter, mut white_to_move) = match parse_fen(&self.game.pgn_headers.fen) {
+            Ok(setup) => (setup.fullmoves.get(), setup.turn.is_white()),
+            Err(_) => (1, true),
+        };
         let mut pgn = String::new();
-        // This next loop should probably be handled by some iter implemenation
-        let mut moves: Vec<char> = self.game.move_list.chars().rev().collect();
-        let mut timestamps: Vec<u32> = self
-            .game
-            .move_timestamps
-            .split(",")
-            .map(|s| s.parse::<u32>().unwrap())
-            .collect();
-        timestamps.reverse();
 
         pgn.push_str(
             &self
@@ -343,33 +852,28 @@ impl ChessGame for CallbackLiveGame {
                 .pgn_headers
                 .to_pgn_string(&self.game.id.to_string()),
         );
-        loop {
-            let m = next_move(&mut moves, &mut position);
-            if m.is_none() {
-                break;
-            }
+        for (m, ts) in self.timed_moves() {
+            let clock_comment = match ts.map(time_from_timestamp) {
+                Some((hours, minutes, secs, tenth_secs)) => format!(
+                    " {{[%clk {}:{:02}:{:02}.{:01}]}} ",
+                    hours, minutes, secs, tenth_secs
+                ),
+                None => " ".to_string(),
+            };
 
-            let ts = timestamps.pop().unwrap();
-            let (hours, minutes, secs, tenth_secs) = time_from_timestamp(ts);
-            let clock_comment = format!(
-                " {{[%clk {}:{:02}:{:02}.{:01}]}} ",
-                hours, minutes, secs, tenth_secs
-            );
-
-            // Next position.turn() returns the next player to move, not the player that made
-            // the current move m
-            if position.turn() == Color::White {
+            if white_to_move {
                 pgn.push_str(&counter.to_string());
-                pgn.push_str("... ");
-                pgn.push_str(&m.unwrap());
+                pgn.push_str(". ");
+                pgn.push_str(&m);
                 pgn.push_str(&clock_comment);
-                counter += 1;
             } else {
                 pgn.push_str(&counter.to_string());
-                pgn.push_str(". ");
-                pgn.push_str(&m.unwrap());
+                pgn.push_str("... ");
+                pgn.push_str(&m);
                 pgn.push_str(&clock_comment);
+                counter += 1;
             }
+            white_to_move = !white_to_move;
         }
 
         pgn.push_str(&self.game.pgn_headers.result);
@@ -378,17 +882,11 @@ impl ChessGame for CallbackLiveGame {
     }
 
     fn white(&mut self) -> Self::PlayerType {
-        match self.players.top.color.as_str() {
-            "white" => self.players.top.clone(),
-            _ => self.players.bottom.clone(),
-        }
+        self.player(Color::White).clone()
     }
 
     fn black(&mut self) -> Self::PlayerType {
-        match self.players.top.color.as_str() {
-            "black" => self.players.top.clone(),
-            _ => self.players.bottom.clone(),
-        }
+        self.player(Color::Black).clone()
     }
 
     fn url(&self) -> String {
@@ -398,6 +896,152 @@ impl ChessGame for CallbackLiveGame {
     fn end_time(&self) -> DateTime<Utc> {
         self.game.end_time.clone()
     }
+
+    fn start_time(&self) -> Option<DateTime<Utc>> {
+        // The callback only reports when the game ended
+        None
+    }
+
+    fn eco(&self) -> Option<String> {
+        match self.game.pgn_headers.eco.as_str() {
+            "" => None,
+            eco => Some(eco.to_string()),
+        }
+    }
+
+    fn opening(&self) -> Option<String> {
+        self.eco()
+            .and_then(|eco| eco_name(&eco))
+            .map(|name| name.to_string())
+    }
+
+    fn moves_with_evals(&self) -> Option<String> {
+        None
+    }
+
+    fn speed(&self) -> Speed {
+        // Live game times are in tenths of a second
+        Speed::from_seconds(
+            self.game.base_time_1 as u32 / 10,
+            self.game.time_increment_1 as u32 / 10,
+        )
+    }
+
+    fn time_class(&self) -> Option<TimeClass> {
+        // Live game times are in tenths of a second
+        TimeClass::from_base_seconds(self.game.base_time_1 as u32 / 10)
+    }
+
+    fn variant(&self) -> String {
+        match self.game.pgn_headers.variant.as_deref() {
+            None | Some("") => "standard".to_string(),
+            Some(variant) => variant.to_lowercase(),
+        }
+    }
+
+    fn moves(&mut self) -> Vec<String> {
+        self.decoded_moves_or_none()
+    }
+
+    fn uci_moves(&mut self) -> Result<Vec<String>, MoveDecodeError> {
+        // Live games may start from a setup position
+        let setup = parse_fen(&self.game.pgn_headers.fen).map_err(MoveDecodeError::InvalidSetup)?;
+        let position: Chess = setup
+            .position(CastlingMode::Standard)
+            .expect("the setup is a legal position");
+        san_to_uci(position, &self.decode_moves()?)
+    }
+
+    fn tags(&mut self) -> BTreeMap<String, String> {
+        // The tags come from the parsed headers, so the moves don't need to be decoded
+        pgn_tags(
+            &self
+                .game
+                .pgn_headers
+                .to_pgn_string(&self.game.id.to_string()),
+        )
+    }
+
+    fn fen_at(&mut self, ply: Option<usize>) -> Result<String, MoveDecodeError> {
+        let setup = parse_fen(&self.game.pgn_headers.fen).map_err(MoveDecodeError::InvalidSetup)?;
+        let position: Chess = setup
+            .position(CastlingMode::Standard)
+            .expect("the setup is a legal position");
+        fen_after(position, &self.decode_moves()?, ply)
+    }
+
+    fn result_for(&mut self, username: &str) -> Option<Outcome> {
+        let username = username.to_lowercase();
+        let color = [&self.players.top, &self.players.bottom]
+            .iter()
+            .find(|p| p.username.to_lowercase() == username)
+            .map(|p| p.color)?;
+        Outcome::from_result_code(&self.get_result_code(color))
+    }
+
+    fn rating_change(&mut self, username: &str) -> Option<i32> {
+        if !self.game.is_rated {
+            return None;
+        }
+        let username = username.to_lowercase();
+        let color = [&self.players.top, &self.players.bottom]
+            .iter()
+            .find(|p| p.username.to_lowercase() == username)
+            .map(|p| p.color)?;
+        match color {
+            Color::White => self.game.rating_change_white,
+            Color::Black => self.game.rating_change_black,
+        }
+    }
+
+    fn clocks(&mut self) -> Vec<MoveClock> {
+        let unit = self.timestamp_unit();
+        self.timed_moves()
+            .into_iter()
+            .enumerate()
+            .map(|(i, (san, ts))| MoveClock {
+                ply: i + 1,
+                san,
+                // Timestamps are rescaled to tenths of a second
+                clock_seconds: ts.map(|ts| f64::from(ts) / 10.0),
+                timestamp_unit: unit,
+            })
+            .collect()
+    }
+
+    fn is_checkmate(&self) -> bool {
+        self.game.is_checkmate
+    }
+
+    fn is_stalemate(&self) -> bool {
+        self.game.is_stalemate
+    }
+
+    fn termination(&self) -> Termination {
+        if self.game.is_checkmate {
+            Termination::Checkmate
+        } else if self.game.is_stalemate {
+            Termination::Stalemate
+        } else {
+            chess_com_message_termination(&self.game.result_message)
+        }
+    }
+}
+
+/// Deserialize an optional value that may be sent as a string or as a number into a string.
+fn from_string_or_number<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<serde_json::Value>::deserialize(deserializer)? {
+        Some(serde_json::Value::String(s)) => Ok(Some(s)),
+        Some(serde_json::Value::Number(n)) => Ok(Some(n.to_string())),
+        Some(serde_json::Value::Null) | None => Ok(None),
+        Some(other) => Err(de::Error::custom(format!(
+            "expected a string or a number, got {}",
+            other
+        ))),
+    }
 }
 
 /// Turn a chess.com timestamp into hours, minutes, seconds, and tenths of a second
@@ -418,6 +1062,635 @@ impl DisplayableChessGame for CallbackLiveGame {}
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::api::normalized::NormalizedGame;
+
+    fn live_player(username: &str, color: &str) -> String {
+        format!(
+            r#"{{
+                "username": "{}", "rating": 1500, "id": 1, "isContentHidden": false,
+                "avatarUrl": "", "countryId": 1, "isEnabled": true, "canWinOnTime": true,
+                "chessTitle": null, "color": "{}", "countryName": "", "defaultTab": 1,
+                "hasMovedAtLeastOnce": true, "isDrawable": false, "isOnline": false,
+                "isInLiveChess": false, "isTouchMove": false, "isVacation": false,
+                "isWhiteOnBottom": true, "lastLoginDate": null, "location": null,
+                "membershipLevel": null, "membershipCode": null, "memberSince": null,
+                "postMoveAction": "", "turnTimeRemaining": "", "flairCode": "",
+                "vacationRemaining": "", "gamesInProgress": 0
+            }}"#,
+            username, color
+        )
+    }
+
+    fn live_game(color_of_winner: Option<&str>, result_message: &str) -> CallbackLiveGame {
+        let color_of_winner = match color_of_winner {
+            Some(c) => format!(r#""{}""#, c),
+            None => "null".to_string(),
+        };
+        let json = format!(
+            r#"{{
+                "players": {{"top": {}, "bottom": {}}},
+                "game": {{
+                    "canSendTrophy": false, "changesPlayersRating": 1,
+                    "colorOfWinner": {}, "id": 1234567890, "initialSetup": "",
+                    "isLiveGame": true, "isAbortable": false, "isAnalyzable": true,
+                    "isCheckmate": false, "isStalemate": false, "isFinished": true,
+                    "isRated": true, "isResignable": false, "lastMove": "", "moveList": "",
+                    "plyCount": 0, "ratingChangeWhite": null, "ratingChangeBlack": null,
+                    "resultMessage": "{}", "endTime": 1612540800, "arena": null,
+                    "turnColor": "white", "type": "chess", "typeName": "Standard Chess",
+                    "allowVacation": false, "moveTimestamps": "", "baseTime1": 1800,
+                    "timeIncrement1": 0,
+                    "pgnHeaders": {{
+                        "Event": "Live Chess", "Site": "Chess.com", "Date": "2021.02.05",
+                        "White": "Alice", "Black": "Bob", "Result": "*", "ECO": "A00",
+                        "WhiteElo": 1500, "BlackElo": 1500, "TimeControl": "180",
+                        "EndTime": "", "Termination": "", "SetUp": "1",
+                        "FEN": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+                    }}
+                }}
+            }}"#,
+            live_player("Bob", "black"),
+            live_player("Alice", "white"),
+            color_of_winner,
+            result_message
+        );
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_archive_months_skip_malformed_urls() {
+        let archives = GameArchives {
+            archives: vec![
+                "https://api.chess.com/pub/player/alice/games/2020/12".to_string(),
+                "https://api.chess.com/pub/player/alice/games/2021".to_string(),
+                "https://api.chess.com/pub/player/alice/games/2021/jan".to_string(),
+                "https://api.chess.com/pub/player/alice/games/2021/13".to_string(),
+                "not a url".to_string(),
+                "https://api.chess.com/v2/player/alice/games/2021/02".to_string(),
+            ],
+        };
+        assert_eq!(archives.months(), vec![(2020, 12), (2021, 2)]);
+    }
+
+    #[test]
+    fn test_result_for() {
+        let game_json = r#"{
+            "white": {"username": "Alice", "rating": 1500, "result": "win", "@id": ""},
+            "black": {"username": "Bob", "rating": 1500, "result": "resigned", "@id": ""},
+            "url": "https://www.chess.com/game/live/1234567890",
+            "fen": "",
+            "pgn": "",
+            "end_time": 1612540800,
+            "time_control": "180",
+            "rules": "chess",
+            "eco": null,
+            "tournament": null,
+            "match": null
+        }"#;
+        let mut game: Game = serde_json::from_str(game_json).unwrap();
+        assert_eq!(game.result_for("alice"), Some(Outcome::Win));
+        assert_eq!(game.result_for("BOB"), Some(Outcome::Loss));
+        assert_eq!(game.result_for("carol"), None);
+
+        game.white.result = "agreed".to_string();
+        game.black.result = "agreed".to_string();
+        assert_eq!(game.result_for("Bob"), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate() {
+        let game_json = r#"{
+            "white": {"username": "Alice", "rating": 1500, "result": "checkmated", "@id": ""},
+            "black": {"username": "Bob", "rating": 1500, "result": "win", "@id": ""},
+            "url": "https://www.chess.com/game/live/1234567890",
+            "fen": "",
+            "pgn": "",
+            "end_time": 1612540800,
+            "time_control": "180",
+            "rules": "chess",
+            "eco": null,
+            "tournament": null,
+            "match": null
+        }"#;
+        let mut game: Game = serde_json::from_str(game_json).unwrap();
+        assert!(game.is_checkmate());
+        assert!(!game.is_stalemate());
+
+        game.white.result = "stalemate".to_string();
+        game.black.result = "stalemate".to_string();
+        assert!(!game.is_checkmate());
+        assert!(game.is_stalemate());
+
+        let mut live = live_game(Some("white"), "Alice won by checkmate");
+        live.game.is_checkmate = true;
+        assert!(live.is_checkmate());
+        assert!(!live.is_stalemate());
+
+        let mut live = live_game(None, "Game drawn by stalemate");
+        live.game.is_stalemate = true;
+        assert!(!live.is_checkmate());
+        assert!(live.is_stalemate());
+    }
+
+    #[test]
+    fn test_daily_game_is_not_live() {
+        let daily = r#"{
+            "players": {"top": {"username": "Bob"}, "bottom": {"username": "Alice"}},
+            "game": {
+                "id": 123456789, "isLiveGame": false, "daysPerTurn": 3,
+                "moveList": "mC0K", "pgnHeaders": {"Event": "Let's Play!"}
+            }
+        }"#;
+
+        assert!(serde_json::from_str::<CallbackLiveGame>(daily).is_err());
+        assert_eq!(
+            CallbackLiveGame::not_live_reason(daily),
+            Some("it is a daily game".to_string())
+        );
+        assert_eq!(
+            CallbackLiveGame::not_live_reason(r#"{"message": "Game not found"}"#),
+            Some("chess.com answered \"Game not found\"".to_string())
+        );
+        assert_eq!(CallbackLiveGame::not_live_reason("not json"), None);
+
+        let live = serde_json::to_string(&live_game(Some("white"), "Alice won")).unwrap();
+        assert_eq!(CallbackLiveGame::not_live_reason(&live), None);
+    }
+
+    #[test]
+    fn test_live_players_by_color() {
+        let mut game = live_game(Some("white"), "Alice won by checkmate");
+        assert_eq!(game.white().username, "Alice");
+        assert_eq!(game.black().username, "Bob");
+        assert_eq!(game.player(Color::Black).color, Color::Black);
+        assert_eq!(game.get_result_code(Color::White), "win");
+        assert_eq!(game.get_result_code(Color::Black), "lose");
+    }
+
+    #[test]
+    fn test_round_tag() {
+        let mut game = live_game(Some("white"), "Alice won by resignation");
+        assert_eq!(game.game.pgn_headers.round, None);
+        assert!(game
+            .pgn()
+            .contains("[Date \"2021.02.05\"]\n[Round \"-\"]\n"));
+
+        let headers: PGNHeaders = serde_json::from_str(
+            r#"{
+                "Event": "Titled Tuesday", "Site": "Chess.com", "Date": "2021.02.05",
+                "Round": 3, "White": "Alice", "Black": "Bob", "Result": "*", "ECO": "A00",
+                "WhiteElo": 1500, "BlackElo": 1500, "TimeControl": "180", "EndTime": "",
+                "Termination": "", "SetUp": "1",
+                "FEN": "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(headers.round, Some("3".to_string()));
+        game.game.pgn_headers = headers;
+        assert!(game.pgn().contains("[Round \"3\"]"));
+    }
+
+    #[test]
+    fn test_live_players_with_white_on_top() {
+        let mut game = live_game(Some("black"), "Bob won on time");
+        std::mem::swap(&mut game.players.top, &mut game.players.bottom);
+        game.players.top.is_white_on_bottom = false;
+        game.players.bottom.is_white_on_bottom = false;
+        game.players.top.turn_time_remaining = "Out of time".to_string();
+
+        assert_eq!(game.players.top.color, Color::White);
+        assert_eq!(game.white().username, "Alice");
+        assert_eq!(game.black().username, "Bob");
+        assert_eq!(game.get_result_code(Color::White), "timeout");
+        assert_eq!(game.get_result_code(Color::Black), "win");
+        assert_eq!(game.result_for("alice"), Some(Outcome::Loss));
+        assert_eq!(game.result_for("bob"), Some(Outcome::Win));
+    }
+
+    #[test]
+    fn test_live_result_code_with_black_on_top() {
+        let mut game = live_game(Some("white"), "Alice won on time");
+        game.players.top.turn_time_remaining = "Out of time".to_string();
+
+        assert_eq!(game.players.top.color, Color::Black);
+        assert_eq!(game.get_result_code(Color::White), "win");
+        assert_eq!(game.get_result_code(Color::Black), "timeout");
+        assert_eq!(game.result_for("alice"), Some(Outcome::Win));
+        assert_eq!(game.result_for("bob"), Some(Outcome::Loss));
+    }
+
+    #[test]
+    fn test_archive_and_live_game_normalize_the_same() {
+        let archive_json = r#"{
+            "white": {"username": "Alice", "rating": 1500, "result": "win", "@id": ""},
+            "black": {"username": "Bob", "rating": 1500, "result": "resigned", "@id": ""},
+            "url": "https://www.chess.com/game/live/1234567890",
+            "fen": "",
+            "pgn": "[Event \"Live Chess\"]\n[Result \"1-0\"]\n[ECO \"C20\"]\n\n1. e4 {[%clk 0:02:59.9]} 1... e5 {[%clk 0:02:58.1]} 1-0",
+            "end_time": 1612540800,
+            "time_control": "180",
+            "rules": "chess",
+            "eco": "https://www.chess.com/openings/Kings-Pawn-Opening",
+            "tournament": null,
+            "match": null
+        }"#;
+        let archive: Game = serde_json::from_str(archive_json).unwrap();
+
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        live.game.move_list = "mC0K".to_string();
+        live.game.pgn_headers.eco = "C20".to_string();
+        live.game.pgn_headers.result = "1-0".to_string();
+
+        let from_archive = NormalizedGame::from(&archive);
+        let from_live = NormalizedGame::from(&live);
+        assert_eq!(from_archive, from_live);
+        assert_eq!(from_archive.pgn, "1. e4 e5 1-0");
+        assert_eq!(from_archive.white.result, Some("win".to_string()));
+        assert_eq!(from_archive.black.result, Some("resigned".to_string()));
+        assert_eq!(from_archive.opening, Some("King's Pawn Game".to_string()));
+    }
+
+    #[test]
+    fn test_live_clocks_from_move_timestamps() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        live.game.move_list = "mC0K".to_string();
+        live.game.move_timestamps = "1799,1781".to_string();
+        live.game.pgn_headers.result = "1-0".to_string();
+
+        let expected = vec![
+            MoveClock {
+                ply: 1,
+                san: "e4".to_string(),
+                clock_seconds: Some(179.9),
+                timestamp_unit: Some(TimestampUnit::Tenths),
+            },
+            MoveClock {
+                ply: 2,
+                san: "e5".to_string(),
+                clock_seconds: Some(178.1),
+                timestamp_unit: Some(TimestampUnit::Tenths),
+            },
+        ];
+        assert_eq!(live.clocks(), expected);
+        assert!(live
+            .pgn()
+            .ends_with("1. e4 {[%clk 0:02:59.9]} 1... e5 {[%clk 0:02:58.1]} 1-0"));
+        // The clocks written to the PGN are read back the same
+        let read_back: Vec<_> = expected
+            .into_iter()
+            .map(|clock| MoveClock {
+                timestamp_unit: None,
+                ..clock
+            })
+            .collect();
+        assert_eq!(MoveClock::from_pgn(&live.pgn()), read_back);
+    }
+
+    fn clock_seconds(live: &mut CallbackLiveGame) -> Vec<Option<f64>> {
+        live.clocks().into_iter().map(|c| c.clock_seconds).collect()
+    }
+
+    #[test]
+    fn test_timestamp_unit_of_time_controls() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        live.game.move_list = "mC0K".to_string();
+        live.game.pgn_headers.result = "1-0".to_string();
+
+        // 3+0 in tenths of a second
+        live.game.move_timestamps = "1799,1781".to_string();
+        assert_eq!(live.timestamp_unit(), Some(TimestampUnit::Tenths));
+
+        // 3+0 in milliseconds
+        live.game.move_timestamps = "179900,178100".to_string();
+        assert_eq!(live.timestamp_unit(), Some(TimestampUnit::Milliseconds));
+        assert_eq!(clock_seconds(&mut live), vec![Some(179.9), Some(178.1)]);
+
+        // 10+5 in seconds, with the increment pushing a clock over the base time
+        live.game.base_time_1 = 6000;
+        live.game.time_increment_1 = 50;
+        live.game.move_timestamps = "603,598".to_string();
+        assert_eq!(live.timestamp_unit(), Some(TimestampUnit::Seconds));
+        assert_eq!(clock_seconds(&mut live), vec![Some(603.0), Some(598.0)]);
+        assert!(live
+            .pgn()
+            .ends_with("1. e4 {[%clk 0:10:03.0]} 1... e5 {[%clk 0:09:58.0]} 1-0"));
+
+        // 1+0 in tenths of a second
+        live.game.base_time_1 = 600;
+        live.game.time_increment_1 = 0;
+        live.game.move_timestamps = "598,591".to_string();
+        assert_eq!(live.timestamp_unit(), Some(TimestampUnit::Tenths));
+    }
+
+    #[test]
+    fn test_implausible_timestamps_leave_out_clocks() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        live.game.move_list = "mC0K".to_string();
+        live.game.pgn_headers.result = "1-0".to_string();
+        // Over the base time of 3+0 in any unit
+        live.game.move_timestamps = "90000000,1781".to_string();
+
+        assert_eq!(live.timestamp_unit(), None);
+        assert_eq!(clock_seconds(&mut live), vec![None, None]);
+        assert!(live.pgn().ends_with("1. e4 1... e5 1-0"));
+    }
+
+    #[test]
+    fn test_seed_position_replaces_a_malformed_fen() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        live.game.move_list = "mC0K".to_string();
+        live.game.pgn_headers.fen = "not a fen".to_string();
+        assert!(live.decode_moves().is_err());
+
+        live.seed_position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(live.decode_moves().unwrap(), vec!["e4", "e5"]);
+        assert_eq!(live.game.initial_setup, live.game.pgn_headers.fen);
+    }
+
+    #[test]
+    fn test_live_uci_moves_from_setup() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        live.game.move_list = "mC0K".to_string();
+        assert_eq!(live.uci_moves().unwrap(), vec!["e2e4", "e7e5"]);
+
+        // After 1. e4, black moves first
+        live.seed_position("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        live.game.move_list = "0K".to_string();
+        assert_eq!(live.uci_moves().unwrap(), vec!["e7e5"]);
+    }
+
+    #[test]
+    fn test_live_pgn_move_numbers_from_setup() {
+        let move_numbers = |pgn: &str| -> Vec<String> {
+            pgn.lines()
+                .filter(|l| !l.starts_with('['))
+                .flat_map(|l| l.split_whitespace())
+                .filter(|t| t.ends_with('.'))
+                .map(|t| t.to_string())
+                .collect()
+        };
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+
+        // 1. e4 e5 2. Nf3 Nc6
+        live.game.move_list = "mC0Kgv5Q".to_string();
+        live.game.move_timestamps = "1800,1800,1790,1780".to_string();
+        assert_eq!(move_numbers(&live.pgn()), vec!["1.", "1...", "2.", "2..."]);
+
+        // After 1. e4, black moves first: 1... e5 2. Nf3 2... Nc6
+        live.seed_position("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1");
+        live.game.move_list = "0Kgv5Q".to_string();
+        assert_eq!(move_numbers(&live.pgn()), vec!["1...", "2.", "2..."]);
+
+        // Setups past the first move keep their move number
+        live.seed_position("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 12");
+        assert_eq!(move_numbers(&live.pgn()), vec!["12...", "13.", "13..."]);
+    }
+
+    #[test]
+    fn test_explain_pgn() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        // 1. e4 d5 2. exd5
+        live.game.move_list = "mCZJCJ".to_string();
+        assert_eq!(
+            crate::displayer::explain_pgn(&live),
+            "   1  mC  e2-e4  e4\n   2  ZJ  d7-d5  d5\n   3  CJ  e4-d5  exd5    captures pawn"
+        );
+
+        // White's knight can't move on black's turn
+        live.game.move_list = "mCgv".to_string();
+        let (moves, error) = live.explain_moves();
+        assert_eq!(moves.len(), 1);
+        assert!(error.is_some());
+    }
+
+    #[test]
+    fn test_live_headers_output() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        let headers = crate::displayer::GameDisplayer::from_str(&mut live, "headers")
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            headers,
+            "[Event \"Live Chess\"]\n\
+             [Site \"Chess.com\"]\n\
+             [Date \"2021.02.05\"]\n\
+             [Round \"-\"]\n\
+             [White \"Alice\"]\n\
+             [Black \"Bob\"]\n\
+             [Result \"*\"]\n\
+             [ECO \"A00\"]\n\
+             [TimeControl \"180\"]\n\
+             [Link \"https://www.chess.com/game/live/1234567890\"]"
+        );
+    }
+
+    #[test]
+    fn test_live_tags_from_headers() {
+        let mut live = live_game(Some("white"), "Alice won by resignation");
+        let tags = live.tags();
+        let headers = live
+            .game
+            .pgn_headers
+            .to_pgn_string(&live.game.id.to_string());
+        assert_eq!(
+            tags.len(),
+            headers.lines().filter(|l| l.starts_with('[')).count()
+        );
+        assert_eq!(tags["White"], live.game.pgn_headers.white);
+        assert_eq!(tags["Result"], live.game.pgn_headers.result);
+        assert_eq!(
+            tags["Link"],
+            format!("https://www.chess.com/game/live/{}", live.game.id)
+        );
+    }
+
+    #[test]
+    fn test_profile_urls() {
+        let player: Player = serde_json::from_str(
+            r#"{"username": "Hikaru", "rating": 3200, "result": "win",
+                "@id": "https://api.chess.com/pub/player/hikaru"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            player.url(),
+            Some("https://www.chess.com/member/Hikaru".to_string())
+        );
+        assert_eq!(player.profile_url(), player.url());
+        assert_eq!(
+            player.api_url(),
+            Some("https://api.chess.com/pub/player/hikaru".to_string())
+        );
+
+        let live = live_game(Some("white"), "Alice won by resignation");
+        assert_eq!(
+            live.players.top.profile_url(),
+            Some(format!(
+                "https://www.chess.com/member/{}",
+                live.players.top.username
+            ))
+        );
+    }
+
+    #[test]
+    fn test_live_result_for() {
+        let mut game = live_game(Some("black"), "Bob won by resignation");
+        assert_eq!(game.result_for("bob"), Some(Outcome::Win));
+        assert_eq!(game.result_for("Alice"), Some(Outcome::Loss));
+        assert_eq!(game.result_for("carol"), None);
+
+        let mut game = live_game(None, "Game drawn by agreement");
+        assert_eq!(game.result_for("alice"), Some(Outcome::Draw));
+        assert_eq!(game.result_for("bob"), Some(Outcome::Draw));
+    }
+
+    #[test]
+    fn test_player_status() {
+        let json = r#"{
+            "username": "Hikaru", "id": 15448422, "isOnline": true,
+            "isInLiveChess": true, "avatarUrl": "", "chessTitle": "GM"
+        }"#;
+        let status: PlayerStatus = serde_json::from_str(json).unwrap();
+        assert_eq!(status.is_online, Some(true));
+        assert_eq!(status.is_in_live_chess, Some(true));
+        assert_eq!(status.describe(), "Hikaru is online, playing live chess");
+
+        let json = r#"{"username": "a_player", "is_online": false}"#;
+        let status: PlayerStatus = serde_json::from_str(json).unwrap();
+        assert_eq!(status.describe(), "a_player is offline");
+
+        let status: PlayerStatus = serde_json::from_str(r#"{"username": "a_player"}"#).unwrap();
+        assert_eq!(status.describe(), "The status of a_player isn't available");
+    }
+
+    #[test]
+    fn test_player_tournaments() {
+        let json = r#"{
+            "finished": [{
+                "url": "https://www.chess.com/tournament/live/titled-tuesday-blitz-january-02-2024-4528654",
+                "@id": "https://api.chess.com/pub/tournament/titled-tuesday-blitz-january-02-2024-4528654",
+                "wins": 7, "losses": 2, "draws": 2, "points_awarded": 8,
+                "placement": 12, "status": "winner", "total_players": 501
+            }],
+            "in_progress": [],
+            "registered": [{
+                "url": "https://www.chess.com/tournament/live/arena-kings",
+                "@id": "https://api.chess.com/pub/tournament/arena-kings",
+                "status": "registered"
+            }]
+        }"#;
+        let tournaments: PlayerTournaments = serde_json::from_str(json).unwrap();
+        let all = tournaments.all();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].name(), "arena kings");
+        assert_eq!(all[0].placement, None);
+        assert_eq!(all[1].id(), "titled-tuesday-blitz-january-02-2024-4528654");
+        assert_eq!(all[1].name(), "titled tuesday blitz january 02 2024");
+        assert_eq!(all[1].placement, Some(12));
+    }
+
+    #[test]
+    fn test_tournament_round_standings() {
+        let json = r#"{
+            "name": "Titled Tuesday",
+            "url": "https://www.chess.com/tournament/live/titled-tuesday-4528654",
+            "status": "finished",
+            "finish_time": 1704229200,
+            "players": [{"username": "hikaru", "status": "winner"}],
+            "rounds": ["https://api.chess.com/pub/tournament/titled-tuesday-4528654/1"]
+        }"#;
+        let tournament: Tournament = serde_json::from_str(json).unwrap();
+        assert_eq!(tournament.rounds.len(), 1);
+        assert!(tournament.finish_time.is_some());
+
+        let json = r#"{
+            "players": [
+                {"username": "a_player", "points": 6.5, "is_advancing": false},
+                {"username": "hikaru", "points": 9, "is_advancing": false}
+            ],
+            "groups": ["https://api.chess.com/pub/tournament/titled-tuesday-4528654/1/1"]
+        }"#;
+        let round: TournamentRound = serde_json::from_str(json).unwrap();
+        let standings: Vec<&str> = round
+            .standings()
+            .iter()
+            .map(|s| s.username.as_str())
+            .collect();
+        assert_eq!(standings, vec!["hikaru", "a_player"]);
+    }
+
+    #[test]
+    fn test_profile_display_username() {
+        let profile: Profile = serde_json::from_str(
+            r#"{"username": "hikaru", "url": "https://www.chess.com/member/Hikaru"}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.display_username(), "Hikaru");
+
+        let profile: Profile = serde_json::from_str(
+            r#"{"username": "hikaru", "url": "https://www.chess.com/member/"}"#,
+        )
+        .unwrap();
+        assert_eq!(profile.display_username(), "hikaru");
+    }
+
+    #[test]
+    fn test_restricted_archives() {
+        let stats: PlayerStats = serde_json::from_str(
+            r#"{
+                "chess_blitz": {
+                    "last": {"rating": 1500, "date": 1612540800, "rd": 45},
+                    "record": {"win": 10, "loss": 5, "draw": 1}
+                },
+                "fide": 0,
+                "tactics": {"highest": {"rating": 1800, "date": 1612540800}}
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(stats.recorded_games(), 16);
+
+        let empty: GameArchives = serde_json::from_str(r#"{"archives": []}"#).unwrap();
+        assert!(empty.is_restricted(&stats));
+
+        let archives: GameArchives = serde_json::from_str(
+            r#"{"archives": ["https://api.chess.com/pub/player/erik/games/2021/01"]}"#,
+        )
+        .unwrap();
+        assert!(!archives.is_restricted(&stats));
+
+        // A new player with no games at all isn't restricted
+        let new_player: PlayerStats = serde_json::from_str(r#"{"fide": 0}"#).unwrap();
+        assert!(!empty.is_restricted(&new_player));
+    }
+
+    #[test]
+    fn test_ongoing_games_deserialization() {
+        let payload = r#"{
+            "games": [
+                {
+                    "url": "https://www.chess.com/game/daily/123456789",
+                    "move_by": 1612800000,
+                    "pgn": "[Event \"Let's Play!\"]\n\n1. e4 *",
+                    "time_control": "1/259200",
+                    "last_activity": 1612540800,
+                    "rated": true,
+                    "turn": "black",
+                    "fen": "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+                    "start_time": 1612540800,
+                    "time_class": "daily",
+                    "rules": "chess",
+                    "white": "https://api.chess.com/pub/player/erik",
+                    "black": "https://api.chess.com/pub/player/hikaru"
+                }
+            ]
+        }"#;
+        let ongoing: OngoingGames = serde_json::from_str(payload).unwrap();
+        let game = &ongoing.games[0];
+
+        assert_eq!(game.white_name(), "erik");
+        assert_eq!(game.black_name(), "hikaru");
+        assert_eq!(game.turn, Color::Black);
+        assert_eq!(game.move_by.timestamp(), 1612800000);
+    }
 
     #[test]
     fn test_time_from_timestamp() {
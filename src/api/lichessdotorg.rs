@@ -1,10 +1,13 @@
-use std::fmt::Debug;
+use std::fmt::{self, Debug};
 
-use super::{ChessGame, ChessPlayer, DisplayableChessGame};
-use chrono::serde::ts_seconds::deserialize as from_ts;
-use chrono::{DateTime, Utc};
+use super::timestamp::deserialize_millis as from_ts_millis;
+use super::{
+    ChessGame, ChessPlayer, Color, DisplayableChessGame, MoveClock, Outcome, Speed, TimeClass,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
-use serde_json;
+
+use crate::utils::{annotate_moves, eco_name, lichess_termination, Termination};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all(deserialize = "camelCase"))]
@@ -14,28 +17,39 @@ pub struct Game {
     pub variant: String,
     pub speed: String,
     pub perf: String,
-    #[serde(deserialize_with = "from_ts")]
+    #[serde(deserialize_with = "from_ts_millis")]
+    #[serde(alias = "created_at")]
     pub created_at: DateTime<Utc>,
-    #[serde(deserialize_with = "from_ts")]
+    #[serde(deserialize_with = "from_ts_millis")]
+    #[serde(alias = "last_move_at")]
     pub last_move_at: DateTime<Utc>,
     pub status: String,
+    pub winner: Option<Color>,
     pub players: Players,
     pub opening: Option<Opening>,
     pub pgn: String,
-    pub clock: Clock,
+    // Correspondence and unlimited games have no clock
+    pub clock: Option<Clock>,
     pub moves: String,
+    pub analysis: Option<Vec<Analysis>>,
 }
 
-impl ChessGame for Game {
-    type PlayerType = Player;
-
-    fn to_json_pretty(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string_pretty(self)
+impl Game {
+    /// Outcome of the game for the player with the given color, if the game finished.
+    pub fn outcome_for(&self, color: Color) -> Option<Outcome> {
+        match self.status.as_str() {
+            "created" | "started" | "aborted" | "noStart" | "unknownFinish" => None,
+            _ => match self.winner {
+                Some(winner) if winner == color => Some(Outcome::Win),
+                Some(_) => Some(Outcome::Loss),
+                None => Some(Outcome::Draw),
+            },
+        }
     }
+}
 
-    fn to_json(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string(self)
-    }
+impl ChessGame for Game {
+    type PlayerType = Player;
 
     fn pgn(&mut self) -> String {
         self.pgn.clone()
@@ -56,6 +70,95 @@ impl ChessGame for Game {
     fn end_time(&self) -> DateTime<Utc> {
         self.last_move_at.clone()
     }
+
+    fn start_time(&self) -> Option<DateTime<Utc>> {
+        Some(self.created_at)
+    }
+
+    fn eco(&self) -> Option<String> {
+        self.opening
+            .as_ref()
+            .filter(|o| !o.eco.is_empty())
+            .map(|o| o.eco.clone())
+    }
+
+    fn moves_with_evals(&self) -> Option<String> {
+        let analysis = self.analysis.as_ref()?;
+        let moves: Vec<&str> = self.moves.split_whitespace().collect();
+        let evals: Vec<String> = analysis.iter().map(|a| a.to_string()).collect();
+        Some(annotate_moves(&moves, &evals))
+    }
+
+    fn speed(&self) -> Speed {
+        match (Speed::from_name(&self.speed), &self.clock) {
+            (Speed::Unknown, None) => Speed::Correspondence,
+            (speed, _) => speed,
+        }
+    }
+
+    fn time_class(&self) -> Option<TimeClass> {
+        TimeClass::from_speed_name(&self.speed)
+    }
+
+    fn variant(&self) -> String {
+        self.variant.clone()
+    }
+
+    fn moves(&mut self) -> Vec<String> {
+        self.moves
+            .split_whitespace()
+            .map(|m| m.to_string())
+            .collect()
+    }
+
+    fn result_for(&mut self, username: &str) -> Option<Outcome> {
+        let username = username.to_lowercase();
+        if self.white().name().to_lowercase() == username {
+            self.outcome_for(Color::White)
+        } else if self.black().name().to_lowercase() == username {
+            self.outcome_for(Color::Black)
+        } else {
+            None
+        }
+    }
+
+    fn rating_change(&mut self, username: &str) -> Option<i32> {
+        if !self.rated {
+            return None;
+        }
+        let username = username.to_lowercase();
+        if self.white().name().to_lowercase() == username {
+            self.players.white.rating_diff
+        } else if self.black().name().to_lowercase() == username {
+            self.players.black.rating_diff
+        } else {
+            None
+        }
+    }
+
+    fn clocks(&mut self) -> Vec<MoveClock> {
+        MoveClock::from_pgn(&self.pgn)
+    }
+
+    fn is_checkmate(&self) -> bool {
+        self.status == "mate"
+    }
+
+    fn is_stalemate(&self) -> bool {
+        self.status == "stalemate"
+    }
+
+    fn termination(&self) -> Termination {
+        lichess_termination(&self.status)
+    }
+
+    fn opening(&self) -> Option<String> {
+        match &self.opening {
+            Some(o) if !o.name.is_empty() => Some(o.name.clone()),
+            Some(o) => eco_name(&o.eco).map(|name| name.to_string()),
+            None => None,
+        }
+    }
 }
 
 impl DisplayableChessGame for Game {}
@@ -72,7 +175,11 @@ pub struct Player {
     // Fields may be missing if user is anonymous
     pub user: Option<User>,
     pub rating: Option<u32>,
+    #[serde(alias = "rating_diff")]
     pub rating_diff: Option<i32>,
+    /// Whether the rating was provisional, which lichess shows with a "?".
+    #[serde(default)]
+    pub provisional: bool,
 }
 
 impl ChessPlayer for Player {
@@ -110,9 +217,17 @@ impl ChessPlayer for Player {
         }
     }
 
+    fn profile_url(&self) -> Option<String> {
+        self.url()
+    }
+
     fn result(&self) -> Option<String> {
         None
     }
+
+    fn is_provisional(&self) -> bool {
+        self.provisional
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -130,10 +245,240 @@ pub struct Opening {
     pub ply: i32,
 }
 
+/// Computer evaluation of the position after a move, either in centipawns or in moves to mate.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Analysis {
+    pub eval: Option<i32>,
+    pub mate: Option<i32>,
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.eval, self.mate) {
+            (_, Some(mate)) => write!(f, "#{}", mate),
+            (Some(eval), None) => write!(f, "{:+.1}", eval as f32 / 100.0),
+            (None, None) => write!(f, "?"),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all(deserialize = "camelCase"))]
 pub struct Clock {
     pub initial: u32,
     pub increment: u32,
+    #[serde(alias = "total_time")]
     pub total_time: u32,
 }
+
+/// A game created by importing a PGN.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportedGame {
+    pub id: String,
+    pub url: String,
+}
+
+/// Public data of a user, of which only the username, with the casing the user chose, is used.
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserProfile {
+    pub id: String,
+    pub username: String,
+}
+
+/// Ratings of a player in one perf type, e.g. "Blitz" or "Chess960", as returned by the rating
+/// history endpoint.
+#[derive(Deserialize, Debug, Clone)]
+pub struct RatingHistory {
+    pub name: String,
+    /// Year, month (starting at 0), day, and rating
+    pub points: Vec<(i32, u32, u32, u32)>,
+}
+
+impl RatingHistory {
+    /// Speed of the perf type. Variants, like Chess960, are Unknown.
+    pub fn speed(&self) -> Speed {
+        let mut chars = self.name.chars();
+        let name = match chars.next() {
+            Some(first) => first.to_lowercase().chain(chars).collect::<String>(),
+            None => String::new(),
+        };
+        Speed::from_name(&name)
+    }
+
+    /// Dated ratings, skipping any point with an invalid date.
+    pub fn points(&self) -> Vec<(NaiveDate, u32)> {
+        self.points
+            .iter()
+            .filter_map(|(y, m, d, rating)| {
+                NaiveDate::from_ymd_opt(*y, m + 1, *d).map(|date| (date, *rating))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_profile_url() {
+        let player: Player = serde_json::from_str(
+            r#"{"user": {"name": "Lance5500", "id": "lance5500"}, "rating": 2389}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            player.profile_url(),
+            Some("https://lichess.org/@/lance5500".to_string())
+        );
+
+        let anonymous: Player = serde_json::from_str(r#"{}"#).unwrap();
+        assert_eq!(anonymous.profile_url(), None);
+    }
+
+    #[test]
+    fn test_timestamps_are_milliseconds() {
+        let game: Game = serde_json::from_str(
+            r#"{
+                "id": "q7ZvsdUF",
+                "rated": true,
+                "variant": "standard",
+                "speed": "blitz",
+                "perf": "blitz",
+                "createdAt": 1514505150384,
+                "lastMoveAt": 1514505592843,
+                "status": "resign",
+                "players": {"white": {}, "black": {}},
+                "pgn": "1. d4 d5 1-0",
+                "moves": "d4 d5"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            game.start_time(),
+            Some(Utc.ymd(2017, 12, 28).and_hms_milli(23, 52, 30, 384))
+        );
+        assert_eq!(
+            game.end_time() - game.created_at,
+            chrono::Duration::milliseconds(442_459)
+        );
+    }
+
+    #[test]
+    fn test_outcome_for() {
+        let game_json = r#"{
+            "id": "q7ZvsdUF",
+            "rated": true,
+            "variant": "standard",
+            "speed": "blitz",
+            "perf": "blitz",
+            "createdAt": 1514505150384,
+            "lastMoveAt": 1514505592843,
+            "status": "resign",
+            "winner": "white",
+            "players": {"white": {}, "black": {}},
+            "pgn": "1. d4 d5 1-0",
+            "clock": {"initial": 300, "increment": 3, "totalTime": 420},
+            "moves": "d4 d5"
+        }"#;
+        let mut game: Game = serde_json::from_str(game_json).unwrap();
+        assert_eq!(game.outcome_for(Color::White), Some(Outcome::Win));
+        assert_eq!(game.outcome_for(Color::Black), Some(Outcome::Loss));
+
+        game.winner = None;
+        game.status = "draw".to_string();
+        assert_eq!(game.outcome_for(Color::White), Some(Outcome::Draw));
+
+        game.status = "started".to_string();
+        assert_eq!(game.outcome_for(Color::Black), None);
+    }
+
+    #[test]
+    fn test_result_for() {
+        let game_json = r#"{
+            "id": "q7ZvsdUF",
+            "rated": true,
+            "variant": "standard",
+            "speed": "blitz",
+            "perf": "blitz",
+            "createdAt": 1514505150384,
+            "lastMoveAt": 1514505592843,
+            "status": "mate",
+            "winner": "black",
+            "players": {
+                "white": {"user": {"name": "Alice", "id": "alice"}},
+                "black": {"user": {"name": "Bob", "id": "bob"}}
+            },
+            "pgn": "1. f3 e5 2. g4 Qh4# 0-1",
+            "clock": {"initial": 300, "increment": 3, "totalTime": 420},
+            "moves": "f3 e5 g4 Qh4#"
+        }"#;
+        let mut game: Game = serde_json::from_str(game_json).unwrap();
+        assert_eq!(game.result_for("bob"), Some(Outcome::Win));
+        assert_eq!(game.result_for("ALICE"), Some(Outcome::Loss));
+        assert_eq!(game.result_for("carol"), None);
+
+        assert!(game.is_checkmate());
+        assert!(!game.is_stalemate());
+
+        game.winner = None;
+        game.status = "stalemate".to_string();
+        assert_eq!(game.result_for("alice"), Some(Outcome::Draw));
+        assert!(!game.is_checkmate());
+        assert!(game.is_stalemate());
+    }
+
+    #[test]
+    fn test_rating_history() {
+        let history: Vec<RatingHistory> = serde_json::from_str(
+            r#"[
+                {"name": "UltraBullet", "points": []},
+                {"name": "Blitz", "points": [[2011, 0, 8, 1472], [2011, 11, 31, 1506]]},
+                {"name": "Chess960", "points": [[2012, 5, 1, 1500]]}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(history[0].speed(), Speed::Bullet);
+        assert_eq!(history[1].speed(), Speed::Blitz);
+        assert_eq!(history[2].speed(), Speed::Unknown);
+        assert_eq!(
+            history[1].points(),
+            vec![
+                (NaiveDate::from_ymd(2011, 1, 8), 1472),
+                (NaiveDate::from_ymd(2011, 12, 31), 1506)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_game_without_clock() {
+        let game_json = r#"{
+            "id": "FbFcKGVG",
+            "rated": false,
+            "variant": "standard",
+            "speed": "unlimited",
+            "perf": "correspondence",
+            "createdAt": 1514505150384,
+            "lastMoveAt": 1514505592843,
+            "status": "mate",
+            "winner": "black",
+            "players": {"white": {}, "black": {}},
+            "pgn": "1. f3 e5 2. g4 Qh4# 0-1",
+            "daysPerTurn": 3,
+            "moves": "f3 e5 g4 Qh4#"
+        }"#;
+        let game: Game = serde_json::from_str(game_json).unwrap();
+        assert!(game.clock.is_none());
+        assert_eq!(game.speed(), Speed::Correspondence);
+    }
+
+    #[test]
+    fn test_analysis_display() {
+        let analysis: Vec<Analysis> =
+            serde_json::from_str(r#"[{"eval": 18}, {"eval": -250}, {"eval": 0}, {"mate": -3}]"#)
+                .unwrap();
+        let evals: Vec<String> = analysis.iter().map(|a| a.to_string()).collect();
+        assert_eq!(evals, vec!["+0.2", "-2.5", "+0.0", "#-3"]);
+    }
+}
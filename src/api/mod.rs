@@ -1,20 +1,50 @@
-use std::fmt::Debug;
+use std::collections::BTreeMap;
+use std::fmt::{self, Debug};
+use std::str::FromStr;
 
 use chrono::{self, DateTime, Datelike, Utc};
-use reqwest::{self, blocking::Request, Method, Url};
+use reqwest::{
+    self,
+    blocking::Request,
+    header::{HeaderValue, AUTHORIZATION, CONTENT_TYPE},
+    Method, Url,
+};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json;
 use thiserror::Error;
 
+use shakmaty::Chess;
+
+pub use crate::utils::Termination;
+use crate::utils::{
+    clock_seconds, fen_after, minimal_pgn, pgn_moves_with_comments, pgn_tags, san_to_uci,
+    MoveDecodeError,
+};
+
 pub mod chessdotcom;
 pub mod lichessdotorg;
+pub mod normalized;
+mod timestamp;
 
 pub trait ChessPlayer {
     fn name(&self) -> String;
     fn title(&self) -> Option<String>;
     fn rating(&self) -> Option<u32>;
+    /// URL of the player's profile page on the website. Anonymous players have no profile.
     fn url(&self) -> Option<String>;
+    /// URL of the player's profile page on the website, the same as url. Anonymous players have
+    /// no profile.
+    fn profile_url(&self) -> Option<String>;
+    /// URL of the player in the API, when games link to it, like chess.com's @id.
+    fn api_url(&self) -> Option<String> {
+        None
+    }
     fn result(&self) -> Option<String>;
+    /// Whether the player's rating was provisional in the game. chess.com doesn't report it, so
+    /// its ratings are never provisional.
+    fn is_provisional(&self) -> bool {
+        false
+    }
 }
 
 /// Trait encompassing minimum information expected from all APIs: a PGN, a white
@@ -22,26 +52,435 @@ pub trait ChessPlayer {
 pub trait ChessGame {
     type PlayerType: ChessPlayer;
 
-    fn to_json_pretty(&self) -> Result<String, serde_json::Error>;
-    fn to_json(&self) -> Result<String, serde_json::Error>;
+    fn to_json_pretty(&self) -> Result<String, serde_json::Error>
+    where
+        Self: Serialize,
+    {
+        serde_json::to_string_pretty(self)
+    }
+    fn to_json(&self) -> Result<String, serde_json::Error>
+    where
+        Self: Serialize,
+    {
+        serde_json::to_string(self)
+    }
     fn pgn(&mut self) -> String;
     fn white(&mut self) -> Self::PlayerType;
     fn black(&mut self) -> Self::PlayerType;
     fn url(&self) -> String;
     fn end_time(&self) -> DateTime<Utc>;
+    /// When the game started, if the API reports it.
+    fn start_time(&self) -> Option<DateTime<Utc>>;
+    fn eco(&self) -> Option<String>;
+    fn opening(&self) -> Option<String>;
+    fn moves_with_evals(&self) -> Option<String>;
+    fn speed(&self) -> Speed;
+    /// Time class of the game by the base time on its clock, if it has one.
+    fn time_class(&self) -> Option<TimeClass>;
+    /// Variant the game was played in, named as lichess names them, e.g. "standard" or
+    /// "chess960". chess.com names are only compared case insensitively to them.
+    fn variant(&self) -> String;
+    fn moves(&mut self) -> Vec<String>;
+    /// Outcome of the game from the perspective of the player with the given username, if they
+    /// played in it and the game finished. Usernames are compared case insensitively.
+    fn result_for(&mut self, username: &str) -> Option<Outcome>;
+    /// Rating points the player with the given username gained, or lost when negative, with the
+    /// game. None for unrated games, players who didn't play in it, and games whose API doesn't
+    /// report it.
+    fn rating_change(&mut self, username: &str) -> Option<i32>;
+    /// Time left on the clock of the player who made each move, for games with a clock.
+    fn clocks(&mut self) -> Vec<MoveClock>;
+    /// Whether the game finished with a checkmate.
+    fn is_checkmate(&self) -> bool;
+    /// Whether the game finished with a stalemate.
+    fn is_stalemate(&self) -> bool;
+    /// How the game ended, in the same terms for every API.
+    fn termination(&self) -> Termination;
+
+    /// The PGN of the game with only the tags of the Seven Tag Roster, for strict PGN databases.
+    fn minimal_pgn(&mut self) -> String {
+        minimal_pgn(&self.pgn())
+    }
+
+    /// The moves of the game in UCI notation, e.g. e2e4, replayed from the starting position.
+    fn uci_moves(&mut self) -> Result<Vec<String>, MoveDecodeError> {
+        san_to_uci(Chess::default(), &self.moves())
+    }
+
+    /// FEN of the position after the first ply moves of the game, or of its final position when
+    /// ply is None.
+    fn fen_at(&mut self, ply: Option<usize>) -> Result<String, MoveDecodeError> {
+        fen_after(Chess::default(), &self.moves(), ply)
+    }
+
+    /// The tag pairs of the PGN of the game, by tag name.
+    fn tags(&mut self) -> BTreeMap<String, String> {
+        pgn_tags(&self.pgn())
+    }
 }
 
 /// A supertrait encompassing required traits for proper displaying of a chess
 /// game, in either JSON, PGN, or table format.
 pub trait DisplayableChessGame: ChessGame + Serialize + DeserializeOwned + Clone + Debug {}
 
-#[derive(Debug, Clone, Deserialize)]
+/// A move of a game with the time left on the clock of the player who made it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MoveClock {
+    pub ply: usize,
+    pub san: String,
+    pub clock_seconds: Option<f64>,
+    /// Unit the clock was sent in, for the games whose API sends raw timestamps.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp_unit: Option<TimestampUnit>,
+}
+
+/// Unit of the move timestamps of a chess.com live game. They are sent in tenths of a second,
+/// but games with timestamps in milliseconds or seconds have been seen too.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampUnit {
+    Tenths,
+    Milliseconds,
+    Seconds,
+}
+
+impl TimestampUnit {
+    /// Convert a timestamp in this unit to tenths of a second.
+    pub fn to_tenths(self, ts: u32) -> u32 {
+        match self {
+            TimestampUnit::Tenths => ts,
+            TimestampUnit::Milliseconds => ts / 100,
+            TimestampUnit::Seconds => ts.saturating_mul(10),
+        }
+    }
+
+    /// Detect the unit of a game's timestamps from its time control, both in tenths of a second.
+    /// No clock can be over the base time plus an increment per move, and the largest clock of a
+    /// game is expected within a tenth of that, which sets the units three orders of magnitude
+    /// apart from each other. None when the timestamps fit no unit.
+    pub fn detect(timestamps: &[u32], base: u32, increment: u32) -> Option<TimestampUnit> {
+        let max = match timestamps.iter().max() {
+            Some(&max) => max,
+            None => return Some(TimestampUnit::Tenths),
+        };
+        if base == 0 {
+            // Without a time control there is nothing to check the timestamps against
+            return Some(TimestampUnit::Tenths);
+        }
+        let limit = base.saturating_add(increment.saturating_mul(timestamps.len() as u32));
+        [
+            TimestampUnit::Tenths,
+            TimestampUnit::Milliseconds,
+            TimestampUnit::Seconds,
+        ]
+        .iter()
+        .copied()
+        .find(|unit| {
+            let tenths = unit.to_tenths(max);
+            tenths <= limit && tenths.saturating_mul(10) > limit
+        })
+    }
+}
+
+impl MoveClock {
+    /// Read the clock of each move from the "%clk" comments of a PGN. Moves without one have no
+    /// clock time.
+    pub fn from_pgn(pgn: &str) -> Vec<MoveClock> {
+        pgn_moves_with_comments(pgn)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (san, comment))| MoveClock {
+                ply: i + 1,
+                san,
+                clock_seconds: clock_seconds(&comment),
+                timestamp_unit: None,
+            })
+            .collect()
+    }
+}
+
+/// Color of a player's pieces. (De)serialized as the "white" and "black" strings used by the APIs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    White,
+    Black,
+}
+
+impl Color {
+    /// The color of the opponent.
+    pub fn other(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Color::White => write!(f, "white"),
+            Color::Black => write!(f, "black"),
+        }
+    }
+}
+
+/// The outcome of a game from the perspective of one of its players.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Outcome {
+    /// Map a chess.com result code (e.g. "win", "resigned", "agreed") to an outcome.
+    pub fn from_result_code(code: &str) -> Option<Outcome> {
+        match code {
+            "win" => Some(Outcome::Win),
+            "checkmated"
+            | "resigned"
+            | "timeout"
+            | "lose"
+            | "abandoned"
+            | "kingofthehill"
+            | "threecheck"
+            | "bughousepartnerlose" => Some(Outcome::Loss),
+            "agreed" | "repetition" | "stalemate" | "insufficient" | "50move"
+            | "timevsinsufficient" => Some(Outcome::Draw),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for Outcome {
+    type Err = String;
+
+    fn from_str(outcome: &str) -> Result<Self, Self::Err> {
+        match outcome {
+            "win" => Ok(Outcome::Win),
+            "loss" => Ok(Outcome::Loss),
+            "draw" => Ok(Outcome::Draw),
+            o => Err(format!("unsupported outcome {}", o)),
+        }
+    }
+}
+
+/// Time class of a game, bucketed by its estimated duration: initial time plus 40 times the
+/// increment.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Speed {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+    Unknown,
+}
+
+impl Speed {
+    /// Bucket a game given its initial time and increment, both in seconds.
+    pub fn from_seconds(initial: u32, increment: u32) -> Speed {
+        match initial.saturating_add(increment.saturating_mul(40)) {
+            0 => Speed::Unknown,
+            t if t < 180 => Speed::Bullet,
+            t if t < 600 => Speed::Blitz,
+            t if t < 1800 => Speed::Rapid,
+            _ => Speed::Classical,
+        }
+    }
+
+    /// Bucket a game given a PGN style time control like "180+2", "600", or "1/259200" for
+    /// daily (correspondence) games.
+    pub fn from_time_control(time_control: &str) -> Speed {
+        if time_control.contains('/') {
+            return Speed::Correspondence;
+        }
+
+        let mut parts = time_control.splitn(2, '+');
+        let initial = parts.next().and_then(|i| i.trim().parse::<u32>().ok());
+        let increment = match parts.next() {
+            Some(i) => i.trim().parse::<u32>().ok(),
+            None => Some(0),
+        };
+
+        match (initial, increment) {
+            (Some(initial), Some(increment)) => Speed::from_seconds(initial, increment),
+            _ => Speed::Unknown,
+        }
+    }
+
+    /// Parse a speed name as used by lichess.org, e.g. "blitz" or "ultraBullet".
+    pub fn from_name(name: &str) -> Speed {
+        match name {
+            "ultraBullet" | "bullet" => Speed::Bullet,
+            "blitz" => Speed::Blitz,
+            "rapid" => Speed::Rapid,
+            "classical" => Speed::Classical,
+            "correspondence" => Speed::Correspondence,
+            _ => Speed::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Speed::Bullet => "bullet",
+            Speed::Blitz => "blitz",
+            Speed::Rapid => "rapid",
+            Speed::Classical => "classical",
+            Speed::Correspondence => "correspondence",
+            Speed::Unknown => "unknown",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Time class of a game by the base time on its clock alone, ignoring the increment: bullet
+/// under 3 minutes, blitz under 10, rapid under 30, and classical from then on. Daily games and
+/// games without a clock have none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeClass {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+}
+
+impl TimeClass {
+    /// Bucket a game given its base time in seconds.
+    pub fn from_base_seconds(base: u32) -> Option<TimeClass> {
+        match base {
+            0 => None,
+            b if b < 180 => Some(TimeClass::Bullet),
+            b if b < 600 => Some(TimeClass::Blitz),
+            b if b < 1800 => Some(TimeClass::Rapid),
+            _ => Some(TimeClass::Classical),
+        }
+    }
+
+    /// Bucket a game given a chess.com time control like "180+2" or "600". Daily time controls
+    /// like "1/259200" have no time class.
+    pub fn from_time_control(time_control: &str) -> Option<TimeClass> {
+        if time_control.contains('/') {
+            return None;
+        }
+        let base = time_control.split('+').next()?.trim().parse::<u32>().ok()?;
+        TimeClass::from_base_seconds(base)
+    }
+
+    /// Read a lichess.org speed name, e.g. "blitz" or "ultraBullet".
+    pub fn from_speed_name(name: &str) -> Option<TimeClass> {
+        match name {
+            "ultraBullet" | "bullet" => Some(TimeClass::Bullet),
+            "blitz" => Some(TimeClass::Blitz),
+            "rapid" => Some(TimeClass::Rapid),
+            "classical" => Some(TimeClass::Classical),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for TimeClass {
+    type Err = String;
+
+    fn from_str(time_class: &str) -> Result<Self, Self::Err> {
+        match time_class {
+            "bullet" => Ok(TimeClass::Bullet),
+            "blitz" => Ok(TimeClass::Blitz),
+            "rapid" => Ok(TimeClass::Rapid),
+            "classical" => Ok(TimeClass::Classical),
+            t => Err(format!("unsupported time class {}", t)),
+        }
+    }
+}
+
+impl fmt::Display for TimeClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            TimeClass::Bullet => "bullet",
+            TimeClass::Blitz => "blitz",
+            TimeClass::Rapid => "rapid",
+            TimeClass::Classical => "classical",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Parameters of the lichess games endpoints set by the crate, which extra parameters can't
+/// override: the date range and number of games scanned, the speed and variant filter, and the
+/// PGN the games are read from.
+pub const LICHESS_MANAGED_PARAMS: &[&str] = &["since", "until", "max", "perfType", "pgnInJson"];
+
+/// Parse an extra query parameter of the lichess games endpoints, given as key=value.
+pub fn parse_lichess_param(param: &str) -> Result<(String, String), String> {
+    let (key, value) = param
+        .split_once('=')
+        .ok_or_else(|| format!("invalid parameter {:?}, expected key=value", param))?;
+    let key = key.trim();
+    if key.is_empty() {
+        return Err(format!("invalid parameter {:?}, the key is empty", param));
+    }
+    if LICHESS_MANAGED_PARAMS.contains(&key) {
+        return Err(format!(
+            "{} can't be set, as it's managed by cgf. Managed parameters are: {}",
+            key,
+            LICHESS_MANAGED_PARAMS.join(", ")
+        ));
+    }
+    Ok((key.to_string(), value.to_string()))
+}
+
+/// Append extra query parameters to a request of the lichess games endpoints, URL-encoded.
+/// Managed parameters are skipped, as the scan depends on them.
+pub fn with_lichess_params(mut request: Request, params: &[(String, String)]) -> Request {
+    let extra: Vec<_> = params
+        .iter()
+        .filter(|(key, _)| !LICHESS_MANAGED_PARAMS.contains(&key.as_str()))
+        .collect();
+    if !extra.is_empty() {
+        let mut pairs = request.url_mut().query_pairs_mut();
+        for (key, value) in extra {
+            pairs.append_pair(key, value);
+        }
+    }
+    request
+}
+
+/// Value of the perfType parameter of the lichess games endpoint that only returns games of the
+/// given speed and variant, if any. Variants other than standard have a single perf, regardless
+/// of their speed.
+pub fn lichess_perf_type(speed: Option<Speed>, variant: Option<&str>) -> Option<String> {
+    match variant {
+        Some(variant) if variant != "standard" => Some(variant.to_string()),
+        _ => match speed? {
+            Speed::Bullet => Some("ultraBullet,bullet".to_string()),
+            Speed::Unknown => None,
+            speed => Some(speed.to_string()),
+        },
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Games {
     ChessDotCom(Vec<chessdotcom::Game>),
     LichessDotOrg(Vec<lichessdotorg::Game>),
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl Games {
+    /// Name of the API the games come from.
+    pub fn api(&self) -> &'static str {
+        match self {
+            Games::ChessDotCom(_) => "chess.com",
+            Games::LichessDotOrg(_) => "lichess.org",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub enum Player {
     ChessDotCom(chessdotcom::Player),
     ChessDotComLive(chessdotcom::LivePlayer),
@@ -81,6 +520,22 @@ impl ChessPlayer for Player {
         }
     }
 
+    fn profile_url(&self) -> Option<String> {
+        match self {
+            Player::ChessDotCom(p) => p.profile_url(),
+            Player::ChessDotComLive(p) => p.profile_url(),
+            Player::LichessDotOrg(p) => p.profile_url(),
+        }
+    }
+
+    fn api_url(&self) -> Option<String> {
+        match self {
+            Player::ChessDotCom(p) => p.api_url(),
+            Player::ChessDotComLive(p) => p.api_url(),
+            Player::LichessDotOrg(p) => p.api_url(),
+        }
+    }
+
     fn result(&self) -> Option<String> {
         match self {
             Player::ChessDotCom(p) => p.result(),
@@ -88,6 +543,14 @@ impl ChessPlayer for Player {
             Player::LichessDotOrg(p) => p.result(),
         }
     }
+
+    fn is_provisional(&self) -> bool {
+        match self {
+            Player::ChessDotCom(p) => p.is_provisional(),
+            Player::ChessDotComLive(p) => p.is_provisional(),
+            Player::LichessDotOrg(p) => p.is_provisional(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -97,6 +560,56 @@ pub enum Game {
     LichessDotOrg(lichessdotorg::Game),
 }
 
+impl Game {
+    /// Name of the API the game comes from.
+    pub fn api(&self) -> &'static str {
+        match self {
+            Game::ChessDotCom(_) | Game::ChessDotComLive(_) => "chess.com",
+            Game::LichessDotOrg(_) => "lichess.org",
+        }
+    }
+
+    /// Replace the names of both players with the result of rename, e.g. to display their
+    /// canonical casing. Anonymous lichess players have no name to replace. The PGN of archived
+    /// chess.com games is kept as sent by the API.
+    pub fn rename_players<F: FnMut(&str) -> String>(&mut self, mut rename: F) {
+        match self {
+            Game::ChessDotCom(g) => {
+                g.white.username = rename(&g.white.username);
+                g.black.username = rename(&g.black.username);
+            }
+            Game::ChessDotComLive(g) => {
+                for player in [&mut g.players.top, &mut g.players.bottom].iter_mut() {
+                    player.username = rename(&player.username);
+                }
+                let headers = &mut g.game.pgn_headers;
+                headers.white = rename(&headers.white);
+                headers.black = rename(&headers.black);
+            }
+            Game::LichessDotOrg(g) => {
+                for player in [&mut g.players.white, &mut g.players.black].iter_mut() {
+                    if let Some(user) = player.user.as_mut() {
+                        user.name = rename(&user.name);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Replay the moves of the game from the given FEN. Only chess.com live games are decoded
+    /// from a starting position, returns whether the game is one of them.
+    pub fn seed_position(&mut self, fen: &str) -> bool {
+        match self {
+            Game::ChessDotComLive(g) => {
+                g.seed_position(fen);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Games serialize with the name of their API, so their JSON is the JSON of the API game instead.
 impl ChessGame for Game {
     type PlayerType = Player;
 
@@ -155,6 +668,142 @@ impl ChessGame for Game {
             Game::LichessDotOrg(g) => g.end_time(),
         }
     }
+
+    fn start_time(&self) -> Option<DateTime<Utc>> {
+        match self {
+            Game::ChessDotCom(g) => g.start_time(),
+            Game::ChessDotComLive(g) => g.start_time(),
+            Game::LichessDotOrg(g) => g.start_time(),
+        }
+    }
+
+    fn eco(&self) -> Option<String> {
+        match self {
+            Game::ChessDotCom(g) => g.eco(),
+            Game::ChessDotComLive(g) => g.eco(),
+            Game::LichessDotOrg(g) => g.eco(),
+        }
+    }
+
+    fn opening(&self) -> Option<String> {
+        match self {
+            Game::ChessDotCom(g) => g.opening(),
+            Game::ChessDotComLive(g) => g.opening(),
+            Game::LichessDotOrg(g) => g.opening(),
+        }
+    }
+
+    fn moves_with_evals(&self) -> Option<String> {
+        match self {
+            Game::ChessDotCom(g) => g.moves_with_evals(),
+            Game::ChessDotComLive(g) => g.moves_with_evals(),
+            Game::LichessDotOrg(g) => g.moves_with_evals(),
+        }
+    }
+
+    fn speed(&self) -> Speed {
+        match self {
+            Game::ChessDotCom(g) => g.speed(),
+            Game::ChessDotComLive(g) => g.speed(),
+            Game::LichessDotOrg(g) => g.speed(),
+        }
+    }
+
+    fn time_class(&self) -> Option<TimeClass> {
+        match self {
+            Game::ChessDotCom(g) => g.time_class(),
+            Game::ChessDotComLive(g) => g.time_class(),
+            Game::LichessDotOrg(g) => g.time_class(),
+        }
+    }
+
+    fn uci_moves(&mut self) -> Result<Vec<String>, MoveDecodeError> {
+        match self {
+            Game::ChessDotCom(g) => g.uci_moves(),
+            Game::ChessDotComLive(g) => g.uci_moves(),
+            Game::LichessDotOrg(g) => g.uci_moves(),
+        }
+    }
+
+    fn fen_at(&mut self, ply: Option<usize>) -> Result<String, MoveDecodeError> {
+        match self {
+            Game::ChessDotCom(g) => g.fen_at(ply),
+            Game::ChessDotComLive(g) => g.fen_at(ply),
+            Game::LichessDotOrg(g) => g.fen_at(ply),
+        }
+    }
+
+    fn tags(&mut self) -> BTreeMap<String, String> {
+        match self {
+            Game::ChessDotCom(g) => g.tags(),
+            Game::ChessDotComLive(g) => g.tags(),
+            Game::LichessDotOrg(g) => g.tags(),
+        }
+    }
+
+    fn variant(&self) -> String {
+        match self {
+            Game::ChessDotCom(g) => g.variant(),
+            Game::ChessDotComLive(g) => g.variant(),
+            Game::LichessDotOrg(g) => g.variant(),
+        }
+    }
+
+    fn moves(&mut self) -> Vec<String> {
+        match self {
+            Game::ChessDotCom(g) => g.moves(),
+            Game::ChessDotComLive(g) => g.moves(),
+            Game::LichessDotOrg(g) => g.moves(),
+        }
+    }
+
+    fn result_for(&mut self, username: &str) -> Option<Outcome> {
+        match self {
+            Game::ChessDotCom(g) => g.result_for(username),
+            Game::ChessDotComLive(g) => g.result_for(username),
+            Game::LichessDotOrg(g) => g.result_for(username),
+        }
+    }
+
+    fn rating_change(&mut self, username: &str) -> Option<i32> {
+        match self {
+            Game::ChessDotCom(g) => g.rating_change(username),
+            Game::ChessDotComLive(g) => g.rating_change(username),
+            Game::LichessDotOrg(g) => g.rating_change(username),
+        }
+    }
+
+    fn clocks(&mut self) -> Vec<MoveClock> {
+        match self {
+            Game::ChessDotCom(g) => g.clocks(),
+            Game::ChessDotComLive(g) => g.clocks(),
+            Game::LichessDotOrg(g) => g.clocks(),
+        }
+    }
+
+    fn is_checkmate(&self) -> bool {
+        match self {
+            Game::ChessDotCom(g) => g.is_checkmate(),
+            Game::ChessDotComLive(g) => g.is_checkmate(),
+            Game::LichessDotOrg(g) => g.is_checkmate(),
+        }
+    }
+
+    fn is_stalemate(&self) -> bool {
+        match self {
+            Game::ChessDotCom(g) => g.is_stalemate(),
+            Game::ChessDotComLive(g) => g.is_stalemate(),
+            Game::LichessDotOrg(g) => g.is_stalemate(),
+        }
+    }
+
+    fn termination(&self) -> Termination {
+        match self {
+            Game::ChessDotCom(g) => g.termination(),
+            Game::ChessDotComLive(g) => g.termination(),
+            Game::LichessDotOrg(g) => g.termination(),
+        }
+    }
 }
 
 impl DisplayableChessGame for Game {}
@@ -165,10 +814,18 @@ pub enum ApiError {
     UnsupportedApi { api: String },
     #[error("endpoint {endpoint:?} not implemented for {api:?}")]
     EndpointNotImplemented { endpoint: String, api: String },
+    #[error("{api} doesn't support {capability}. {supported}")]
+    UnsupportedCapability {
+        api: String,
+        capability: Capability,
+        supported: String,
+    },
     #[error("URL could not be parsed")]
     URLParseFailed(#[from] url::ParseError),
     #[error("HTTP Error")]
     HTTPError(#[from] reqwest::Error),
+    #[error("the token can't be sent in a header")]
+    InvalidToken(#[from] reqwest::header::InvalidHeaderValue),
 }
 
 #[derive(PartialEq, Debug)]
@@ -177,7 +834,94 @@ pub enum Api {
     LichessDotOrg,
 }
 
+/// Operations a search can request, which not every API implements.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Capability {
+    GameById,
+    PlayerGames,
+    ListArchives,
+    OngoingGames,
+    RatingHistory,
+    Export,
+    Tournaments,
+    PlayerStatus,
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Capability::GameById => "finding games by ID",
+            Capability::PlayerGames => "finding games by player",
+            Capability::ListArchives => "listing archives",
+            Capability::OngoingGames => "listing ongoing games",
+            Capability::RatingHistory => "rating history",
+            Capability::Export => "exporting games",
+            Capability::Tournaments => "listing tournaments",
+            Capability::PlayerStatus => "player status",
+        };
+        write!(f, "{}", s)
+    }
+}
+
 impl Api {
+    /// Every supported API, in the order their capabilities are listed.
+    pub const ALL: [Api; 2] = [Api::ChessDotCom, Api::LichessDotOrg];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Api::ChessDotCom => "chess.com",
+            Api::LichessDotOrg => "lichess.org",
+        }
+    }
+
+    /// What can be done with the API. chess.com has no endpoint for a player's rating history,
+    /// which is taken from their games, while lichess has no monthly archives, ongoing games,
+    /// tournaments of a player, nor player status.
+    pub fn capabilities(&self) -> &'static [Capability] {
+        match self {
+            Api::ChessDotCom => &[
+                Capability::GameById,
+                Capability::PlayerGames,
+                Capability::ListArchives,
+                Capability::OngoingGames,
+                Capability::RatingHistory,
+                Capability::Export,
+                Capability::Tournaments,
+                Capability::PlayerStatus,
+            ],
+            Api::LichessDotOrg => &[
+                Capability::GameById,
+                Capability::PlayerGames,
+                Capability::RatingHistory,
+                Capability::Export,
+            ],
+        }
+    }
+
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities().contains(&capability)
+    }
+
+    /// Fail unless the API supports the capability, listing what each API supports.
+    pub fn require(&self, capability: Capability) -> Result<(), ApiError> {
+        if self.supports(capability) {
+            return Ok(());
+        }
+        let supported: Vec<String> = Api::ALL
+            .iter()
+            .map(|api| {
+                let capabilities: Vec<String> =
+                    api.capabilities().iter().map(|c| c.to_string()).collect();
+                format!("{} supports {}", api.name(), capabilities.join(", "))
+            })
+            .collect();
+        Err(ApiError::UnsupportedCapability {
+            api: self.name().to_string(),
+            capability,
+            supported: supported.join("; "),
+        })
+    }
+
     pub fn from_str(s: &str) -> Result<Self, ApiError> {
         match s {
             "chess.com" => Ok(Api::ChessDotCom),
@@ -229,11 +973,160 @@ impl Api {
         }
     }
 
+    pub fn user(&self, username: &str) -> Result<Request, ApiError> {
+        let url = match self {
+            Api::ChessDotCom => format!("https://api.chess.com/pub/player/{}", username),
+            Api::LichessDotOrg => format!("https://lichess.org/api/user/{}", username),
+        };
+        Ok(Request::new(Method::GET, Url::parse(&url)?))
+    }
+
+    pub fn player_stats(&self, username: &str) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => {
+                let url = Url::parse(&format!(
+                    "https://api.chess.com/pub/player/{}/stats",
+                    username
+                ))?;
+                Ok(Request::new(Method::GET, url))
+            }
+            Api::LichessDotOrg => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/player/{user}/stats".to_string(),
+                api: "lichess.org".to_string(),
+            }),
+        }
+    }
+
+    pub fn tournament(&self, id: &str) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => {
+                let url = Url::parse(&format!("https://api.chess.com/pub/tournament/{}", id))?;
+                Ok(Request::new(Method::GET, url))
+            }
+            Api::LichessDotOrg => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/tournament/{id}".to_string(),
+                api: "lichess.org".to_string(),
+            }),
+        }
+    }
+
+    pub fn tournament_round(&self, id: &str, round: u32) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => {
+                let url = Url::parse(&format!(
+                    "https://api.chess.com/pub/tournament/{}/{}",
+                    id, round
+                ))?;
+                Ok(Request::new(Method::GET, url))
+            }
+            Api::LichessDotOrg => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/tournament/{id}/{round}".to_string(),
+                api: "lichess.org".to_string(),
+            }),
+        }
+    }
+
+    pub fn player_tournaments(&self, username: &str) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => {
+                let url = Url::parse(&format!(
+                    "https://api.chess.com/pub/player/{}/tournaments",
+                    username
+                ))?;
+                Ok(Request::new(Method::GET, url))
+            }
+            Api::LichessDotOrg => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/player/{user}/tournaments".to_string(),
+                api: "lichess.org".to_string(),
+            }),
+        }
+    }
+
+    pub fn player_status(&self, username: &str) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => {
+                let url = Url::parse(&format!(
+                    "https://www.chess.com/callback/user/popup/{}",
+                    username
+                ))?;
+                Ok(Request::new(Method::GET, url))
+            }
+            Api::LichessDotOrg => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/callback/user/popup/{user}".to_string(),
+                api: "lichess.org".to_string(),
+            }),
+        }
+    }
+
+    /// Import a game from its PGN, as the user of the OAuth token if one is given, or else
+    /// anonymously.
+    pub fn import_game(&self, pgn: &str, token: Option<&str>) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/import".to_string(),
+                api: "chess.com".to_string(),
+            }),
+            Api::LichessDotOrg => {
+                let url = Url::parse("https://lichess.org/api/import")?;
+                let mut request = Request::new(Method::POST, url);
+                let body = url::form_urlencoded::Serializer::new(String::new())
+                    .append_pair("pgn", pgn)
+                    .finish();
+                let headers = request.headers_mut();
+                headers.insert(
+                    CONTENT_TYPE,
+                    HeaderValue::from_static("application/x-www-form-urlencoded"),
+                );
+                if let Some(token) = token {
+                    headers.insert(
+                        AUTHORIZATION,
+                        HeaderValue::from_str(&format!("Bearer {}", token))?,
+                    );
+                }
+                *request.body_mut() = Some(body.into());
+                Ok(request)
+            }
+        }
+    }
+
+    pub fn rating_history(&self, username: &str) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/user/{user}/rating-history".to_string(),
+                api: "chess.com".to_string(),
+            }),
+            Api::LichessDotOrg => {
+                let url = Url::parse(&format!(
+                    "https://lichess.org/api/user/{}/rating-history",
+                    username
+                ))?;
+                Ok(Request::new(Method::GET, url))
+            }
+        }
+    }
+
+    pub fn ongoing_games(&self, username: &str) -> Result<Request, ApiError> {
+        match self {
+            Api::ChessDotCom => {
+                let url = Url::parse(&format!(
+                    "https://api.chess.com/pub/player/{}/games",
+                    username
+                ))?;
+                Ok(Request::new(Method::GET, url))
+            }
+            Api::LichessDotOrg => Err(ApiError::EndpointNotImplemented {
+                endpoint: "/{user}/games".to_string(),
+                api: "lichess.org".to_string(),
+            }),
+        }
+    }
+
     pub fn user_games(
         &self,
         username: &str,
         from: DateTime<Utc>,
         to: DateTime<Utc>,
+        perf_type: Option<&str>,
     ) -> Result<Request, ApiError> {
         match self {
             Api::ChessDotCom => {
@@ -250,14 +1143,17 @@ impl Api {
                 Ok(Request::new(Method::GET, url))
             }
             Api::LichessDotOrg => {
-                let params = [
-                    ("evals", "true"),
-                    ("pgnInJson", "true"),
-                    ("clocks", "true"),
-                    ("opening", "true"),
-                    ("since", &from.timestamp().to_string()),
-                    ("until", &to.timestamp().to_string()),
+                let mut params = vec![
+                    ("evals", "true".to_string()),
+                    ("pgnInJson", "true".to_string()),
+                    ("clocks", "true".to_string()),
+                    ("opening", "true".to_string()),
+                    ("since", from.timestamp_millis().to_string()),
+                    ("until", to.timestamp_millis().to_string()),
                 ];
+                if let Some(perf_type) = perf_type {
+                    params.push(("perfType", perf_type.to_string()));
+                }
                 let url = Url::parse_with_params(
                     &format!("https://lichess.org/api/games/user/{}", username),
                     &params,
@@ -273,20 +1169,31 @@ impl Api {
         }
     }
 
-    pub fn last_user_game(&self, username: &str) -> Result<Request, ApiError> {
+    /// Request the last max games of a user, or all of their games if max is None.
+    pub fn last_user_games(
+        &self,
+        username: &str,
+        max: Option<usize>,
+        perf_type: Option<&str>,
+    ) -> Result<Request, ApiError> {
         match self {
             Api::ChessDotCom => Err(ApiError::EndpointNotImplemented {
                 endpoint: "/{user}/games/archives".to_string(),
                 api: "chess.com".to_string(),
             }),
             Api::LichessDotOrg => {
-                let params = [
-                    ("evals", "true"),
-                    ("pgnInJson", "true"),
-                    ("clocks", "true"),
-                    ("opening", "true"),
-                    ("max", "1"),
+                let mut params = vec![
+                    ("evals", "true".to_string()),
+                    ("pgnInJson", "true".to_string()),
+                    ("clocks", "true".to_string()),
+                    ("opening", "true".to_string()),
                 ];
+                if let Some(max) = max {
+                    params.push(("max", max.to_string()));
+                }
+                if let Some(perf_type) = perf_type {
+                    params.push(("perfType", perf_type.to_string()));
+                }
                 let url = Url::parse_with_params(
                     &format!("https://lichess.org/api/games/user/{}", username),
                     &params,
@@ -319,6 +1226,149 @@ mod tests {
     use super::*;
     use chrono::TimeZone;
 
+    #[test]
+    fn test_capabilities() {
+        for capability in [
+            Capability::GameById,
+            Capability::PlayerGames,
+            Capability::RatingHistory,
+            Capability::Export,
+        ] {
+            assert!(Api::ChessDotCom.require(capability).is_ok());
+            assert!(Api::LichessDotOrg.require(capability).is_ok());
+        }
+        for capability in [
+            Capability::ListArchives,
+            Capability::OngoingGames,
+            Capability::Tournaments,
+            Capability::PlayerStatus,
+        ] {
+            assert!(Api::ChessDotCom.require(capability).is_ok());
+            assert!(matches!(
+                Api::LichessDotOrg.require(capability),
+                Err(ApiError::UnsupportedCapability { .. })
+            ));
+        }
+
+        let err = Api::LichessDotOrg
+            .require(Capability::OngoingGames)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "lichess.org doesn't support listing ongoing games. chess.com supports finding games \
+             by ID, finding games by player, listing archives, listing ongoing games, rating \
+             history, exporting games, listing tournaments, player status; lichess.org supports \
+             finding games by ID, finding games by player, rating history, exporting games"
+        );
+    }
+
+    #[test]
+    fn test_color_serde() {
+        assert_eq!(serde_json::to_string(&Color::White).unwrap(), r#""white""#);
+        assert_eq!(
+            serde_json::from_str::<Color>(r#""black""#).unwrap(),
+            Color::Black
+        );
+        assert!(serde_json::from_str::<Color>(r#""Black""#).is_err());
+        assert_eq!(Color::White.other(), Color::Black);
+        assert_eq!(Color::Black.other(), Color::White);
+        assert_eq!(Color::Black.to_string(), "black");
+    }
+
+    const CHESS_DOT_COM_GAME: &str = r#"{
+        "white": {"username": "Alice", "rating": 1500, "result": "win", "@id": "https://api.chess.com/pub/player/alice"},
+        "black": {"username": "Bob", "rating": 1500, "result": "resigned", "@id": "https://api.chess.com/pub/player/bob"},
+        "url": "https://www.chess.com/game/live/1234567890",
+        "fen": "",
+        "pgn": "1. e4 e5 1-0",
+        "start_time": 1612540000,
+        "end_time": 1612540800,
+        "time_control": "180",
+        "rules": "chess",
+        "eco": null,
+        "tournament": null,
+        "match": null
+    }"#;
+
+    const LICHESS_GAME: &str = r#"{
+        "id": "q7ZvsdUF",
+        "rated": true,
+        "variant": "standard",
+        "speed": "blitz",
+        "perf": "blitz",
+        "createdAt": 1514505150000,
+        "lastMoveAt": 1514505592000,
+        "status": "resign",
+        "winner": "white",
+        "players": {
+            "white": {"user": {"name": "Alice", "id": "alice"}, "rating": 1500, "ratingDiff": 8},
+            "black": {"user": {"name": "Bob", "id": "bob"}, "rating": 1500, "ratingDiff": -8}
+        },
+        "opening": {"eco": "C20", "name": "King's Pawn Game", "ply": 2},
+        "pgn": "1. e4 e5 1-0",
+        "clock": {"initial": 300, "increment": 3, "totalTime": 420},
+        "moves": "e4 e5"
+    }"#;
+
+    const LIVE_PLAYER: &str = r#"{
+        "username": "Alice", "rating": 1500, "id": 1, "isContentHidden": false,
+        "avatarUrl": "", "countryId": 1, "isEnabled": true, "canWinOnTime": true,
+        "chessTitle": null, "color": "white", "countryName": "", "defaultTab": 1,
+        "hasMovedAtLeastOnce": true, "isDrawable": false, "isOnline": false,
+        "isInLiveChess": false, "isTouchMove": false, "isVacation": false,
+        "isWhiteOnBottom": true, "lastLoginDate": 1612540000, "location": null,
+        "membershipLevel": null, "membershipCode": null, "memberSince": null,
+        "postMoveAction": "", "turnTimeRemaining": "", "flairCode": "",
+        "vacationRemaining": "", "gamesInProgress": 0
+    }"#;
+
+    /// Serialize a value, read it back, and serialize it again, which should be unchanged.
+    fn assert_round_trips<T: Serialize + DeserializeOwned>(value: &T) {
+        let serialized = serde_json::to_value(value).unwrap();
+        let read_back: T = serde_json::from_value(serialized.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&read_back).unwrap(), serialized);
+    }
+
+    #[test]
+    fn test_games_round_trip() {
+        let games = Games::ChessDotCom(vec![serde_json::from_str(CHESS_DOT_COM_GAME).unwrap()]);
+        assert_round_trips(&games);
+        let games = Games::LichessDotOrg(vec![serde_json::from_str(LICHESS_GAME).unwrap()]);
+        assert_round_trips(&games);
+    }
+
+    #[test]
+    fn test_game_json_is_the_json_of_the_api_game() {
+        let chess_dot_com: chessdotcom::Game = serde_json::from_str(CHESS_DOT_COM_GAME).unwrap();
+        let lichess: lichessdotorg::Game = serde_json::from_str(LICHESS_GAME).unwrap();
+        let games = [
+            (
+                Game::ChessDotCom(chess_dot_com.clone()),
+                serde_json::to_string(&chess_dot_com).unwrap(),
+                serde_json::to_string_pretty(&chess_dot_com).unwrap(),
+            ),
+            (
+                Game::LichessDotOrg(lichess.clone()),
+                serde_json::to_string(&lichess).unwrap(),
+                serde_json::to_string_pretty(&lichess).unwrap(),
+            ),
+        ];
+        for (game, json, pretty) in games.iter() {
+            assert_eq!(&game.to_json().unwrap(), json);
+            assert_eq!(&game.to_json_pretty().unwrap(), pretty);
+        }
+    }
+
+    #[test]
+    fn test_players_round_trip() {
+        let game: chessdotcom::Game = serde_json::from_str(CHESS_DOT_COM_GAME).unwrap();
+        assert_round_trips(&Player::ChessDotCom(game.white));
+        let player = Player::ChessDotComLive(serde_json::from_str(LIVE_PLAYER).unwrap());
+        assert_round_trips(&player);
+        let game: lichessdotorg::Game = serde_json::from_str(LICHESS_GAME).unwrap();
+        assert_round_trips(&Player::LichessDotOrg(game.players.black));
+    }
+
     #[test]
     fn test_month_string() {
         assert_eq!(month_string(10), "10".to_string());
@@ -353,6 +1403,31 @@ mod tests {
         assert_eq!(result.method(), &Method::GET);
     }
 
+    #[test]
+    fn test_lichess_dot_org_api_import_endpoint_request() {
+        let api = Api::from_str("lichess.org").expect("should not break");
+        let result = api.import_game("1. e4 e5 *", Some("lip_abc")).unwrap();
+        assert_eq!(
+            result.url(),
+            &Url::parse("https://lichess.org/api/import").unwrap()
+        );
+        assert_eq!(result.method(), &Method::POST);
+        assert_eq!(result.headers()[AUTHORIZATION], "Bearer lip_abc");
+        let body = result.body().and_then(|b| b.as_bytes()).unwrap();
+        assert_eq!(body, b"pgn=1.+e4+e5+*");
+
+        let result = api.import_game("1. e4 e5 *", None).unwrap();
+        assert!(result.headers().get(AUTHORIZATION).is_none());
+
+        let imported: lichessdotorg::ImportedGame =
+            serde_json::from_str(r#"{"id": "R6iLjwz5", "url": "https://lichess.org/R6iLjwz5"}"#)
+                .unwrap();
+        assert_eq!(imported.url, "https://lichess.org/R6iLjwz5");
+
+        let api = Api::from_str("chess.com").expect("should not break");
+        assert!(api.import_game("1. e4 e5 *", None).is_err());
+    }
+
     #[test]
     fn test_chess_dot_com_api_user_archives_endpoint_request() {
         let api = Api::from_str("chess.com").expect("should not break");
@@ -363,6 +1438,19 @@ mod tests {
         assert_eq!(result.method(), &Method::GET);
     }
 
+    #[test]
+    fn test_chess_dot_com_api_ongoing_games_endpoint_request() {
+        let api = Api::from_str("chess.com").expect("should not break");
+        // Parsing URL should not break
+        let expected = Url::parse("https://api.chess.com/pub/player/user1/games").unwrap();
+        let result = api.ongoing_games("user1").unwrap();
+        assert_eq!(result.url(), &expected);
+        assert_eq!(result.method(), &Method::GET);
+
+        let api = Api::from_str("lichess.org").expect("should not break");
+        assert!(api.ongoing_games("user1").is_err());
+    }
+
     #[test]
     fn test_chess_dot_com_api_user_games_endpoint_request() {
         let api = Api::from_str("chess.com").expect("should not break");
@@ -370,7 +1458,7 @@ mod tests {
         let to = Utc.ymd(2020, 10, 1).and_hms(0, 0, 0);
         // Parsing URL should not break
         let expected = Url::parse("https://api.chess.com/pub/player/user1/games/2020/09").unwrap();
-        let result = api.user_games("user1", from, to).unwrap();
+        let result = api.user_games("user1", from, to, None).unwrap();
         assert_eq!(result.url(), &expected);
         assert_eq!(result.method(), &Method::GET);
     }
@@ -381,12 +1469,163 @@ mod tests {
         let from = Utc.ymd(2020, 9, 1).and_hms(0, 0, 0);
         let to = Utc.ymd(2020, 10, 1).and_hms(0, 0, 0);
         // Parsing URL should not break
-        let expected = Url::parse("https://lichess.org/api/games/user/user1?evals=true&pgnInJson=true&clocks=true&opening=true&since=1598918400&until=1601510400").unwrap();
-        let result = api.user_games("user1", from, to).unwrap();
+        let expected = Url::parse("https://lichess.org/api/games/user/user1?evals=true&pgnInJson=true&clocks=true&opening=true&since=1598918400000&until=1601510400000").unwrap();
+        let result = api.user_games("user1", from, to, None).unwrap();
         assert_eq!(result.url(), &expected);
         assert_eq!(result.method(), &Method::GET);
     }
 
+    #[test]
+    fn test_lichess_dot_org_api_last_user_games_endpoint_request() {
+        let api = Api::from_str("lichess.org").expect("should not break");
+        // Parsing URL should not break
+        let expected = Url::parse("https://lichess.org/api/games/user/user1?evals=true&pgnInJson=true&clocks=true&opening=true&max=5").unwrap();
+        let result = api.last_user_games("user1", Some(5), None).unwrap();
+        assert_eq!(result.url(), &expected);
+        assert_eq!(result.method(), &Method::GET);
+    }
+
+    #[test]
+    fn test_lichess_dot_org_api_user_games_extra_params() {
+        let api = Api::from_str("lichess.org").expect("should not break");
+        let params = vec![
+            parse_lichess_param("tags=false").unwrap(),
+            parse_lichess_param("accuracy=true").unwrap(),
+            ("since".to_string(), "0".to_string()),
+            parse_lichess_param("note=a b&c").unwrap(),
+        ];
+        let request = with_lichess_params(
+            api.last_user_games("user1", Some(5), None).unwrap(),
+            &params,
+        );
+        let expected = Url::parse("https://lichess.org/api/games/user/user1?evals=true&pgnInJson=true&clocks=true&opening=true&max=5&tags=false&accuracy=true&note=a+b%26c").unwrap();
+        assert_eq!(request.url(), &expected);
+
+        assert!(parse_lichess_param("tags").is_err());
+        assert!(parse_lichess_param("=true").is_err());
+        assert!(parse_lichess_param("max=10").is_err());
+        assert_eq!(
+            parse_lichess_param("literate=true=1"),
+            Ok(("literate".to_string(), "true=1".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_lichess_dot_org_api_user_games_perf_type() {
+        let api = Api::from_str("lichess.org").expect("should not break");
+        let from = Utc.ymd(2020, 9, 1).and_hms(0, 0, 0);
+        let to = Utc.ymd(2020, 10, 1).and_hms(0, 0, 0);
+        let perf_type = lichess_perf_type(Some(Speed::Blitz), None);
+        let result = api
+            .user_games("user1", from, to, perf_type.as_deref())
+            .unwrap();
+        assert!(result
+            .url()
+            .query_pairs()
+            .any(|(k, v)| k == "perfType" && v == "blitz"));
+
+        let perf_type = lichess_perf_type(Some(Speed::Bullet), None);
+        let result = api
+            .last_user_games("user1", None, perf_type.as_deref())
+            .unwrap();
+        assert!(result
+            .url()
+            .query_pairs()
+            .any(|(k, v)| k == "perfType" && v == "ultraBullet,bullet"));
+
+        assert_eq!(
+            lichess_perf_type(Some(Speed::Blitz), Some("chess960")),
+            Some("chess960".to_string())
+        );
+        assert_eq!(
+            lichess_perf_type(Some(Speed::Rapid), Some("standard")),
+            Some("rapid".to_string())
+        );
+        assert_eq!(lichess_perf_type(None, None), None);
+    }
+
+    #[test]
+    fn test_outcome_from_result_code() {
+        assert_eq!(Outcome::from_result_code("win"), Some(Outcome::Win));
+        assert_eq!(Outcome::from_result_code("resigned"), Some(Outcome::Loss));
+        assert_eq!(Outcome::from_result_code("timeout"), Some(Outcome::Loss));
+        assert_eq!(Outcome::from_result_code("agreed"), Some(Outcome::Draw));
+        assert_eq!(
+            Outcome::from_result_code("timevsinsufficient"),
+            Some(Outcome::Draw)
+        );
+        assert_eq!(Outcome::from_result_code("unknown"), None);
+    }
+
+    #[test]
+    fn test_speed_from_seconds_boundaries() {
+        assert_eq!(Speed::from_seconds(0, 0), Speed::Unknown);
+        assert_eq!(Speed::from_seconds(60, 0), Speed::Bullet);
+        assert_eq!(Speed::from_seconds(179, 0), Speed::Bullet);
+        assert_eq!(Speed::from_seconds(180, 0), Speed::Blitz);
+        assert_eq!(Speed::from_seconds(120, 1), Speed::Bullet);
+        assert_eq!(Speed::from_seconds(120, 2), Speed::Blitz);
+        assert_eq!(Speed::from_seconds(599, 0), Speed::Blitz);
+        assert_eq!(Speed::from_seconds(600, 0), Speed::Rapid);
+        assert_eq!(Speed::from_seconds(1799, 0), Speed::Rapid);
+        assert_eq!(Speed::from_seconds(1800, 0), Speed::Classical);
+        assert_eq!(Speed::from_seconds(900, 30), Speed::Classical);
+        assert_eq!(Speed::from_seconds(60, u32::MAX), Speed::Classical);
+        assert_eq!(Speed::from_time_control("60+4294967295"), Speed::Classical);
+    }
+
+    #[test]
+    fn test_speed_from_time_control() {
+        assert_eq!(Speed::from_time_control("60"), Speed::Bullet);
+        assert_eq!(Speed::from_time_control("180+2"), Speed::Blitz);
+        assert_eq!(Speed::from_time_control("600"), Speed::Rapid);
+        assert_eq!(Speed::from_time_control("1800"), Speed::Classical);
+        assert_eq!(Speed::from_time_control("1/259200"), Speed::Correspondence);
+        assert_eq!(Speed::from_time_control("-"), Speed::Unknown);
+        assert_eq!(Speed::from_time_control(""), Speed::Unknown);
+    }
+
+    #[test]
+    fn test_time_class_from_time_control() {
+        assert_eq!(TimeClass::from_time_control("60"), Some(TimeClass::Bullet));
+        // Only the base time counts, unlike speeds
+        assert_eq!(
+            TimeClass::from_time_control("180+2"),
+            Some(TimeClass::Blitz)
+        );
+        assert_eq!(
+            TimeClass::from_time_control("120+2"),
+            Some(TimeClass::Bullet)
+        );
+        assert_eq!(Speed::from_time_control("120+2"), Speed::Blitz);
+        assert_eq!(TimeClass::from_time_control("600"), Some(TimeClass::Rapid));
+        assert_eq!(
+            TimeClass::from_time_control("1800"),
+            Some(TimeClass::Classical)
+        );
+        assert_eq!(TimeClass::from_time_control("1/259200"), None);
+        assert_eq!(TimeClass::from_time_control("-"), None);
+        assert_eq!(TimeClass::from_time_control(""), None);
+    }
+
+    #[test]
+    fn test_time_class_from_speed_name() {
+        assert_eq!(
+            TimeClass::from_speed_name("ultraBullet"),
+            Some(TimeClass::Bullet)
+        );
+        assert_eq!(TimeClass::from_speed_name("rapid"), Some(TimeClass::Rapid));
+        assert_eq!(TimeClass::from_speed_name("correspondence"), None);
+    }
+
+    #[test]
+    fn test_speed_from_name() {
+        assert_eq!(Speed::from_name("ultraBullet"), Speed::Bullet);
+        assert_eq!(Speed::from_name("blitz"), Speed::Blitz);
+        assert_eq!(Speed::from_name("correspondence"), Speed::Correspondence);
+        assert_eq!(Speed::from_name("chess960"), Speed::Unknown);
+    }
+
     #[test]
     #[should_panic]
     fn test_unsupported_api() {
@@ -0,0 +1,224 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+use super::{chessdotcom, ChessGame, ChessPlayer, Color, Outcome, Speed};
+use crate::utils::{eco_name, movetext, pgn_tag, Termination};
+
+/// What identifies a game regardless of the API it was found in: the players, the day it ended,
+/// and its result. Used to recognize the same game when it was played on one site and imported
+/// into the other, as URLs differ.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GameIdentity {
+    pub white: String,
+    pub black: String,
+    pub date: NaiveDate,
+    pub white_outcome: Option<Outcome>,
+}
+
+impl GameIdentity {
+    pub fn of(game: &mut impl ChessGame) -> Self {
+        let white = game.white().name().to_lowercase();
+        GameIdentity {
+            white_outcome: game.result_for(&white),
+            black: game.black().name().to_lowercase(),
+            date: game.end_time().date().naive_utc(),
+            white,
+        }
+    }
+}
+
+/// A player as seen from any API, with missing information omitted when serialized.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NormalizedPlayer {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+}
+
+impl NormalizedPlayer {
+    pub fn from_player(player: &impl ChessPlayer) -> Self {
+        NormalizedPlayer {
+            name: player.name(),
+            title: player.title(),
+            rating: player.rating(),
+            result: player.result(),
+        }
+    }
+}
+
+/// An API agnostic view of a chess game. Unlike the API specific structs, the set of fields is
+/// the same regardless of where the game came from, and fields that are missing or empty are
+/// omitted when serialized.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NormalizedGame {
+    pub url: String,
+    pub white: NormalizedPlayer,
+    pub black: NormalizedPlayer,
+    pub end_time: DateTime<Utc>,
+    pub speed: Speed,
+    #[serde(default)]
+    pub termination: Termination,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eco: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opening: Option<String>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub pgn: String,
+}
+
+impl NormalizedGame {
+    pub fn from_game(game: &mut impl ChessGame) -> Self {
+        NormalizedGame {
+            url: game.url(),
+            white: NormalizedPlayer::from_player(&game.white()),
+            black: NormalizedPlayer::from_player(&game.black()),
+            end_time: game.end_time(),
+            speed: game.speed(),
+            termination: game.termination(),
+            eco: game.eco(),
+            opening: game.opening(),
+            pgn: game.pgn(),
+        }
+    }
+
+    /// A chess.com game can be found in a player's archives or looked up by ID, and each returns
+    /// a different payload. Both are normalized from the data they have in common: the opening
+    /// comes from the ECO code, and the PGN is rebuilt from the moves, so that the same game
+    /// looks the same regardless of how it was found.
+    fn from_chessdotcom(
+        game: &mut impl ChessGame,
+        url: String,
+        results: (String, String),
+        result: &str,
+    ) -> Self {
+        let mut white = NormalizedPlayer::from_player(&game.white());
+        white.result = Some(results.0);
+        let mut black = NormalizedPlayer::from_player(&game.black());
+        black.result = Some(results.1);
+
+        NormalizedGame {
+            url,
+            white,
+            black,
+            end_time: game.end_time(),
+            speed: game.speed(),
+            termination: game.termination(),
+            eco: game.eco(),
+            opening: game
+                .eco()
+                .and_then(|eco| eco_name(&eco))
+                .map(|name| name.to_string()),
+            pgn: movetext(&game.moves(), result),
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+impl From<&chessdotcom::Game> for NormalizedGame {
+    fn from(game: &chessdotcom::Game) -> Self {
+        let results = (game.white.result.clone(), game.black.result.clone());
+        let result = pgn_tag(&game.pgn, "Result").unwrap_or_else(|| "*".to_string());
+        NormalizedGame::from_chessdotcom(&mut game.clone(), game.url.clone(), results, &result)
+    }
+}
+
+impl From<&chessdotcom::CallbackLiveGame> for NormalizedGame {
+    fn from(game: &chessdotcom::CallbackLiveGame) -> Self {
+        let results = (
+            game.get_result_code(Color::White),
+            game.get_result_code(Color::Black),
+        );
+        // Live game URLs redirect to the same URL archives use
+        let url = format!("https://www.chess.com/game/live/{}", game.game.id);
+        let result = game.game.pgn_headers.result.clone();
+        NormalizedGame::from_chessdotcom(&mut game.clone(), url, results, &result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::lichessdotorg;
+
+    const LICHESS_GAME: &str = r#"{
+        "id": "q7ZvsdUF",
+        "rated": true,
+        "variant": "standard",
+        "speed": "blitz",
+        "perf": "blitz",
+        "createdAt": 1514505150384,
+        "lastMoveAt": 1514505592843,
+        "status": "resign",
+        "players": {
+            "white": {"user": {"name": "Lance5500", "title": "LM", "id": "lance5500"}, "rating": 2389, "ratingDiff": 4},
+            "black": {"user": {"name": "TryingHard87", "id": "tryinghard87"}, "rating": 2498, "ratingDiff": -4}
+        },
+        "pgn": "1. d4 d5 2. c4 c6 1-0",
+        "clock": {"initial": 300, "increment": 3, "totalTime": 420},
+        "moves": "d4 d5 c4 c6"
+    }"#;
+
+    #[test]
+    fn test_same_game_on_both_apis_has_the_same_identity() {
+        let chessdotcom_json = r#"{
+            "white": {"username": "Lance5500", "rating": 2389, "result": "win", "@id": ""},
+            "black": {"username": "TryingHard87", "rating": 2498, "result": "resigned", "@id": ""},
+            "url": "https://www.chess.com/game/live/1234567890",
+            "fen": "",
+            "pgn": "1. d4 d5 2. c4 c6 1-0",
+            "end_time": 1514505592,
+            "time_control": "300+3",
+            "rules": "chess",
+            "tournament": null,
+            "match": null
+        }"#;
+        let mut chessdotcom_game: chessdotcom::Game =
+            serde_json::from_str(chessdotcom_json).unwrap();
+        let mut lichess_game: lichessdotorg::Game = serde_json::from_str(LICHESS_GAME).unwrap();
+        lichess_game.last_move_at = chessdotcom_game.end_time - chrono::Duration::hours(1);
+        lichess_game.winner = Some(Color::White);
+
+        assert_eq!(
+            GameIdentity::of(&mut chessdotcom_game),
+            GameIdentity::of(&mut lichess_game)
+        );
+
+        lichess_game.winner = Some(Color::Black);
+        assert_ne!(
+            GameIdentity::of(&mut chessdotcom_game),
+            GameIdentity::of(&mut lichess_game)
+        );
+
+        lichess_game.winner = Some(Color::White);
+        lichess_game.last_move_at = lichess_game.last_move_at + chrono::Duration::days(1);
+        assert_ne!(
+            GameIdentity::of(&mut chessdotcom_game),
+            GameIdentity::of(&mut lichess_game)
+        );
+    }
+
+    #[test]
+    fn test_compact_json_omits_missing_fields() {
+        let mut game: lichessdotorg::Game = serde_json::from_str(LICHESS_GAME).unwrap();
+        let normalized = NormalizedGame::from_game(&mut game);
+        let json = normalized.to_json().unwrap();
+
+        assert!(!json.contains("\"opening\""));
+        assert!(!json.contains("\"eco\""));
+        assert!(!json.contains("\"result\""));
+        assert!(!json.contains("null"));
+        assert!(json.contains("\"title\":\"LM\""));
+        assert!(json.contains("\"speed\":\"blitz\""));
+        assert!(json.contains("\"termination\":\"resignation\""));
+        assert_eq!(normalized.white.rating, Some(2389));
+        assert_eq!(normalized.black.title, None);
+    }
+}
@@ -0,0 +1,55 @@
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{de, Deserialize, Deserializer};
+
+/// A date as the APIs send it, a timestamp in seconds for chess.com or in milliseconds for
+/// lichess, or as games are serialized, an RFC 3339 string, so that serialized games can be
+/// read back.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Timestamp {
+    Number(i64),
+    Rfc3339(String),
+}
+
+impl Timestamp {
+    fn into_date<E: de::Error>(self, millis: bool) -> Result<DateTime<Utc>, E> {
+        match self {
+            Timestamp::Number(n) => {
+                let date = if millis {
+                    Utc.timestamp_millis_opt(n)
+                } else {
+                    Utc.timestamp_opt(n, 0)
+                };
+                date.single()
+                    .ok_or_else(|| E::custom(format!("invalid timestamp {}", n)))
+            }
+            Timestamp::Rfc3339(date) => DateTime::parse_from_rfc3339(&date)
+                .map(|d| d.with_timezone(&Utc))
+                .map_err(E::custom),
+        }
+    }
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Timestamp::deserialize(deserializer)?.into_date(false)
+}
+
+pub fn deserialize_option<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<Timestamp>::deserialize(deserializer)?
+        .map(|t| t.into_date(false))
+        .transpose()
+}
+
+/// Like deserialize, for timestamps in milliseconds.
+pub fn deserialize_millis<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Timestamp::deserialize(deserializer)?.into_date(true)
+}
@@ -1,8 +1,22 @@
-use cgf::{cli::ChessGameFinderCLI, error::ChessError};
+use cgf::{cli::ChessGameFinderCLI, client::ClientError, displayer, error::ChessError, finder};
 
 fn main() -> Result<(), ChessError> {
+    if std::env::var_os(displayer::CLIPBOARD_HOLDER_ENV).is_some() {
+        return displayer::serve_clipboard();
+    }
     openssl_probe::init_ssl_cert_env_vars();
     env_logger::init();
+    if let Err(e) = finder::install_interrupt_handler() {
+        log::warn!("Failed to install Ctrl-C handler: {}", e);
+    }
     let cli = ChessGameFinderCLI::new();
-    cli.run()
+    match cli.run() {
+        Ok(Some(code)) => std::process::exit(code),
+        Ok(None) => Ok(()),
+        Err(ChessError::ChessClientError(e @ ClientError::ProfileRestricted { .. })) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        Err(e) => Err(e),
+    }
 }
@@ -0,0 +1,197 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use log;
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+/// The month games are still being added to, as (year, month). chess.com buckets its archives
+/// by UTC, so the month is taken in UTC whatever the timezone of now: near the end of a month, a
+/// timezone ahead of UTC may already be in the next one while the archive is still open.
+pub fn current_month<Tz: TimeZone>(now: &DateTime<Tz>) -> (u32, u32) {
+    let now = now.with_timezone(&Utc);
+    (now.year() as u32, now.month())
+}
+
+/// Whether a month is over, so that no more games will be added to its archive.
+pub fn month_is_over<Tz: TimeZone>(month: (u32, u32), now: &DateTime<Tz>) -> bool {
+    month < current_month(now)
+}
+
+/// Months in which a player played each of their opponents, learned from the months of games
+/// fetched so far. Lets searches for an opponent skip the months already known to have no games
+/// against them. Only months that are over are recorded, as more games may still be played in
+/// the current one.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct OpponentIndex {
+    /// Months whose games have been fetched, as (year, month).
+    scanned: BTreeSet<(u32, u32)>,
+    /// Months with at least one game against each opponent, by lowercase opponent name.
+    opponents: BTreeMap<String, BTreeSet<(u32, u32)>>,
+}
+
+impl OpponentIndex {
+    /// Where the index of a player in an API is stored under the cache dir.
+    pub fn path(dir: &Path, api: &str, player: &str) -> PathBuf {
+        dir.join(format!("opponents_{}_{}.json", api, player.to_lowercase()))
+    }
+
+    /// Load an index, starting an empty one if there is none or it can't be read.
+    pub fn load(path: &Path) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return OpponentIndex::default(),
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable opponent cache {:?}: {}", path, e);
+            OpponentIndex::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// Record the opponents of every game of a month that is over.
+    pub fn record<I: IntoIterator<Item = String>>(&mut self, month: (u32, u32), opponents: I) {
+        self.scanned.insert(month);
+        for opponent in opponents {
+            self.opponents
+                .entry(opponent.to_lowercase())
+                .or_default()
+                .insert(month);
+        }
+    }
+
+    /// Whether the month is known to have no games against any of the opponents. Nothing can be
+    /// skipped when no opponents are searched.
+    pub fn can_skip(&self, month: (u32, u32), opponents: &[String]) -> bool {
+        if opponents.is_empty() || !self.scanned.contains(&month) {
+            return false;
+        }
+        opponents
+            .iter()
+            .all(|o| match self.opponents.get(&o.to_lowercase()) {
+                Some(months) => !months.contains(&month),
+                None => true,
+            })
+    }
+}
+
+/// A response saved with the ETag the API sent along with it.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct CachedResponse {
+    pub etag: String,
+    pub body: String,
+}
+
+/// Responses saved under the cache dir by request, so that repeating a request can ask the API
+/// to only send the body again if it changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    pub fn new(dir: PathBuf) -> Self {
+        ResponseCache { dir }
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(format!("{}.etag.json", name))
+    }
+
+    /// The cached response to a request, if there is one that can be read.
+    pub fn get(&self, name: &str) -> Option<CachedResponse> {
+        let path = self.path(name);
+        let contents = fs::read_to_string(&path).ok()?;
+        serde_json::from_str(&contents)
+            .map_err(|e| log::warn!("Ignoring unreadable cached response {:?}: {}", path, e))
+            .ok()
+    }
+
+    pub fn put(&self, name: &str, response: &CachedResponse) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(name), serde_json::to_string(response)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skips_scanned_months_without_the_opponents() {
+        let mut index = OpponentIndex::default();
+        index.record((2021, 1), vec!["Alice".to_string(), "bob".to_string()]);
+        index.record((2021, 2), vec!["carol".to_string()]);
+
+        let alice = vec!["alice".to_string()];
+        assert!(!index.can_skip((2021, 1), &alice));
+        assert!(index.can_skip((2021, 2), &alice));
+        // Months never fetched may have games against anyone
+        assert!(!index.can_skip((2021, 3), &alice));
+        assert!(!index.can_skip((2021, 2), &[]));
+
+        let alice_or_carol = vec!["alice".to_string(), "carol".to_string()];
+        assert!(!index.can_skip((2021, 2), &alice_or_carol));
+    }
+
+    #[test]
+    fn test_current_month_is_taken_in_utc() {
+        use chrono::FixedOffset;
+
+        // The last day of January in UTC is already February 1st at +13
+        let ahead = FixedOffset::east_opt(13 * 3600).unwrap();
+        let now = ahead.ymd(2021, 2, 1).and_hms(10, 0, 0);
+        assert_eq!(current_month(&now), (2021, 1));
+        assert!(!month_is_over((2021, 1), &now));
+        assert!(month_is_over((2020, 12), &now));
+
+        // Once January is over in UTC, it is over at +13 too
+        let now = ahead.ymd(2021, 2, 1).and_hms(13, 0, 0);
+        assert_eq!(current_month(&now), (2021, 2));
+        assert!(month_is_over((2021, 1), &now));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("cgf_cache_test_{}", std::process::id()));
+        let path = OpponentIndex::path(&dir, "chess.com", "A_Player");
+        assert_eq!(
+            path.file_name().unwrap(),
+            "opponents_chess.com_a_player.json"
+        );
+        assert_eq!(OpponentIndex::load(&path), OpponentIndex::default());
+
+        let mut index = OpponentIndex::default();
+        index.record((2020, 12), vec!["bob".to_string()]);
+        index.save(&path).unwrap();
+        assert_eq!(OpponentIndex::load(&path), index);
+
+        fs::write(&path, "not json").unwrap();
+        assert_eq!(OpponentIndex::load(&path), OpponentIndex::default());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_response_cache() {
+        let dir = std::env::temp_dir().join(format!("cgf_etag_test_{}", std::process::id()));
+        let cache = ResponseCache::new(dir.clone());
+        assert_eq!(cache.get("chesscom_archives_user.json"), None);
+
+        let response = CachedResponse {
+            etag: "\"abc\"".to_string(),
+            body: "{}".to_string(),
+        };
+        cache.put("chesscom_archives_user.json", &response).unwrap();
+        assert_eq!(cache.get("chesscom_archives_user.json"), Some(response));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,14 +1,213 @@
-use clap::{App, Arg, ArgGroup};
+use clap::{App, AppSettings, Arg, ArgGroup, SubCommand};
+use std::cell::RefCell;
+use std::collections::HashMap;
 use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDate, Utc, Weekday};
 
-use crate::displayer::GameDisplayer;
+use crate::api::{
+    normalized::NormalizedGame, parse_lichess_param, Api, Capability, ChessGame, Game, Outcome,
+    Speed, TimeClass,
+};
+use crate::client::skipped_games;
+use crate::database::GameDatabase;
+use crate::displayer::{
+    analysis_urls, boards_every_move, explain_pgn, format_rating_gap, game_line, ongoing_table,
+    rating_gap, tournaments_table, BoardSteps, ClipboardWriter, GameDisplayer, OutputEncoding,
+    TableColumn, DEFAULT_COLUMNS,
+};
 use crate::error::ChessError;
-use crate::finder::{GameFinder, Search};
+use crate::finder::{interrupted, DedupeKey, GameFinder, GroupKey, Search};
+use crate::report::{group_table, rating_history_csv, Repertoire};
+use crate::sample::clock_seed;
+use crate::template::Template;
+use crate::utils::{parse_date, parse_duration, parse_fen, strip_annotations, truncate_pgn};
+
+/// Exit code used by --exit-code when no game matches the search.
+const NOT_FOUND_EXIT_CODE: i32 = 3;
+
+/// Exit code used by --exit-code when the game found has no outcome for the player, e.g. it is
+/// still being played, was aborted, or the player's color is unknown.
+const UNKNOWN_OUTCOME_EXIT_CODE: i32 = 4;
+
+/// Seconds between polls of --watch when no interval is given.
+const DEFAULT_WATCH_INTERVAL: u64 = 60;
+
+/// Shortest interval between polls allowed by --watch, to avoid hammering the APIs.
+const MIN_WATCH_INTERVAL: u64 = 10;
+
+/// How many of the latest games --watch requests on every poll.
+const WATCH_BATCH_SIZE: usize = 10;
+
+/// Fail to parse the arguments when the api doesn't support the capability, before searching.
+fn require_capability(api: &str, capability: Capability) -> Result<(), clap::Error> {
+    Api::from_str(api)
+        .and_then(|api| api.require(capability))
+        .map_err(|e| {
+            clap::Error::with_description(&e.to_string(), clap::ErrorKind::ArgumentConflict)
+        })
+}
+
+/// chess.com live game IDs are at least this long, so shorter all-digit values are assumed to be
+/// numeric usernames.
+const MIN_GAME_ID_LENGTH: usize = 10;
+
+/// Whether a PLAYER_OR_ID value should be treated as a game ID: it must be all digits and at
+/// least MIN_GAME_ID_LENGTH long.
+fn looks_like_game_id(player_or_id: &str) -> bool {
+    player_or_id.len() >= MIN_GAME_ID_LENGTH && player_or_id.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Trim a username given on the command line, and reject it if it has characters that neither
+/// chess.com nor lichess.org allow in usernames: anything but ASCII letters, digits, - and _.
+fn clean_username(username: &str) -> Result<&str, String> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err("usernames can't be empty".to_string());
+    }
+    match username
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || *c == '_'))
+    {
+        Some(c) => Err(format!(
+            "{:?} is not a valid username, usernames can't contain {:?}",
+            username, c
+        )),
+        None => Ok(username),
+    }
+}
+
+/// lichess game IDs are this long. Game URLs may add four more characters for the player's color.
+const LICHESS_GAME_ID_LENGTH: usize = 8;
+
+/// APIs games can be looked up in by ID.
+const APIS: &[&str] = &["chess.com", "lichess.org"];
+
+const TEMPLATE_FILE_HELP: &str = "Output each game rendered with the template in FILE, text with placeholders in braces replaced by the values of the game: {white}, {black}, {white_rating}, {black_rating}, {result}, {date}, {speed}, {variant}, {eco}, {opening}, {url}, {moves}, and {pgn}. Write {{ and }} for literal braces.";
+
+/// Titles players can have on chess.com and lichess.org.
+const TITLES: [&str; 12] = [
+    "GM", "IM", "FM", "CM", "NM", "WGM", "WIM", "WFM", "WCM", "WNM", "LM", "BOT",
+];
+
+/// Parse an entry of --ids-file into its API and game ID. Entries may be prefixed with their API,
+/// like chess.com:1234567890, or else the API is told by the shape of the ID: all digits and at
+/// least MIN_GAME_ID_LENGTH long for chess.com, or LICHESS_GAME_ID_LENGTH alphanumeric characters
+/// with at least one letter for lichess.org. Anything else is ambiguous and must be prefixed.
+fn parse_id_entry(entry: &str) -> Result<(&'static str, String), String> {
+    let entry = entry.trim();
+    if let Some((prefix, id)) = entry.split_once(':') {
+        return match APIS.iter().find(|api| **api == prefix.trim()) {
+            Some(api) if !id.trim().is_empty() => Ok((api, id.trim().to_string())),
+            Some(_) => Err(format!("no game ID after {}", prefix)),
+            None => Err(format!(
+                "unknown API {}, use one of: {}",
+                prefix,
+                APIS.join(", ")
+            )),
+        };
+    }
+
+    if looks_like_game_id(entry) {
+        Ok(("chess.com", entry.to_string()))
+    } else if entry.len() == LICHESS_GAME_ID_LENGTH
+        && entry.chars().all(|c| c.is_ascii_alphanumeric())
+        && entry.chars().any(|c| c.is_ascii_alphabetic())
+    {
+        Ok(("lichess.org", entry.to_string()))
+    } else {
+        Err(format!(
+            "can't tell the API of game ID {}, prefix it with chess.com: or lichess.org:",
+            entry
+        ))
+    }
+}
+
+/// Read the entries of an --ids-file, one per line, skipping blank lines and # comments.
+fn read_ids_file(path: &Path) -> Result<Vec<(&'static str, String)>, ChessError> {
+    let contents = fs::read_to_string(path)?;
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| {
+            parse_id_entry(line).map_err(|e| {
+                ChessError::ValidationError(format!("{}:{}: {}", path.display(), i + 1, e))
+            })
+        })
+        .collect()
+}
+
+/// How a display mode writes the multiple games of --all, --last, --since-game-id, --watch and
+/// --ids-file.
+#[derive(Debug, PartialEq)]
+enum MultiGame {
+    /// One game after another, separated by a blank line.
+    Separated,
+    /// One game per line, as newline delimited JSON.
+    OnePerLine,
+    /// Several games can't be told apart, with a suggestion of a display mode that can.
+    Unsupported(&'static str),
+}
+
+fn multi_game_support(output: &str) -> MultiGame {
+    match output {
+        "table" | "report" | "pgn" | "minimal-pgn" | "headers" | "analysis-url" => {
+            MultiGame::Separated
+        }
+        "json" | "json-compact" | "clocks-json" | "headers-json" | "uci" | "line" => {
+            MultiGame::OnePerLine
+        }
+        "json-pretty" => MultiGame::Unsupported(
+            "as several pretty printed games are not valid JSON. Use --json or --json-compact for one game per line instead",
+        ),
+        "evals" => MultiGame::Unsupported(
+            "as the games can't be told apart. Use --pgn, which includes the evaluations, or --report instead",
+        ),
+        _ => MultiGame::Separated,
+    }
+}
 
 pub struct ChessGameFinderCLI {
     output: String,
+    raw: bool,
+    ongoing: bool,
+    list_archives: bool,
+    validate: bool,
+    exit_code: bool,
+    repertoire: Option<Option<usize>>,
+    group_by: Option<GroupKey>,
+    rating_history: Option<Option<Speed>>,
+    export: Option<(PathBuf, bool)>,
+    import_to_lichess: bool,
+    lichess_token: Option<String>,
+    tournaments: bool,
+    status: bool,
+    sqlite: Option<PathBuf>,
+    ids_file: Option<PathBuf>,
+    watch: Option<u64>,
+    encoding: OutputEncoding,
+    clipboard: bool,
+    output_file: Option<PathBuf>,
+    color: bool,
+    columns: Vec<TableColumn>,
+    compare_ratings: bool,
+    template: Option<Template>,
+    seed_position: Option<String>,
+    truncate_moves: Option<u32>,
+    strip_annotations: bool,
+    ply: Option<usize>,
+    board_steps: BoardSteps,
+    all: bool,
+    /// Usernames resolved by --normalize-usernames, by api and lowercased username, so each
+    /// player's profile is requested once per run.
+    usernames: Option<RefCell<HashMap<(String, String), String>>>,
     finder: GameFinder,
 }
 
@@ -22,18 +221,162 @@ impl ChessGameFinderCLI {
         I: Iterator<Item = T>,
         T: Into<OsString> + Clone,
     {
-        let displays = &["pgn", "json-pretty", "json"];
+        let displays = &[
+            "pgn",
+            "minimal-pgn",
+            "uci",
+            "line",
+            "analysis-url",
+            "board-every-move",
+            "explain-pgn",
+            "json-pretty",
+            "json",
+            "json-compact",
+            "clocks-json",
+            "headers-json",
+            "headers",
+            "evals",
+            "report",
+            "template-file",
+        ];
 
         let app = App::new("Chess game finder")
         .version("0.3.4")
         .author("Tomas Farias <tomas@tomasfarias.dev>")
         .about("Finds games using online chess APIs")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("rating-history")
+                .about("Print a player's rating over time as CSV, oldest first")
+                .arg(
+                    Arg::with_name("username")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("USERNAME")
+                        .help("The player's username"),
+                )
+                .arg(
+                    Arg::with_name("api")
+                        .long("api")
+                        .short("a")
+                        .takes_value(true)
+                        .default_value("chess.com")
+                        .possible_values(&["chess.com", "lichess.org"])
+                        .help("Choose the API where to find the player's ratings."),
+                )
+                .arg(
+                    Arg::with_name("speed")
+                        .long("speed")
+                        .takes_value(true)
+                        .possible_values(&["bullet", "blitz", "rapid", "classical", "correspondence"])
+                        .help("Only include ratings of games of this speed"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("tournaments")
+                .about("List the chess.com tournaments a player registered for, is playing, or played")
+                .arg(
+                    Arg::with_name("username")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("USERNAME")
+                        .help("The player's username"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("status")
+                .about("Show whether a chess.com player is online and playing live chess")
+                .arg(
+                    Arg::with_name("username")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("USERNAME")
+                        .help("The player's username"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("export")
+                .about("Write every game of a player into a directory, one PGN file per month")
+                .arg(
+                    Arg::with_name("username")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("USERNAME")
+                        .help("The player's username"),
+                )
+                .arg(
+                    Arg::with_name("api")
+                        .long("api")
+                        .short("a")
+                        .takes_value(true)
+                        .default_value("chess.com")
+                        .possible_values(&["chess.com", "lichess.org"])
+                        .help("Choose the API where to export the player's games from."),
+                )
+                .arg(
+                    Arg::with_name("to")
+                        .long("to")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("DIR")
+                        .help("Directory to write the PGN files to, created if it doesn't exist"),
+                )
+                .arg(
+                    Arg::with_name("resume")
+                        .long("resume")
+                        .takes_value(false)
+                        .help("Skip the months a previous export to DIR already wrote, continuing an interrupted export"),
+                )
+                .arg(
+                    Arg::with_name("progress")
+                        .long("progress")
+                        .takes_value(false)
+                        .help("Report the progress of the export on stderr"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import-to-lichess")
+                .about("Import a game into lichess.org from its PGN, and print the link to the imported game")
+                .arg(
+                    Arg::with_name("id")
+                        .takes_value(true)
+                        .required(true)
+                        .value_name("ID")
+                        .help("The ID of the game to import"),
+                )
+                .arg(
+                    Arg::with_name("api")
+                        .long("api")
+                        .short("a")
+                        .takes_value(true)
+                        .default_value("chess.com")
+                        .possible_values(&["chess.com", "lichess.org"])
+                        .help("Choose the API where to find the game."),
+                )
+                .arg(
+                    Arg::with_name("token")
+                        .long("token")
+                        .takes_value(true)
+                        .value_name("TOKEN")
+                        .env("LICHESS_TOKEN")
+                        .hide_env_values(true)
+                        .help("lichess.org OAuth token, to import the game as its user instead of anonymously"),
+                ),
+        )
         .arg(
             Arg::with_name("player_or_id")
                 .takes_value(true)
-                .required(true)
+                .required_unless("ids-file")
                 .value_name("PLAYER_OR_ID")
-                .help("A Game ID or a player's username whose game to look for. If it contains all digits, will assume it's a Game ID unless the --player flag is used."),
+                .help("A Game ID or a player's username whose game to look for. If it contains all digits and is at least 10 characters long, will assume it's a Game ID unless the --player flag is used."),
+        )
+        .arg(
+            Arg::with_name("ids-file")
+                .long("ids-file")
+                .takes_value(true)
+                .value_name("PATH")
+                .conflicts_with_all(&["player_or_id", "player", "last", "watch", "raw", "ongoing", "validate", "exit-code", "repertoire", "group-by"])
+                .help("Find the games of the IDs in the file at PATH, one per line. Each ID may be prefixed with its API, like chess.com:1234567890 or lichess.org:q7ZvsdUF, or else the API is told by the shape of the ID: long numbers are chess.com games, and 8 letters and digits are lichess.org games. Blank lines and lines starting with # are skipped."),
         )
         .arg(
             Arg::with_name("player")
@@ -77,18 +420,270 @@ impl ChessGameFinderCLI {
                 .takes_value(false)
                 .help("Output game as pretty JSON"),
         )
+        .arg(
+            Arg::with_name("json-compact")
+                .long("json-compact")
+                .takes_value(false)
+                .help("Output game as compact JSON with a stable set of fields, omitting missing ones"),
+        )
+        .arg(
+            Arg::with_name("clocks-json")
+                .long("clocks-json")
+                .takes_value(false)
+                .help("Output the moves of the game as JSON objects with the ply, the SAN move, and the seconds left on the clock of the player who made it"),
+        )
+        .arg(
+            Arg::with_name("headers-json")
+                .long("headers-json")
+                .takes_value(false)
+                .help("Output the tag pairs of the PGN of the game as a JSON object of tag names to values"),
+        )
+        .arg(
+            Arg::with_name("headers")
+                .long("headers")
+                .takes_value(false)
+                .help("Output only the tag pairs of the PGN of the game: the Seven Tag Roster, ECO, TimeControl, and Link, for a catalog of games without their moves"),
+        )
         .arg(
             Arg::with_name("pgn")
                 .long("pgn")
                 .takes_value(false)
                 .help("Output game PGN string"),
         )
+        .arg(
+            Arg::with_name("minimal-pgn")
+                .long("minimal-pgn")
+                .takes_value(false)
+                .help("Output game PGN string with only the Seven Tag Roster (Event, Site, Date, Round, White, Black, Result), using '?' for unknown values"),
+        )
+        .arg(
+            Arg::with_name("uci")
+                .long("uci")
+                .takes_value(false)
+                .help("Output the moves of the game in UCI notation (e.g. e2e4), separated by spaces, to feed them to an engine"),
+        )
+        .arg(
+            Arg::with_name("line")
+                .long("line")
+                .takes_value(false)
+                .help("Output each game in a single line, led by a glyph of the player's result: ✓ for a win, ✗ for a loss, and = for a draw"),
+        )
+        .arg(
+            Arg::with_name("analysis-url")
+                .long("analysis-url")
+                .takes_value(false)
+                .help("Output the FEN of the final position of the game, or of the position at --ply, with links to it in the lichess.org and chess.com analysis boards"),
+        )
+        .arg(
+            Arg::with_name("ply")
+                .long("ply")
+                .takes_value(true)
+                .value_name("N")
+                .requires("analysis-url")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Show the position after the first N half-moves with --analysis-url. Positions past the end of the game are its final position."),
+        )
+        .arg(
+            Arg::with_name("board-every-move")
+                .long("board-every-move")
+                .takes_value(false)
+                .help("Output each move of the game followed by the board after it, e.g. for step-by-step reviews. Limit the moves with --from-ply and --to-ply."),
+        )
+        .arg(
+            Arg::with_name("explain-pgn")
+                .long("explain-pgn")
+                .takes_value(false)
+                .hidden(true)
+                .help("Output each move of a chess.com live game as it is decoded from chess.com's move encoding: its characters, squares, SAN, and any capture, castle, or promotion. For troubleshooting the decoder."),
+        )
+        .arg(
+            Arg::with_name("from-ply")
+                .long("from-ply")
+                .takes_value(true)
+                .value_name("N")
+                .requires("board-every-move")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Start --board-every-move at the board after the Nth half-move"),
+        )
+        .arg(
+            Arg::with_name("to-ply")
+                .long("to-ply")
+                .takes_value(true)
+                .value_name("N")
+                .requires("board-every-move")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("End --board-every-move at the board after the Nth half-move"),
+        )
+        .arg(
+            Arg::with_name("fancy-board")
+                .long("fancy-board")
+                .takes_value(false)
+                .requires("board-every-move")
+                .help("Draw the boards of --board-every-move with chess symbols instead of letters"),
+        )
+        .arg(
+            Arg::with_name("no-color")
+                .long("no-color")
+                .takes_value(false)
+                .help("Don't color the result glyphs of --line. They are only colored when writing to a terminal and NO_COLOR isn't set."),
+        )
+        .arg(
+            Arg::with_name("evals")
+                .long("evals")
+                .takes_value(false)
+                .help("Output the moves of the game annotated with computer evaluations (lichess.org games with analysis only)"),
+        )
+        .arg(
+            Arg::with_name("report")
+                .long("report")
+                .takes_value(false)
+                .help("Output a Markdown document with a section per game containing its summary table and PGN"),
+        )
+        .arg(
+            Arg::with_name("template-file")
+                .long("template-file")
+                .takes_value(true)
+                .value_name("FILE")
+                .help(TEMPLATE_FILE_HELP),
+        )
         .group(
             ArgGroup::with_name("display")
                 .args(displays)
                 .multiple(false)
                 .required(false),
         )
+        .arg(
+            Arg::with_name("columns")
+                .long("columns")
+                .takes_value(true)
+                .value_name("COLUMNS")
+                .use_delimiter(true)
+                .validator(|c| c.parse::<TableColumn>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Comma separated list of the rows to show in the game table, and in --report, in order. Valid rows are players, result, termination, opening, speed, moves, duration, and url, all shown by default, and profiles, with links to the profile pages of the players."),
+        )
+        .arg(
+            Arg::with_name("compare-ratings")
+                .long("compare-ratings")
+                .takes_value(false)
+                .help("Show the rating gap of each game, the searched player's rating minus their opponent's, or ? when either is unknown. Applies to the game table and --line when searching by player."),
+        )
+        .arg(
+            Arg::with_name("raw")
+                .long("raw")
+                .takes_value(false)
+                .conflicts_with("display")
+                .help("Print the raw API response without parsing it, useful for debugging. Player searches fetch the month of games given by --year and --month, or otherwise the chess.com archives list or the last --last N lichess.org games."),
+        )
+        .arg(
+            Arg::with_name("ongoing")
+                .long("ongoing")
+                .takes_value(false)
+                .conflicts_with_all(&["display", "raw", "last", "exit-code"])
+                .help("List the daily games a player is currently playing (chess.com only)"),
+        )
+        .arg(
+            Arg::with_name("list-archives")
+                .long("list-archives")
+                .takes_value(false)
+                .conflicts_with_all(&["display", "raw", "ongoing", "last", "exit-code", "validate"])
+                .help("Print the URLs of a player's monthly archives as chess.com lists them, one per line, to debug archives that can't be parsed (chess.com only)"),
+        )
+        .arg(
+            Arg::with_name("validate")
+                .long("validate")
+                .takes_value(false)
+                .conflicts_with_all(&["display", "raw"])
+                .help("Fetch and fully parse the games, including PGN generation, without displaying them. Prints OK, or the first error and exits with code 1."),
+        )
+        .arg(
+            Arg::with_name("exit-code")
+                .long("exit-code")
+                .takes_value(false)
+                .conflicts_with("last")
+                .help("When searching by player, exit with a code reflecting the outcome of the game for the player: 0 for a win, 1 for a loss, 2 for a draw, 3 if no game was found, and 4 if the game has no outcome for the player, e.g. it is unfinished or was aborted."),
+        )
+        .arg(
+            Arg::with_name("repertoire")
+                .long("repertoire")
+                .takes_value(false)
+                .conflicts_with_all(&["display", "raw", "exit-code"])
+                .help("Scan a player's games (all of them, or the last N with --last) and print the openings they played with each color, most played first"),
+        )
+        .arg(
+            Arg::with_name("repertoire-plies")
+                .long("repertoire-plies")
+                .takes_value(true)
+                .value_name("PLIES")
+                .requires("repertoire")
+                .validator(|n| match n.parse::<usize>() {
+                    Ok(n) if n >= 1 => Ok(()),
+                    _ => Err("must be a number greater than 0".to_string()),
+                })
+                .help("Identify openings in the repertoire by their first PLIES moves instead of their ECO code"),
+        )
+        .arg(
+            Arg::with_name("group-by")
+                .long("group-by")
+                .takes_value(true)
+                .value_name("KEY")
+                .possible_values(&["opponent", "opening", "speed", "month", "color", "termination"])
+                .conflicts_with_all(&["display", "raw", "exit-code", "repertoire"])
+                .help("Scan a player's games (all of them, or the last N with --last) and print the number of games and the player's wins, losses, and draws by opponent, opening, speed, month, color, or how the game ended. Games without a known opening are left out of the opening groups."),
+        )
+        .arg(
+            Arg::with_name("sqlite")
+                .long("sqlite")
+                .takes_value(true)
+                .value_name("PATH")
+                .conflicts_with_all(&["display", "raw", "ongoing", "validate", "exit-code", "repertoire", "group-by", "watch", "clipboard"])
+                .help("Save the games found (one, or the last N with --last) into a games table of the SQLite database at PATH instead of printing them. The database and table are created if they don't exist, and games saved before are updated."),
+        )
+        .arg(
+            Arg::with_name("truncate-moves")
+                .long("truncate-moves")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|n| match n.parse::<u32>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a positive number of moves")),
+                })
+                .help("Cut the PGN of --pgn and --minimal-pgn after the first N full moves, ending it with the * result of a game in progress."),
+        )
+        .arg(
+            Arg::with_name("strip-annotations")
+                .long("strip-annotations")
+                .takes_value(false)
+                .help("Remove every comment, including clock times and evaluations, and every NAG from the PGN of --pgn and --minimal-pgn, leaving bare moves and the result."),
+        )
+        .arg(
+            Arg::with_name("normalize-usernames")
+                .long("normalize-usernames")
+                .takes_value(false)
+                .help("Display the usernames of players with the casing they chose, as shown in their profile, instead of the casing used by the games. Falls back to the username of the game when the profile can't be requested."),
+        )
+        .arg(
+            Arg::with_name("clipboard")
+                .long("clipboard")
+                .takes_value(false)
+                .help("Copy the output to the system clipboard instead of printing it. Falls back to printing when no clipboard is available."),
+        )
+        .arg(
+            Arg::with_name("output-file")
+                .long("output-file")
+                .short("O")
+                .takes_value(true)
+                .value_name("PATH")
+                .conflicts_with_all(&["clipboard", "sqlite", "watch"])
+                .help("Write the output to the file at PATH instead of printing it, with the same layout. The file is only written once the search succeeds, replacing it if it exists."),
+        )
+        .arg(
+            Arg::with_name("encoding")
+                .long("encoding")
+                .takes_value(true)
+                .possible_values(&["utf-8", "latin1"])
+                .requires("pgn")
+                .help("Encoding of the PGN output, defaults to utf-8. Characters that cannot be encoded as latin1 are replaced with '?'."),
+        )
         .arg(
             Arg::with_name("year")
                 .short("y")
@@ -118,193 +713,3038 @@ impl ChessGameFinderCLI {
                 .long("date")
                 .takes_value(true)
                 .help("Fetch games from a specific date in RFC-3339 format"),
-        );
-
-        let matches = app.get_matches_from_safe(args)?;
-
-        let player_or_id = matches
-            .value_of("player_or_id")
-            .expect("player or id argument is required");
-        let api = matches.value_of("api").expect("api defaults to chess.com");
-        let mut game_finder =
-            if matches.is_present("player") || !player_or_id.chars().all(char::is_numeric) {
-                GameFinder::by_player(player_or_id, api)
-            } else {
-                GameFinder::by_id(player_or_id, api)
-            };
-
-        if matches.is_present("white") {
-            game_finder.white();
-        } else if matches.is_present("black") {
-            game_finder.black();
-        }
-
-        if matches.is_present("date") {
-            let date = matches.value_of("date").expect("date is present");
-            let parsed_date = DateTime::parse_from_rfc3339(date)
-                .unwrap()
-                .with_timezone(&Utc);
-            game_finder.date(parsed_date);
-        }
-
-        match matches.value_of("year") {
-            Some(y) => {
-                let year = y.parse::<u32>().unwrap();
-                game_finder.year(year);
-            }
-            None => (),
-        };
-
-        match matches.value_of("month") {
-            Some(m) => {
-                let month = m.parse::<u32>().unwrap();
-                game_finder.month(month);
-            }
-            None => (),
-        };
-
-        match matches.value_of("day") {
-            Some(d) => {
-                let day = d.parse::<u32>().unwrap();
-                game_finder.day(day);
-            }
-            None => (),
-        };
-
-        let mut output = "table";
-
-        for display in displays {
-            if matches.is_present(display) {
-                output = display;
-                break;
-            }
-        }
-
-        Ok(ChessGameFinderCLI {
-            output: output.to_owned(),
-            finder: game_finder,
-        })
-    }
-
-    pub fn run(self) -> Result<(), ChessError> {
-        log::info!("Finding game");
-        match self.finder.search {
-            Search::Player(_) => {
-                let mut game = self.finder.find_by_player()?;
-                let displayer = GameDisplayer::from_str(&mut game, &self.output)?;
-                println!("{}", displayer);
-            }
-            Search::ID(_) => {
-                let mut game = self.finder.find_by_id()?;
-                let displayer = GameDisplayer::from_str(&mut game, &self.output)?;
-                println!("{}", displayer);
-            }
-        }
-
-        log::info!("Done!");
-        Ok(())
-    }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::finder::Pieces;
-
-    #[test]
-    fn test_single_game_id() {
-        let args = vec!["cgf", "12345678910"];
-        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
-        let finder = GameFinder {
-            search: Search::ID("12345678910".to_owned()),
-            api: "chess.com".to_string(),
-            pieces: None,
-            year: None,
-            month: None,
-            day: None,
-            opponent: None,
-        };
-        assert_eq!(cgf.finder, finder);
-    }
-
-    #[test]
-    fn test_single_player_username() {
-        let args = vec!["cgf", "a_player"];
-        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
-        let finder = GameFinder {
-            search: Search::Player("a_player".to_owned()),
-            api: "chess.com".to_string(),
-            pieces: None,
-            year: None,
-            month: None,
-            day: None,
-            opponent: None,
-        };
-        assert_eq!(cgf.finder, finder);
+        )
+        .arg(
+            Arg::with_name("today")
+                .long("today")
+                .takes_value(false)
+                .conflicts_with_all(&["date", "year", "month", "day"])
+                .help("Fetch games from today, in UTC"),
+        )
+        .arg(
+            Arg::with_name("week")
+                .long("week")
+                .takes_value(true)
+                .value_name("N")
+                .conflicts_with_all(&["date", "today", "month", "day", "from", "to"])
+                .validator(|n| match n.parse::<u32>() {
+                    Ok(n) if (1..=53).contains(&n) => Ok(()),
+                    _ => Err("must be an ISO week number between 1 and 53".to_string()),
+                })
+                .help("Fetch games from Monday to Sunday of ISO week N, of the ISO year given by --year or else the current one. Weeks can span two months, or two years."),
+        )
+        .arg(
+            Arg::with_name("this-week")
+                .long("this-week")
+                .takes_value(false)
+                .conflicts_with_all(&["date", "today", "year", "month", "day", "from", "to", "week"])
+                .help("Fetch games from Monday to Sunday of the current ISO week, in UTC"),
+        )
+        .arg(
+            Arg::with_name("from")
+                .long("from")
+                .takes_value(true)
+                .value_name("DATE")
+                .conflicts_with("date")
+                .validator(|d| parse_date(&d).map(|_| ()))
+                .help("Only find games that ended on DATE or later, in YYYY-MM-DD format"),
+        )
+        .arg(
+            Arg::with_name("to")
+                .long("to")
+                .takes_value(true)
+                .value_name("DATE")
+                .conflicts_with("date")
+                .validator(|d| parse_date(&d).map(|_| ()))
+                .help("Only find games that ended on DATE or earlier, in YYYY-MM-DD format"),
+        )
+        .arg(
+            Arg::with_name("watch")
+                .long("watch")
+                .takes_value(false)
+                .conflicts_with_all(&["raw", "ongoing", "validate", "exit-code", "repertoire", "group-by", "last", "clipboard"])
+                .help("Keep polling for the player's games and print every new game as it finishes, until interrupted with Ctrl-C"),
+        )
+        .arg(
+            Arg::with_name("watch-interval")
+                .long("watch-interval")
+                .takes_value(true)
+                .value_name("SECONDS")
+                .requires("watch")
+                .validator(|n| match n.parse::<u64>() {
+                    Ok(n) if n >= MIN_WATCH_INTERVAL => Ok(()),
+                    _ => Err(format!("must be a number of seconds of at least {}", MIN_WATCH_INTERVAL)),
+                })
+                .help("Seconds to wait between polls with --watch, defaults to 60"),
+        )
+        .arg(
+            Arg::with_name("opponent")
+                .long("opponent")
+                .short("o")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .value_name("OPPONENT")
+                .validator(|opponent| clean_username(&opponent).map(|_| ()))
+                .help("Only find games against OPPONENT. Can be repeated, or given a comma separated list, to find games against any of them."),
+        )
+        .arg(
+            Arg::with_name("exact-opponent")
+                .long("exact-opponent")
+                .takes_value(false)
+                .requires("opponent")
+                .help("Match opponents case sensitively, e.g. to tell lichess.org's Anonymous opponents apart from a user named anonymous"),
+        )
+        .arg(
+            Arg::with_name("titled")
+                .long("titled")
+                .takes_value(false)
+                .help("Only find games against titled opponents. chess.com archives don't include titles, so no chess.com game is found."),
+        )
+        .arg(
+            Arg::with_name("opponent-title")
+                .long("opponent-title")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .value_name("TITLE")
+                .possible_values(&TITLES)
+                .case_insensitive(true)
+                .help("Only find games against opponents with TITLE, e.g. GM. Can be repeated, or given a comma separated list."),
+        )
+        .arg(
+            Arg::with_name("min-rating")
+                .long("min-rating")
+                .takes_value(true)
+                .value_name("RATING")
+                .validator(|n| n.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Only find games where the opponent was rated at least RATING. Games against unrated opponents, like lichess's anonymous players, are left out."),
+        )
+        .arg(
+            Arg::with_name("max-rating")
+                .long("max-rating")
+                .takes_value(true)
+                .value_name("RATING")
+                .validator(|n| n.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Only find games where the opponent was rated at most RATING. Games against unrated opponents, like lichess's anonymous players, are left out."),
+        )
+        .arg(
+            Arg::with_name("min-rating-gain")
+                .long("min-rating-gain")
+                .takes_value(true)
+                .value_name("POINTS")
+                .conflicts_with("max-rating-loss")
+                .validator(|n| n.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Only find games where the player gained at least POINTS rating points. Unrated games, and chess.com games from archives, which don't report rating changes, are left out."),
+        )
+        .arg(
+            Arg::with_name("max-rating-loss")
+                .long("max-rating-loss")
+                .takes_value(true)
+                .value_name("POINTS")
+                .validator(|n| n.parse::<u32>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Only find games where the player lost at least POINTS rating points. Unrated games, and chess.com games from archives, which don't report rating changes, are left out."),
+        )
+        .arg(
+            Arg::with_name("exclude-provisional")
+                .long("exclude-provisional")
+                .takes_value(false)
+                .help("Leave out games where either player's rating was provisional. chess.com doesn't report provisional ratings, so none of its games are left out."),
+        )
+        .arg(
+            Arg::with_name("eco")
+                .long("eco")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .value_name("ECO")
+                .help("Only find games with an ECO code starting with ECO, e.g. B20 or B2. Can be repeated, or given a comma separated list, to find games with any of them."),
+        )
+        .arg(
+            Arg::with_name("result")
+                .long("result")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .use_delimiter(true)
+                .possible_values(&["win", "loss", "draw"])
+                .value_name("RESULT")
+                .help("Only find games the player won, lost, or drew. Can be repeated, or given a comma separated list, to find games with any of them."),
+        )
+        .arg(
+            Arg::with_name("fail-fast")
+                .long("fail-fast")
+                .takes_value(false)
+                .requires("prefer-source")
+                .conflicts_with("best-effort")
+                .help("With --prefer-source, fail when searching either API fails"),
+        )
+        .arg(
+            Arg::with_name("best-effort")
+                .long("best-effort")
+                .takes_value(false)
+                .requires("prefer-source")
+                .help("With --prefer-source, show the games of the API that succeeded when searching the other fails, with a warning naming it. This is the default."),
+        )
+        .arg(
+            Arg::with_name("prefer-source")
+                .long("prefer-source")
+                .takes_value(true)
+                .value_name("API")
+                .possible_values(&["chess.com", "lichess.org"])
+                .requires("last")
+                .help("Search the player's last --last N games on both chess.com and lichess.org, merged newest first. Games found on both, with the same players, day, and result, keep only the copy from API."),
+        )
+        .arg(
+            Arg::with_name("checkmate")
+                .long("checkmate")
+                .takes_value(false)
+                .conflicts_with("stalemate")
+                .help("Only find games that finished with a checkmate"),
+        )
+        .arg(
+            Arg::with_name("stalemate")
+                .long("stalemate")
+                .takes_value(false)
+                .help("Only find games that finished with a stalemate"),
+        )
+        .arg(
+            Arg::with_name("dedupe-by")
+                .long("dedupe-by")
+                .takes_value(true)
+                .value_name("KEY")
+                .possible_values(&["opponent", "opening", "day"])
+                .help("When collecting multiple games (--last, --repertoire), keep only the most recent game per opponent, opening, or day. Duplicates are removed before --last limits the number of games."),
+        )
+        .arg(
+            Arg::with_name("min-duration")
+                .long("min-duration")
+                .takes_value(true)
+                .value_name("DURATION")
+                .validator(|d| parse_duration(&d).map(|_| ()))
+                .help("Only find games that lasted at least DURATION, in seconds or with an h, m, or s suffix (e.g. 1h). Games without a start time are excluded."),
+        )
+        .arg(
+            Arg::with_name("max-duration")
+                .long("max-duration")
+                .takes_value(true)
+                .value_name("DURATION")
+                .validator(|d| parse_duration(&d).map(|_| ()))
+                .help("Only find games that lasted at most DURATION, in seconds or with an h, m, or s suffix (e.g. 5m). Games without a start time are excluded."),
+        )
+        .arg(
+            Arg::with_name("cache-dir")
+                .long("cache-dir")
+                .takes_value(true)
+                .value_name("DIR")
+                .help("Cache what is learned from searches in DIR, like the months in which a player played each opponent, so that repeated --opponent searches skip months without games against them, and chess.com responses so they are only downloaded again when they change"),
+        )
+        .arg(
+            Arg::with_name("quiet-errors")
+                .long("quiet-errors")
+                .takes_value(false)
+                .help("Don't warn about each game skipped because it could not be parsed, print how many were skipped at the end instead"),
+        )
+        .arg(
+            Arg::with_name("speed")
+                .long("speed")
+                .takes_value(true)
+                .possible_values(&["bullet", "blitz", "rapid", "classical", "correspondence"])
+                .help("Only find games of this speed. lichess.org sends only the games of this speed."),
+        )
+        .arg(
+            Arg::with_name("time-control")
+                .long("time-control")
+                .short("t")
+                .takes_value(true)
+                .value_name("CLASS")
+                .possible_values(&["bullet", "blitz", "rapid", "classical"])
+                .help("Only find games of this time class. chess.com games are classed by their base time alone, ignoring the increment: bullet under 3 minutes, blitz under 10, rapid under 30, and classical from then on. lichess.org games are classed by their speed. Daily games have no time class."),
+        )
+        .arg(
+            Arg::with_name("variant")
+                .long("variant")
+                .takes_value(true)
+                .possible_values(&[
+                    "standard", "chess960", "crazyhouse", "antichess", "atomic", "horde",
+                    "kingOfTheHill", "racingKings", "threeCheck",
+                ])
+                .help("Only find games of this variant. lichess.org sends only the games of this variant."),
+        )
+        .arg(
+            Arg::with_name("dump-fixtures")
+                .long("dump-fixtures")
+                .takes_value(true)
+                .value_name("DIR")
+                .hidden(true)
+                .help("Save the raw body of every response into DIR, to be used as test fixtures"),
+        )
+        .arg(
+            Arg::with_name("progress")
+                .long("progress")
+                .takes_value(false)
+                .help("Report the progress of archive scans on stderr, with an estimate of the time remaining"),
+        )
+        .arg(
+            Arg::with_name("concurrency")
+                .long("concurrency")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|n| match n.parse::<usize>() {
+                    Ok(n) if n > 0 => Ok(()),
+                    _ => Err(String::from("must be a positive number of requests")),
+                })
+                .help("Request up to N chess.com archives at the same time when scanning them, defaults to 1. Requests share their pacing, and a rate limited request is retried after waiting, as long as --max-total-retries allows it. lichess.org months are always requested one at a time."),
+        )
+        .arg(
+            Arg::with_name("random")
+                .long("random")
+                .takes_value(false)
+                .conflicts_with_all(&["last", "watch", "ongoing", "repertoire", "group-by"])
+                .help("Pick a game of the player at random among the games that match the search, instead of the latest one. Every archive that matches the date filters is scanned."),
+        )
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .takes_value(true)
+                .value_name("N")
+                .requires("random")
+                .validator(|n| n.parse::<u64>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Seed of --random, to pick the same game again. Defaults to a seed taken from the clock, which is logged"),
+        )
+        .arg(
+            Arg::with_name("lichess-param")
+                .long("lichess-param")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .value_name("KEY=VALUE")
+                .validator(|p| parse_lichess_param(&p).map(|_| ()))
+                .help("Add a query parameter to the requests of the lichess.org games endpoint, e.g. accuracy=true. Can be repeated. The parameters cgf manages, like since, until, and max, can't be set."),
+        )
+        .arg(
+            Arg::with_name("max-total-retries")
+                .long("max-total-retries")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|n| n.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                .help("Retry failed requests at most N times over the whole run, e.g. 50, so a struggling API can't turn a long scan into hours of retries. Once spent, failures surface immediately."),
+        )
+        .arg(
+            Arg::with_name("unique")
+                .long("unique")
+                .takes_value(false)
+                .conflicts_with_all(&["last", "random", "since-game-id", "watch", "ongoing", "prefer-source"])
+                .help("Fail unless exactly one game matches the search, instead of finding the latest of several. Every archive that matches the date filters is scanned."),
+        )
+        .arg(
+            Arg::with_name("all")
+                .long("all")
+                .takes_value(false)
+                .conflicts_with_all(&["last", "random", "since-game-id", "watch", "ongoing", "prefer-source", "unique", "exit-code", "repertoire", "group-by"])
+                .help("Find every game that matches the search, oldest first, instead of only the latest. Games are separated by a blank line, or one per line with the JSON outputs."),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .short("n")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|n| match n.parse::<usize>() {
+                    Ok(n) if n >= 1 => Ok(()),
+                    _ => Err("must be a number greater than 0".to_string()),
+                })
+                .help("Find at most the N most recent games that match the search with --all. Without --all a single game is found, so the limit is effectively 1."),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .takes_value(false)
+                .help("Fail if the game found by player is not one they played, a safety net for bugs in cgf's filters"),
+        )
+        .arg(
+            Arg::with_name("seed-position")
+                .long("seed-position")
+                .takes_value(true)
+                .value_name("FEN")
+                .validator(|fen| parse_fen(&fen).map(|_| ()))
+                .help("Replay the moves of chess.com live games from FEN instead of the starting position sent with the game, for games whose stored FEN is wrong"),
+        )
+        .arg(
+            Arg::with_name("since-game-id")
+                .long("since-game-id")
+                .takes_value(true)
+                .value_name("ID")
+                .conflicts_with_all(&["watch", "ids-file", "random", "prefer-source", "exit-code", "ongoing"])
+                .help("Fetch every game of a player played after the game with ID, or game URL, newest first, stopping the scan once it's reached. If the game isn't found, every game scanned is returned with a warning. Combine with --last N to fetch at most N games."),
+        )
+        .arg(
+            Arg::with_name("last")
+                .long("last")
+                .takes_value(true)
+                .value_name("N")
+                .validator(|n| match n.parse::<usize>() {
+                    Ok(n) if n >= 1 => Ok(()),
+                    _ => Err("must be a number greater than 0".to_string()),
+                })
+                .help("Fetch the last N games of a player, newest first, regardless of their date"),
+        );
+
+        let matches = app.get_matches_from_safe(args)?;
+
+        if let Some(history) = matches.subcommand_matches("rating-history") {
+            let username = history
+                .value_of("username")
+                .expect("username argument is required");
+            let api = history.value_of("api").expect("api defaults to chess.com");
+            let speed = history.value_of("speed").map(Speed::from_name);
+            require_capability(api, Capability::RatingHistory)?;
+
+            return Ok(ChessGameFinderCLI {
+                rating_history: Some(speed),
+                ..ChessGameFinderCLI::with_finder(GameFinder::by_player(username, api))
+            });
+        }
+
+        if let Some(tournaments) = matches.subcommand_matches("tournaments") {
+            let username = tournaments
+                .value_of("username")
+                .expect("username argument is required");
+
+            return Ok(ChessGameFinderCLI {
+                tournaments: true,
+                ..ChessGameFinderCLI::with_finder(GameFinder::by_player(username, "chess.com"))
+            });
+        }
+
+        if let Some(status) = matches.subcommand_matches("status") {
+            let username = status
+                .value_of("username")
+                .expect("username argument is required");
+
+            return Ok(ChessGameFinderCLI {
+                status: true,
+                ..ChessGameFinderCLI::with_finder(GameFinder::by_player(username, "chess.com"))
+            });
+        }
+
+        if let Some(export) = matches.subcommand_matches("export") {
+            let username = export
+                .value_of("username")
+                .expect("username argument is required");
+            let api = export.value_of("api").expect("api defaults to chess.com");
+            let dir = export.value_of("to").expect("to argument is required");
+            require_capability(api, Capability::Export)?;
+            let mut finder = GameFinder::by_player(username, api);
+            if export.is_present("progress") {
+                finder.progress();
+            }
+
+            return Ok(ChessGameFinderCLI {
+                export: Some((PathBuf::from(dir), export.is_present("resume"))),
+                ..ChessGameFinderCLI::with_finder(finder)
+            });
+        }
+
+        if let Some(import) = matches.subcommand_matches("import-to-lichess") {
+            let id = import.value_of("id").expect("id argument is required");
+            let api = import.value_of("api").expect("api defaults to chess.com");
+
+            return Ok(ChessGameFinderCLI {
+                import_to_lichess: true,
+                lichess_token: import.value_of("token").map(String::from),
+                ..ChessGameFinderCLI::with_finder(GameFinder::by_id(id.trim(), api))
+            });
+        }
+
+        let api = matches.value_of("api").expect("api defaults to chess.com");
+        // The IDs of --ids-file are searched by finders made from this one
+        let player_or_id = match matches.value_of("ids-file") {
+            Some(_) => "",
+            None => matches
+                .value_of("player_or_id")
+                .expect("player or id argument is required")
+                .trim(),
+        };
+        let mut game_finder = if matches.is_present("ids-file") {
+            GameFinder::by_id(player_or_id, api)
+        } else if matches.is_present("player") || !looks_like_game_id(player_or_id) {
+            let player = clean_username(player_or_id)
+                .map_err(|e| clap::Error::with_description(&e, clap::ErrorKind::InvalidValue))?;
+            GameFinder::by_player(player, api)
+        } else {
+            GameFinder::by_id(player_or_id, api)
+        };
+
+        // The IDs of --ids-file may be of either API, and are checked as they are searched
+        if !matches.is_present("ids-file") {
+            let capability = if matches.is_present("list-archives") {
+                Capability::ListArchives
+            } else if matches.is_present("ongoing") {
+                Capability::OngoingGames
+            } else if let Search::ID(_) = game_finder.search {
+                Capability::GameById
+            } else {
+                Capability::PlayerGames
+            };
+            require_capability(api, capability)?;
+        }
+
+        if matches.is_present("white") {
+            game_finder.white();
+        } else if matches.is_present("black") {
+            game_finder.black();
+        }
+
+        if matches.is_present("date") {
+            let date = matches.value_of("date").expect("date is present");
+            let parsed_date = DateTime::parse_from_rfc3339(date)
+                .unwrap()
+                .with_timezone(&Utc);
+            game_finder.date(parsed_date);
+        }
+
+        if matches.is_present("today") {
+            game_finder.today();
+        }
+
+        // With --week, --year is the ISO year of the week rather than a filter, as the week
+        // may start in the year before
+        if let Some(week) = matches.value_of("week") {
+            let week = week.parse::<u32>().expect("week is validated");
+            let year = match matches.value_of("year") {
+                Some(y) => y.parse::<i32>().map_err(|e| {
+                    clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue)
+                })?,
+                None => Utc::now().iso_week().year(),
+            };
+            let monday = NaiveDate::from_isoywd_opt(year, week, Weekday::Mon).ok_or_else(|| {
+                clap::Error::with_description(
+                    &format!("{} has no ISO week {}", year, week),
+                    clap::ErrorKind::InvalidValue,
+                )
+            })?;
+            game_finder.week_of(monday);
+        } else if matches.is_present("this-week") {
+            game_finder.week_of(Utc::now().date().naive_utc());
+        }
+
+        match matches.value_of("year") {
+            Some(y) if !matches.is_present("week") => {
+                let year = y.parse::<u32>().unwrap();
+                game_finder.year(year);
+            }
+            _ => (),
+        };
+
+        match matches.value_of("month") {
+            Some(m) => {
+                let month = m.parse::<u32>().unwrap();
+                game_finder.month(month);
+            }
+            None => (),
+        };
+
+        match matches.value_of("day") {
+            Some(d) => {
+                let day = d.parse::<u32>().unwrap();
+                game_finder.day(day);
+            }
+            None => (),
+        };
+
+        if let Some(d) = matches.value_of("from") {
+            game_finder.from(parse_date(d).expect("from is validated"));
+        }
+
+        if let Some(d) = matches.value_of("to") {
+            game_finder.to(parse_date(d).expect("to is validated"));
+        }
+
+        // Opponents are added with their exact case only after this is set
+        if matches.is_present("exact-opponent") {
+            game_finder.exact_opponent();
+        }
+
+        if let Some(opponents) = matches.values_of("opponent") {
+            for opponent in opponents {
+                game_finder.opponent(opponent.trim());
+            }
+        }
+
+        if matches.is_present("titled") {
+            game_finder.titled();
+        }
+
+        if let Some(titles) = matches.values_of("opponent-title") {
+            for title in titles {
+                game_finder.opponent_title(title);
+            }
+        }
+
+        if let Some(min) = matches.value_of("min-rating") {
+            game_finder.min_rating(min.parse::<u32>().expect("min-rating is validated"));
+        }
+
+        if let Some(max) = matches.value_of("max-rating") {
+            game_finder.max_rating(max.parse::<u32>().expect("max-rating is validated"));
+        }
+
+        if let Some(points) = matches.value_of("min-rating-gain") {
+            game_finder
+                .min_rating_gain(points.parse::<u32>().expect("min-rating-gain is validated"));
+        }
+
+        if let Some(points) = matches.value_of("max-rating-loss") {
+            game_finder
+                .max_rating_loss(points.parse::<u32>().expect("max-rating-loss is validated"));
+        }
+
+        if matches.is_present("exclude-provisional") {
+            game_finder.exclude_provisional();
+        }
+
+        if let Some(ecos) = matches.values_of("eco") {
+            for eco in ecos {
+                game_finder.eco(eco);
+            }
+        }
+
+        if let Some(results) = matches.values_of("result") {
+            for result in results {
+                game_finder.result(
+                    result
+                        .parse::<Outcome>()
+                        .expect("result is one of the possible values"),
+                );
+            }
+        }
+
+        if matches.is_present("checkmate") {
+            game_finder.checkmate(true);
+        }
+
+        if matches.is_present("stalemate") {
+            game_finder.stalemate(true);
+        }
+
+        if let Some(key) = matches.value_of("dedupe-by") {
+            game_finder.dedupe_by(
+                key.parse::<DedupeKey>()
+                    .expect("dedupe-by is one of the possible values"),
+            );
+        }
+
+        if let Some(api) = matches.value_of("prefer-source") {
+            game_finder.prefer_source(api);
+        }
+
+        if matches.is_present("fail-fast") {
+            game_finder.fail_fast();
+        }
+
+        if let Some(d) = matches.value_of("min-duration") {
+            game_finder.min_duration(parse_duration(d).expect("min-duration is validated"));
+        }
+
+        if let Some(d) = matches.value_of("max-duration") {
+            game_finder.max_duration(parse_duration(d).expect("max-duration is validated"));
+        }
+
+        if let Some(dir) = matches.value_of("cache-dir") {
+            game_finder.cache_dir(dir);
+        }
+
+        if matches.is_present("quiet-errors") {
+            game_finder.quiet_errors();
+        }
+
+        if let Some(speed) = matches.value_of("speed") {
+            game_finder.speed(Speed::from_name(speed));
+        }
+
+        if let Some(time_class) = matches.value_of("time-control") {
+            game_finder.time_class(
+                time_class
+                    .parse::<TimeClass>()
+                    .expect("time-control is one of the possible values"),
+            );
+        }
+
+        if let Some(variant) = matches.value_of("variant") {
+            game_finder.variant(variant);
+        }
+
+        if let Some(dir) = matches.value_of("dump-fixtures") {
+            game_finder.dump_fixtures(dir);
+        }
+
+        if matches.is_present("progress") {
+            game_finder.progress();
+        }
+
+        if let Some(n) = matches.value_of("concurrency") {
+            game_finder.concurrency(n.parse().expect("concurrency is validated"));
+        }
+
+        let columns = match matches.values_of("columns") {
+            Some(columns) => columns
+                .map(|c| c.parse::<TableColumn>().expect("columns are validated"))
+                .collect(),
+            None => DEFAULT_COLUMNS.to_vec(),
+        };
+
+        if matches.is_present("random") {
+            let seed = match matches.value_of("seed") {
+                Some(seed) => seed.parse::<u64>().expect("seed is validated as a number"),
+                None => clock_seed(),
+            };
+            log::info!("Picking a random game with --seed {}", seed);
+            game_finder.random(seed);
+        }
+
+        if let Some(params) = matches.values_of("lichess-param") {
+            for param in params {
+                let (key, value) = parse_lichess_param(param).expect("lichess-param is validated");
+                game_finder.lichess_param(&key, &value);
+            }
+        }
+
+        if let Some(n) = matches.value_of("max-total-retries") {
+            game_finder.max_total_retries(n.parse().expect("max-total-retries is validated"));
+        }
+
+        if matches.is_present("unique") {
+            if let Search::ID(_) = game_finder.search {
+                return Err(clap::Error::with_description(
+                    "--unique only applies to searches by player",
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+            game_finder.unique();
+        }
+
+        if matches.is_present("all") {
+            if let Search::ID(_) = game_finder.search {
+                return Err(clap::Error::with_description(
+                    "--all only applies to searches by player",
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+        }
+
+        if matches.is_present("explain-pgn")
+            && (api != "chess.com" || !matches!(game_finder.search, Search::ID(_)))
+        {
+            return Err(clap::Error::with_description(
+                "--explain-pgn only applies to chess.com live games, searched by ID",
+                clap::ErrorKind::ArgumentConflict,
+            ));
+        }
+
+        if matches.is_present("strict") {
+            game_finder.strict();
+        }
+
+        if let Some(n) = matches.value_of("limit") {
+            game_finder.limit(n.parse::<usize>().expect("limit is validated as a number"));
+        }
+
+        if let Some(n) = matches.value_of("last") {
+            game_finder.last(n.parse::<usize>().expect("last is validated as a number"));
+        }
+
+        let mut output = "table";
+
+        for display in displays {
+            if matches.is_present(display) {
+                output = display;
+                break;
+            }
+        }
+
+        // Templates are parsed once, and fail before any game is searched
+        let template = match matches.value_of("template-file") {
+            Some(path) => Some(Template::from_file(Path::new(path)).map_err(|e| {
+                clap::Error::with_description(&e.to_string(), clap::ErrorKind::InvalidValue)
+            })?),
+            None => None,
+        };
+
+        for pgn_option in ["truncate-moves", "strip-annotations"].iter() {
+            if matches.is_present(pgn_option) && !["pgn", "minimal-pgn"].contains(&output) {
+                return Err(clap::Error::with_description(
+                    &format!("--{} only applies to --pgn and --minimal-pgn", pgn_option),
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+        }
+
+        if let Some(id) = matches.value_of("since-game-id") {
+            if let Search::ID(_) = game_finder.search {
+                return Err(clap::Error::with_description(
+                    "--since-game-id only applies to searches by player",
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+            game_finder.since_game_id(id);
+        }
+
+        if matches.is_present("compare-ratings") {
+            if !["table", "line"].contains(&output) {
+                return Err(clap::Error::with_description(
+                    "--compare-ratings only applies to the game table and --line",
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+            if let Search::ID(_) = game_finder.search {
+                return Err(clap::Error::with_description(
+                    "--compare-ratings only applies to searches by player",
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+        }
+
+        if matches.is_present("all")
+            || matches.is_present("last")
+            || matches.is_present("since-game-id")
+            || matches.is_present("watch")
+            || matches.is_present("ids-file")
+        {
+            if let MultiGame::Unsupported(suggestion) = multi_game_support(output) {
+                return Err(clap::Error::with_description(
+                    &format!("--{} can't display multiple games, {}", output, suggestion),
+                    clap::ErrorKind::ArgumentConflict,
+                ));
+            }
+        }
+
+        let repertoire = if matches.is_present("repertoire") {
+            Some(matches.value_of("repertoire-plies").map(|n| {
+                n.parse::<usize>()
+                    .expect("repertoire-plies is validated as a number")
+            }))
+        } else {
+            None
+        };
+
+        let watch = if matches.is_present("watch") {
+            Some(match matches.value_of("watch-interval") {
+                Some(interval) => interval
+                    .parse::<u64>()
+                    .expect("watch-interval is validated as a number"),
+                None => DEFAULT_WATCH_INTERVAL,
+            })
+        } else {
+            None
+        };
+
+        let encoding = matches
+            .value_of("encoding")
+            .unwrap_or("utf-8")
+            .parse::<OutputEncoding>()
+            .expect("encoding is one of the possible values");
+
+        Ok(ChessGameFinderCLI {
+            output: output.to_owned(),
+            raw: matches.is_present("raw"),
+            ongoing: matches.is_present("ongoing"),
+            list_archives: matches.is_present("list-archives"),
+            validate: matches.is_present("validate"),
+            exit_code: matches.is_present("exit-code"),
+            repertoire,
+            group_by: matches.value_of("group-by").map(|key| {
+                key.parse::<GroupKey>()
+                    .expect("group-by is one of the possible values")
+            }),
+            rating_history: None,
+            export: None,
+            import_to_lichess: false,
+            lichess_token: None,
+            tournaments: false,
+            status: false,
+            sqlite: matches.value_of("sqlite").map(PathBuf::from),
+            ids_file: matches.value_of("ids-file").map(PathBuf::from),
+            watch,
+            encoding,
+            clipboard: matches.is_present("clipboard"),
+            output_file: matches.value_of("output-file").map(PathBuf::from),
+            color: !matches.is_present("no-color")
+                && !matches.is_present("clipboard")
+                && !matches.is_present("output-file")
+                && std::env::var_os("NO_COLOR").is_none()
+                && io::stdout().is_terminal(),
+            columns,
+            compare_ratings: matches.is_present("compare-ratings"),
+            template,
+            seed_position: matches
+                .value_of("seed-position")
+                .map(|f| f.trim().to_string()),
+            truncate_moves: matches
+                .value_of("truncate-moves")
+                .map(|n| n.parse().expect("truncate-moves is validated")),
+            strip_annotations: matches.is_present("strip-annotations"),
+            ply: matches
+                .value_of("ply")
+                .map(|n| n.parse().expect("ply is validated")),
+            board_steps: BoardSteps {
+                from_ply: matches
+                    .value_of("from-ply")
+                    .map(|n| n.parse().expect("from-ply is validated")),
+                to_ply: matches
+                    .value_of("to-ply")
+                    .map(|n| n.parse().expect("to-ply is validated")),
+                fancy: matches.is_present("fancy-board"),
+            },
+            all: matches.is_present("all"),
+            usernames: if matches.is_present("normalize-usernames") {
+                Some(RefCell::new(HashMap::new()))
+            } else {
+                None
+            },
+            finder: game_finder,
+        })
+    }
+
+    /// A run of the finder, with every display and output option left at its default, which
+    /// the subcommands start from.
+    fn with_finder(finder: GameFinder) -> Self {
+        ChessGameFinderCLI {
+            output: "table".to_owned(),
+            raw: false,
+            ongoing: false,
+            list_archives: false,
+            validate: false,
+            exit_code: false,
+            repertoire: None,
+            group_by: None,
+            rating_history: None,
+            export: None,
+            import_to_lichess: false,
+            lichess_token: None,
+            tournaments: false,
+            status: false,
+            sqlite: None,
+            ids_file: None,
+            watch: None,
+            encoding: OutputEncoding::Utf8,
+            clipboard: false,
+            output_file: None,
+            color: false,
+            columns: DEFAULT_COLUMNS.to_vec(),
+            compare_ratings: false,
+            template: None,
+            seed_position: None,
+            truncate_moves: None,
+            strip_annotations: false,
+            ply: None,
+            board_steps: BoardSteps::default(),
+            all: false,
+            usernames: None,
+            finder,
+        }
+    }
+
+    /// Run the search and display the results. Returns the exit code the process should use
+    /// when it is determined by the search, i.e. when --exit-code is set.
+    pub fn run(self) -> Result<Option<i32>, ChessError> {
+        let code = match &self.output_file {
+            Some(path) => self.run_to_file(path),
+            None => {
+                let mut out = self.output_target()?;
+                let code = self.run_to(&mut *out);
+                out.flush()?;
+                code
+            }
+        };
+        if self.finder.quiet_errors {
+            let (skipped, total) = skipped_games();
+            if skipped > 0 {
+                eprintln!("{} of {} games could not be parsed", skipped, total);
+            }
+        }
+        code
+    }
+
+    /// Where the output should be written to.
+    fn output_target(&self) -> Result<Box<dyn Write>, ChessError> {
+        if self.clipboard {
+            Ok(Box::new(ClipboardWriter::new()))
+        } else {
+            Ok(Box::new(io::stdout()))
+        }
+    }
+
+    /// Run the search and write the results to the file at path. The file is only replaced once
+    /// the search succeeded, so a failed search leaves an existing file untouched.
+    fn run_to_file(&self, path: &Path) -> Result<Option<i32>, ChessError> {
+        let mut buffer = Vec::new();
+        let code = self.run_to(&mut buffer)?;
+        if code != Some(NOT_FOUND_EXIT_CODE) {
+            fs::write(path, buffer)?;
+        }
+        Ok(code)
+    }
+
+    /// Run the search and write the results to the out target.
+    pub fn run_to(&self, out: &mut dyn Write) -> Result<Option<i32>, ChessError> {
+        self.finder.validate()?;
+        for warning in self.finder.warnings(Utc::now()) {
+            eprintln!("Warning: {}", warning);
+        }
+
+        if let Some(speed) = self.rating_history {
+            log::info!("Building rating history");
+            let points = self.finder.rating_history(speed)?;
+            write!(out, "{}", rating_history_csv(&points))?;
+            return Ok(None);
+        }
+
+        if let Some((dir, resume)) = &self.export {
+            log::info!("Exporting games to {:?}", dir);
+            let written = self.finder.export_by_player(dir, *resume)?;
+            writeln!(out, "Exported {} games to {}", written, dir.display())?;
+            return Ok(None);
+        }
+
+        if self.import_to_lichess {
+            log::info!("Importing game into lichess.org");
+            let imported = self
+                .finder
+                .import_to_lichess(self.lichess_token.as_deref())?;
+            writeln!(out, "{}", imported.url)?;
+            return Ok(None);
+        }
+
+        if self.status {
+            log::info!("Checking status");
+            let status = self.finder.find_status_of_player()?;
+            writeln!(out, "{}", status.describe())?;
+            return Ok(None);
+        }
+
+        if self.tournaments {
+            log::info!("Listing tournaments");
+            let tournaments = self.finder.find_tournaments_by_player()?;
+            if tournaments.is_empty() {
+                writeln!(
+                    out,
+                    "No tournaments found for {}",
+                    self.finder.search.get_value()
+                )?;
+            } else {
+                self.write(out, &GameDisplayer::Table(tournaments_table(&tournaments)))?;
+            }
+            return Ok(None);
+        }
+
+        if self.raw {
+            log::info!("Fetching raw response");
+            writeln!(out, "{}", self.finder.find_raw()?)?;
+            return Ok(None);
+        }
+
+        if self.ongoing {
+            if let Search::ID(_) = self.finder.search {
+                return Err(ChessError::UnsupportedOutputError(
+                    "ongoing games of a game ID".to_string(),
+                ));
+            }
+            log::info!("Finding ongoing games");
+            let games = self.finder.find_ongoing_by_player()?;
+            self.write(out, &GameDisplayer::Table(ongoing_table(&games)))?;
+            return Ok(None);
+        }
+
+        if self.list_archives {
+            if let Search::ID(_) = self.finder.search {
+                return Err(ChessError::UnsupportedOutputError(
+                    "archives of a game ID".to_string(),
+                ));
+            }
+            log::info!("Listing archives");
+            for url in self.finder.find_archive_urls()? {
+                writeln!(out, "{}", url)?;
+            }
+            return Ok(None);
+        }
+
+        if self.validate {
+            log::info!("Validating games");
+            return match self.validate_games() {
+                Ok(count) => {
+                    writeln!(out, "OK ({} games)", count)?;
+                    Ok(None)
+                }
+                Err(e) => {
+                    writeln!(out, "FAILED: {}", e)?;
+                    Ok(Some(1))
+                }
+            };
+        }
+
+        if let Some(plies) = self.repertoire {
+            let player = match &self.finder.search {
+                Search::Player(p) => p,
+                Search::ID(_) => {
+                    return Err(ChessError::UnsupportedOutputError(
+                        "repertoire of a game ID".to_string(),
+                    ))
+                }
+            };
+            log::info!("Building repertoire");
+            let mut repertoire = Repertoire::new(player, plies);
+            for mut game in self.finder.collect_by_player(self.finder.last)? {
+                repertoire.add(&mut game);
+            }
+            self.write(out, &GameDisplayer::Table(repertoire.to_table()))?;
+            return Ok(None);
+        }
+
+        if let Some(key) = self.group_by {
+            if let Search::ID(_) = self.finder.search {
+                return Err(ChessError::UnsupportedOutputError(
+                    "grouping a game ID".to_string(),
+                ));
+            }
+            log::info!("Grouping games by {:?}", key);
+            let groups = self.finder.group_by_player(key)?;
+            self.write(out, &GameDisplayer::Table(group_table(&groups, key)))?;
+            return Ok(None);
+        }
+
+        if let Some(path) = &self.sqlite {
+            log::info!("Saving games to {:?}", path);
+            let count = self.save_games(path)?;
+            writeln!(out, "Saved {} games to {}", count, path.display())?;
+            return Ok(None);
+        }
+
+        if let Some(interval) = self.watch {
+            if let Search::ID(_) = self.finder.search {
+                return Err(ChessError::UnsupportedOutputError(
+                    "watching a game ID".to_string(),
+                ));
+            }
+            log::info!("Watching for new games every {} seconds", interval);
+            self.watch(out, Duration::from_secs(interval))?;
+            return Ok(None);
+        }
+
+        if let Some(path) = &self.ids_file {
+            log::info!("Finding the games of the IDs in {:?}", path);
+            return self.find_ids(out, path);
+        }
+
+        log::info!("Finding game");
+        match self.finder.search {
+            Search::Player(_) if self.all => {
+                let games = self.finder.find_all_by_player()?;
+                self.write_all(out, games)?;
+            }
+            Search::Player(_)
+                if self.finder.last.is_some() || self.finder.since_game_id.is_some() =>
+            {
+                let games = self.finder.find_last_by_player()?;
+                self.write_all(out, games)?;
+            }
+            Search::Player(ref player) => {
+                let mut game = match self.finder.find_by_player() {
+                    Err(ChessError::GameNotFoundError) if self.exit_code => {
+                        return Ok(Some(NOT_FOUND_EXIT_CODE))
+                    }
+                    result => result?,
+                };
+                let displayer = self.display(&mut game)?;
+                self.write(out, &displayer)?;
+
+                if self.exit_code {
+                    let outcome = game.result_for(player);
+                    log::info!("Outcome for {}: {:?}", player, outcome);
+                    return Ok(Some(outcome_exit_code(outcome)));
+                }
+            }
+            Search::ID(_) => {
+                let mut game = self.finder.find_by_id()?;
+                let displayer = self.display(&mut game)?;
+                self.write(out, &displayer)?;
+            }
+        }
+
+        log::info!("Done!");
+        Ok(None)
+    }
+
+    /// Display a game in the requested output, with the chosen table rows.
+    fn display(&self, game: &mut Game) -> Result<GameDisplayer, ChessError> {
+        self.seed(game);
+        self.normalize_usernames(game);
+        if self.output == "line" {
+            let player = match &self.finder.search {
+                Search::Player(p) => Some(p.as_str()),
+                Search::ID(_) => None,
+            };
+            let mut line = game_line(game, player, self.color);
+            if let Some(gap) = self.rating_gap(game) {
+                line.push_str(&format!("  Δ {}", gap));
+            }
+            return Ok(GameDisplayer::Default(line));
+        }
+        if let Some(template) = &self.template {
+            return Ok(GameDisplayer::Default(template.render(game)));
+        }
+        if self.output == "analysis-url" {
+            return Ok(GameDisplayer::Default(analysis_urls(game, self.ply)?));
+        }
+        if self.output == "explain-pgn" {
+            return match game {
+                Game::ChessDotComLive(live) => Ok(GameDisplayer::Default(explain_pgn(live))),
+                _ => Err(ChessError::UnsupportedOutputError(
+                    "explain-pgn for games other than chess.com live games".to_string(),
+                )),
+            };
+        }
+        if self.output == "board-every-move" {
+            return Ok(GameDisplayer::Default(boards_every_move(
+                game,
+                self.board_steps,
+            )?));
+        }
+        match GameDisplayer::with_columns(game, &self.output, &self.columns)? {
+            GameDisplayer::Default(pgn) if self.edits_pgn() => {
+                Ok(GameDisplayer::Default(self.edit_pgn(pgn)))
+            }
+            GameDisplayer::Table(mut table) => {
+                if let Some(gap) = self.rating_gap(game) {
+                    table.add_row(row!["Rating gap", H2 -> gap]);
+                }
+                Ok(GameDisplayer::Table(table))
+            }
+            displayer => Ok(displayer),
+        }
+    }
+
+    /// The rating gap of the game for the searched player with --compare-ratings.
+    fn rating_gap(&self, game: &mut Game) -> Option<String> {
+        match &self.finder.search {
+            Search::Player(player) if self.compare_ratings => {
+                Some(format_rating_gap(rating_gap(game, player)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the PGN output is edited, with --strip-annotations or --truncate-moves.
+    fn edits_pgn(&self) -> bool {
+        self.strip_annotations || self.truncate_moves.is_some()
+    }
+
+    fn edit_pgn(&self, mut pgn: String) -> String {
+        if self.strip_annotations {
+            pgn = strip_annotations(&pgn);
+        }
+        if let Some(n) = self.truncate_moves {
+            pgn = truncate_pgn(&pgn, n);
+        }
+        pgn
+    }
+
+    /// Replace the usernames of the players with their display username with
+    /// --normalize-usernames, requesting the profile of each player only once.
+    fn normalize_usernames(&self, game: &mut Game) {
+        let usernames = match &self.usernames {
+            Some(usernames) => usernames,
+            None => return,
+        };
+        let api = game.api();
+        game.rename_players(|username| {
+            let key = (api.to_string(), username.to_lowercase());
+            if let Some(resolved) = usernames.borrow().get(&key) {
+                return resolved.clone();
+            }
+            let resolved = self
+                .finder
+                .display_username(api, username)
+                .unwrap_or_else(|e| {
+                    log::warn!("Could not resolve username {}: {}", username, e);
+                    username.to_string()
+                });
+            usernames.borrow_mut().insert(key, resolved.clone());
+            resolved
+        });
+    }
+
+    /// Override the starting position of the game with --seed-position.
+    fn seed(&self, game: &mut Game) {
+        if let Some(fen) = &self.seed_position {
+            if !game.seed_position(fen) {
+                eprintln!(
+                    "Warning: --seed-position only applies to chess.com live games, ignoring it for {}",
+                    game.url()
+                );
+            }
+        }
+    }
+
+    /// Poll the player's latest games every interval, printing the games that finished since the
+    /// last poll, oldest first. Games that finished before watching started are not printed.
+    /// Runs until interrupted.
+    fn watch(&self, out: &mut dyn Write, interval: Duration) -> Result<(), ChessError> {
+        let mut high_water = Utc::now();
+        let mut printed = 0;
+
+        while !interrupted() {
+            let games = match self.finder.collect_by_player(Some(WATCH_BATCH_SIZE)) {
+                Ok(games) => games,
+                Err(ChessError::GameNotFoundError) => Vec::new(),
+                Err(e) => {
+                    log::warn!("Polling for new games failed: {}", e);
+                    Vec::new()
+                }
+            };
+
+            let mut new_games = games
+                .into_iter()
+                .filter(|g| g.end_time() > high_water)
+                .collect::<Vec<Game>>();
+            new_games.reverse();
+
+            for mut game in new_games.into_iter() {
+                if printed > 0 {
+                    self.separate_games(out)?;
+                }
+                high_water = high_water.max(game.end_time());
+                let displayer = self.display(&mut game)?;
+                self.write(out, &displayer)?;
+                out.flush()?;
+                printed += 1;
+            }
+
+            // Sleep in short steps to stop promptly when interrupted
+            let mut waited = Duration::from_secs(0);
+            while waited < interval && !interrupted() {
+                thread::sleep(Duration::from_secs(1));
+                waited += Duration::from_secs(1);
+            }
+        }
+        Ok(())
+    }
+
+    /// Find and display the game of each ID of an --ids-file, in order, with a finder for the API
+    /// of each ID. IDs whose game can't be found are skipped with a warning.
+    fn find_ids(&self, out: &mut dyn Write, path: &Path) -> Result<Option<i32>, ChessError> {
+        let mut found = 0;
+        for (api, id) in read_ids_file(path)? {
+            let mut finder = self.finder.clone();
+            finder.api = api.to_string();
+            finder.search = Search::ID(id.clone());
+
+            let mut game = match finder.find_by_id() {
+                Ok(game) => game,
+                Err(e) => {
+                    eprintln!("Warning: skipping {}:{}, {}", api, id, e);
+                    continue;
+                }
+            };
+            if found > 0 {
+                self.separate_games(out)?;
+            }
+            let displayer = self.display(&mut game)?;
+            self.write(out, &displayer)?;
+            found += 1;
+        }
+
+        if found == 0 {
+            Err(ChessError::GameNotFoundError)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The games of the search: every game of a player with --all, their last games with --last,
+    /// or a single game.
+    fn found_games(&self) -> Result<Vec<Game>, ChessError> {
+        Ok(match self.finder.search {
+            Search::Player(_) if self.all => self.finder.find_all_by_player()?,
+            Search::Player(_)
+                if self.finder.last.is_some() || self.finder.since_game_id.is_some() =>
+            {
+                self.finder.find_last_by_player()?
+            }
+            Search::Player(_) => vec![self.finder.find_by_player()?],
+            Search::ID(_) => vec![self.finder.find_by_id()?],
+        })
+    }
+
+    /// Write the games of the search into the SQLite database at path.
+    fn save_games(&self, path: &Path) -> Result<usize, ChessError> {
+        let database = GameDatabase::open(path)?;
+        let games = self.found_games()?;
+        let count = games.len();
+        for mut game in games.into_iter() {
+            self.seed(&mut game);
+            database.upsert(&mut game)?;
+        }
+        Ok(count)
+    }
+
+    /// Run the whole fetch and parse pipeline over the games of the search, returning how many
+    /// games were validated.
+    fn validate_games(&self) -> Result<usize, ChessError> {
+        let games = self.found_games()?;
+        let count = games.len();
+        for mut game in games.into_iter() {
+            self.seed(&mut game);
+            validate_game(&mut game)?;
+        }
+        Ok(count)
+    }
+
+    /// Write every game one after another.
+    fn write_all(&self, out: &mut dyn Write, games: Vec<Game>) -> Result<(), ChessError> {
+        for (i, mut game) in games.into_iter().enumerate() {
+            if i > 0 {
+                self.separate_games(out)?;
+            }
+            let displayer = self.display(&mut game)?;
+            self.write(out, &displayer)?;
+        }
+        Ok(())
+    }
+
+    /// Write what goes between two games of the output.
+    fn separate_games(&self, out: &mut dyn Write) -> Result<(), ChessError> {
+        if multi_game_support(&self.output) == MultiGame::Separated {
+            out.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    fn write(&self, out: &mut dyn Write, displayer: &GameDisplayer) -> Result<(), ChessError> {
+        out.write_all(&displayer.encode(&self.encoding))?;
+        out.write_all(b"\n")?;
+        Ok(())
+    }
+}
+
+/// Exercise every conversion of a game. Move decoding panics on unexpected input, so panics are
+/// caught and reported as an error.
+fn validate_game(game: &mut Game) -> Result<(), ChessError> {
+    game.to_json()?;
+    NormalizedGame::from_game(game).to_json()?;
+
+    panic::catch_unwind(AssertUnwindSafe(|| {
+        game.pgn();
+        game.moves();
+    }))
+    .map_err(|_| ChessError::ValidationError(format!("decoding {} failed", game.url())))
+}
+
+fn outcome_exit_code(outcome: Option<Outcome>) -> i32 {
+    match outcome {
+        Some(Outcome::Win) => 0,
+        Some(Outcome::Loss) => 1,
+        Some(Outcome::Draw) => 2,
+        None => UNKNOWN_OUTCOME_EXIT_CODE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finder::Pieces;
+    use crate::fixtures;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    #[test]
+    fn test_single_game_id() {
+        let args = vec!["cgf", "12345678910"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::ID("12345678910".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: None,
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_single_player_username() {
+        let args = vec!["cgf", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: None,
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_numeric_player_username() {
+        let args = vec!["cgf", "12345678910", "--player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("12345678910".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: None,
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_short_numeric_player_username() {
+        let args = vec!["cgf", "123456789"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.search, Search::Player("123456789".to_owned()));
+
+        let args = vec!["cgf", "1234567890"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.search, Search::ID("1234567890".to_owned()));
+    }
+
+    #[test]
+    fn test_looks_like_game_id() {
+        assert!(!looks_like_game_id("12345"));
+        assert!(!looks_like_game_id("123456789"));
+        assert!(looks_like_game_id("1234567890"));
+        assert!(looks_like_game_id("12345678910"));
+        assert!(!looks_like_game_id("a_player_with_a_long_name"));
+        assert!(!looks_like_game_id("12345678a10"));
+    }
+
+    #[test]
+    fn test_chess_dot_com_api_choice() {
+        let args = vec!["cgf", "a_player", "--api=chess.com"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: None,
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_lichess_dot_org_api_choice() {
+        let args = vec!["cgf", "a_player", "--api=lichess.org"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "lichess.org".to_string(),
+            pieces: None,
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_white_player_username() {
+        let args = vec!["cgf", "a_player", "--white"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: Some(Pieces::White),
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_white_player_opponent_username() {
+        let args = vec!["cgf", "a_player", "--white", "-o", "An_Opponent"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: Some(Pieces::White),
+            year: None,
+            month: None,
+            day: None,
+            opponents: vec!["an_opponent".to_string()],
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_last_player_games() {
+        let args = vec!["cgf", "a_player", "--last", "5", "--white"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: Some(Pieces::White),
+            year: None,
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: Some(5),
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+
+        let args = vec!["cgf", "a_player", "--last", "0"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_progress() {
+        let args = vec!["cgf", "a_player", "--year", "2020", "--progress"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let finder = GameFinder {
+            search: Search::Player("a_player".to_owned()),
+            api: "chess.com".to_string(),
+            pieces: None,
+            year: Some(2020),
+            month: None,
+            day: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: true,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
+        };
+        assert_eq!(cgf.finder, finder);
+    }
+
+    #[test]
+    fn test_today() {
+        let args = vec!["cgf", "a_player", "--today"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let today = Utc::now();
+        assert_eq!(cgf.finder.year, Some(today.year() as u32));
+        assert_eq!(cgf.finder.month, Some(today.month()));
+        assert_eq!(cgf.finder.day, Some(today.day()));
+
+        for conflict in [
+            vec!["--date", "2020-11-01T00:00:00Z"],
+            vec!["--year", "2020"],
+            vec!["--month", "11"],
+            vec!["--day", "1"],
+        ] {
+            let mut args = vec!["cgf", "a_player", "--today"];
+            args.extend(conflict);
+            assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_week() {
+        let args = vec!["cgf", "a_player", "--week", "53", "--year", "2020"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.from, Some(NaiveDate::from_ymd(2020, 12, 28)));
+        assert_eq!(cgf.finder.to, Some(NaiveDate::from_ymd(2021, 1, 3)));
+        assert_eq!(cgf.finder.year, None);
+
+        let args = vec!["cgf", "a_player", "--week", "53", "--year", "2021"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--week", "54"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--this-week"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let today = Utc::now().date().naive_utc();
+        let (from, to) = (cgf.finder.from.unwrap(), cgf.finder.to.unwrap());
+        assert_eq!(from.weekday(), Weekday::Mon);
+        assert!(from <= today && today <= to);
+
+        let args = vec!["cgf", "a_player", "--this-week", "--month", "5"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_all() {
+        let args = vec!["cgf", "a_player", "--all", "--json"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.all);
+
+        let args = vec!["cgf", "a_player", "--all", "--json-pretty"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "1234567890", "--all"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--all", "--last", "5"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--all", "--evals"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_strict() {
+        let args = vec!["cgf", "a_player", "--strict"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.strict);
+    }
+
+    #[test]
+    fn test_limit() {
+        let args = vec!["cgf", "a_player", "--all", "-n", "5"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.limit, Some(5));
+
+        let args = vec!["cgf", "a_player", "--all", "--limit", "0"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_usernames_are_trimmed() {
+        let args = vec!["cgf", " a_player\t", "--opponent", "one, two "];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.search, Search::Player("a_player".to_string()));
+        assert_eq!(cgf.finder.opponents, vec!["one", "two"]);
+    }
+
+    #[test]
+    fn test_invalid_usernames_are_rejected() {
+        for player in ["a player", "a/player", "../a_player", "player?x=1", " "].iter() {
+            let args = vec!["cgf", player];
+            assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        }
+
+        let args = vec!["cgf", "a_player", "--opponent", "an opponent"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        assert_eq!(clean_username(" a-Player_1 "), Ok("a-Player_1"));
+    }
+
+    #[test]
+    fn test_since_game_id() {
+        let args = vec!["cgf", "a_player", "--since-game-id", "1234567890", "--pgn"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.since_game_id, Some("1234567890".to_string()));
+        assert_eq!(cgf.finder.last, None);
+
+        let args = vec!["cgf", "1234567890", "--since-game-id", "1234567891"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--since-game-id",
+            "1234567890",
+            "--watch",
+        ];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_list_archives() {
+        let args = vec!["cgf", "a_player", "--list-archives"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.list_archives);
+
+        let args = vec!["cgf", "a_player", "--list-archives", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_capabilities_fail_when_parsing() {
+        let args = vec!["cgf", "a_player", "--list-archives", "--api", "lichess.org"];
+        let err = ChessGameFinderCLI::new_from(args.into_iter())
+            .err()
+            .unwrap();
+        assert_eq!(err.kind, clap::ErrorKind::ArgumentConflict);
+        assert!(err
+            .message
+            .contains("lichess.org doesn't support listing archives"));
+        assert!(err
+            .message
+            .contains("lichess.org supports finding games by ID"));
+
+        let args = vec!["cgf", "a_player", "--ongoing", "--api", "lichess.org"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--ongoing"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_ok());
+
+        let args = vec!["cgf", "rating-history", "a_player", "--api", "lichess.org"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_ok());
+    }
+
+    #[test]
+    fn test_compare_ratings() {
+        let args = vec!["cgf", "a_player", "--compare-ratings", "--line"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.compare_ratings);
+
+        let args = vec!["cgf", "a_player", "--compare-ratings", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "1234567890", "--compare-ratings"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_merge_policy() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--last",
+            "3",
+            "--prefer-source",
+            "lichess.org",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(!cgf.finder.fail_fast);
+
+        let mut args = vec![
+            "cgf",
+            "a_player",
+            "--last",
+            "3",
+            "--prefer-source",
+            "lichess.org",
+        ];
+        args.push("--fail-fast");
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.fail_fast);
+
+        let args = vec!["cgf", "a_player", "--fail-fast"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--last",
+            "3",
+            "--prefer-source",
+            "lichess.org",
+            "--fail-fast",
+            "--best-effort",
+        ];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_random() {
+        let args = vec!["cgf", "a_player", "--random", "--seed", "42"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.random, Some(42));
+
+        let args = vec!["cgf", "a_player", "--random"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.random.is_some());
+
+        let args = vec!["cgf", "a_player", "--seed", "42"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--random", "--last", "5"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_watch() {
+        let args = vec!["cgf", "a_player", "--watch", "--pgn"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.watch, Some(60));
+
+        let args = vec!["cgf", "a_player", "--watch", "--watch-interval", "300"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.watch, Some(300));
+
+        let args = vec!["cgf", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.watch, None);
+
+        let args = vec!["cgf", "a_player", "--watch", "--watch-interval", "1"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--watch", "--last", "5"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_multiple_filter_values() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--eco",
+            "B20",
+            "--eco",
+            "b12",
+            "--result",
+            "win,draw",
+            "--opponent",
+            "Hikaru",
+            "--opponent",
+            "magnus",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.ecos, vec!["B20", "B12"]);
+        assert_eq!(cgf.finder.results, vec![Outcome::Win, Outcome::Draw]);
+        assert_eq!(cgf.finder.opponents, vec!["hikaru", "magnus"]);
+
+        let args = vec!["cgf", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.ecos.is_empty());
+        assert!(cgf.finder.results.is_empty());
+        assert!(cgf.finder.opponents.is_empty());
+
+        let args = vec!["cgf", "a_player", "--result", "won"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_dedupe_by() {
+        let args = vec!["cgf", "a_player", "--last", "3", "--dedupe-by", "opponent"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.dedupe_by, Some(DedupeKey::Opponent));
+
+        let args = vec!["cgf", "a_player", "--dedupe-by", "color"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_date_range() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--from",
+            "2021-01-01",
+            "--to",
+            "2021-03-31",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.from, Some(NaiveDate::from_ymd(2021, 1, 1)));
+        assert_eq!(cgf.finder.to, Some(NaiveDate::from_ymd(2021, 3, 31)));
+
+        let args = vec!["cgf", "a_player", "--from", "01/01/2021"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_reversed_date_range_fails_before_searching() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--from",
+            "2021-03-31",
+            "--to",
+            "2021-01-01",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let mut out = Vec::new();
+        match cgf.run_to(&mut out) {
+            Err(ChessError::ValidationError(reason)) => {
+                assert_eq!(reason, "from date 2021-03-31 is after to date 2021-01-01")
+            }
+            r => panic!("expected a validation error, got {:?}", r),
+        }
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_prefer_source() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--last",
+            "5",
+            "--prefer-source",
+            "lichess.org",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.prefer_source, Some("lichess.org".to_string()));
+
+        let args = vec!["cgf", "a_player", "--prefer-source", "lichess.org"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_columns() {
+        let cgf = ChessGameFinderCLI::new_from(vec!["cgf", "a_player"].into_iter()).unwrap();
+        assert_eq!(cgf.columns, DEFAULT_COLUMNS.to_vec());
+
+        let args = vec!["cgf", "a_player", "--columns", "url,players"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.columns, vec![TableColumn::Url, TableColumn::Players]);
+
+        let args = vec!["cgf", "a_player", "--columns", "players,clocks"];
+        match ChessGameFinderCLI::new_from(args.into_iter()) {
+            Err(e) => assert!(e.to_string().contains(
+                "valid columns are: players, result, termination, opening, speed, moves, duration, url"
+            )),
+            Ok(_) => panic!("expected an unknown column error"),
+        }
+    }
+
+    #[test]
+    fn test_cache_dir() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--opponent",
+            "bob",
+            "--cache-dir",
+            "cache",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(
+            cgf.finder.cache_dir,
+            Some(std::path::PathBuf::from("cache"))
+        );
+    }
+
+    #[test]
+    fn test_checkmate_and_stalemate() {
+        let args = vec!["cgf", "a_player", "--checkmate", "--white"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.checkmate, Some(true));
+        assert_eq!(cgf.finder.stalemate, None);
+        assert_eq!(cgf.finder.pieces, Some(Pieces::White));
+
+        let args = vec!["cgf", "a_player", "--stalemate"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.stalemate, Some(true));
+
+        let args = vec!["cgf", "a_player", "--checkmate", "--stalemate"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_minimal_pgn_output() {
+        let args = vec!["cgf", "a_player", "--minimal-pgn"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.output, "minimal-pgn");
+
+        let args = vec!["cgf", "a_player", "--minimal-pgn", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_quiet_errors() {
+        let cgf = ChessGameFinderCLI::new_from(vec!["cgf", "a_player"].into_iter()).unwrap();
+        assert!(!cgf.finder.quiet_errors);
+
+        let args = vec!["cgf", "a_player", "--last", "50", "--quiet-errors"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.quiet_errors);
+    }
+
+    #[test]
+    fn test_seed_position() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let args = vec!["cgf", "a_player", "--seed-position", fen];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.seed_position, Some(fen.to_string()));
+
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--seed-position",
+            "8/8/8/8/8/8/8/8 w - - 0 1",
+        ];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_normalize_usernames_uses_resolved_names() {
+        let args = vec![
+            "cgf",
+            "q7ZvsdUF",
+            "--api",
+            "lichess.org",
+            "--normalize-usernames",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let usernames = cgf.usernames.as_ref().unwrap();
+        for (name, resolved) in [("lance5500", "LANCE5500"), ("tryinghard87", "TryingHard87")] {
+            usernames.borrow_mut().insert(
+                ("lichess.org".to_string(), name.to_string()),
+                resolved.to_string(),
+            );
+        }
+
+        let mut game = Game::LichessDotOrg(fixtures::lichess_game_with(json!({
+            "players": {
+                "white": {"user": {"name": "Lance5500", "id": "lance5500"}, "rating": 2389},
+                "black": {"user": {"name": "tryinghard87", "id": "tryinghard87"}, "rating": 2498}
+            }
+        })));
+        cgf.normalize_usernames(&mut game);
+        match game {
+            Game::LichessDotOrg(g) => {
+                assert_eq!(g.players.white.user.unwrap().name, "LANCE5500");
+                assert_eq!(g.players.black.user.unwrap().name, "TryingHard87");
+            }
+            _ => unreachable!(),
+        }
+
+        let args = vec!["cgf", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.usernames.is_none());
+    }
+
+    #[test]
+    fn test_analysis_url() {
+        let args = vec!["cgf", "a_player", "--analysis-url", "--ply", "20"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.output, "analysis-url");
+        assert_eq!(cgf.ply, Some(20));
+
+        let args = vec!["cgf", "a_player", "--analysis-url"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.ply, None);
+
+        let args = vec!["cgf", "a_player", "--pgn", "--ply", "20"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
     }
 
     #[test]
-    fn test_numeric_player_username() {
-        let args = vec!["cgf", "12345678910", "--player"];
+    fn test_board_every_move() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--board-every-move",
+            "--from-ply",
+            "10",
+            "--to-ply",
+            "20",
+            "--fancy-board",
+        ];
         let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
-        let finder = GameFinder {
-            search: Search::Player("12345678910".to_owned()),
-            api: "chess.com".to_string(),
-            pieces: None,
-            year: None,
-            month: None,
-            day: None,
-            opponent: None,
-        };
-        assert_eq!(cgf.finder, finder);
+        assert_eq!(cgf.output, "board-every-move");
+        assert_eq!(
+            cgf.board_steps,
+            BoardSteps {
+                from_ply: Some(10),
+                to_ply: Some(20),
+                fancy: true,
+            }
+        );
+
+        let args = vec!["cgf", "a_player", "--pgn", "--to-ply", "20"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
     }
 
     #[test]
-    fn test_chess_dot_com_api_choice() {
-        let args = vec!["cgf", "a_player", "--api=chess.com"];
+    fn test_explain_pgn_only_applies_to_chess_com_live_games() {
+        let args = vec!["cgf", "1234567890", "--explain-pgn"];
         let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
-        let finder = GameFinder {
-            search: Search::Player("a_player".to_owned()),
-            api: "chess.com".to_string(),
-            pieces: None,
-            year: None,
-            month: None,
-            day: None,
-            opponent: None,
-        };
-        assert_eq!(cgf.finder, finder);
+        assert_eq!(cgf.output, "explain-pgn");
+
+        let args = vec!["cgf", "a_player", "--explain-pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "q7ZvsdUF", "--api", "lichess.org", "--explain-pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
     }
 
     #[test]
-    fn test_lichess_dot_org_api_choice() {
-        let args = vec!["cgf", "a_player", "--api=lichess.org"];
+    fn test_truncate_moves() {
+        let args = vec!["cgf", "a_player", "--pgn", "--truncate-moves", "12"];
         let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
-        let finder = GameFinder {
-            search: Search::Player("a_player".to_owned()),
-            api: "lichess.org".to_string(),
-            pieces: None,
-            year: None,
-            month: None,
-            day: None,
-            opponent: None,
-        };
-        assert_eq!(cgf.finder, finder);
+        assert_eq!(cgf.truncate_moves, Some(12));
+
+        let args = vec!["cgf", "a_player", "--pgn", "--truncate-moves", "0"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--json", "--truncate-moves", "12"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
     }
 
     #[test]
-    fn test_white_player_username() {
-        let args = vec!["cgf", "a_player", "--white"];
+    fn test_strip_annotations() {
+        let args = vec!["cgf", "a_player", "--pgn", "--strip-annotations"];
         let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
-        let finder = GameFinder {
-            search: Search::Player("a_player".to_owned()),
-            api: "chess.com".to_string(),
-            pieces: Some(Pieces::White),
-            year: None,
-            month: None,
-            day: None,
-            opponent: None,
-        };
-        assert_eq!(cgf.finder, finder);
+        assert!(cgf.strip_annotations);
+        assert_eq!(
+            cgf.edit_pgn("1. e4 { [%clk 0:03:00] } 1... e5 $1 1-0".to_string()),
+            "1. e4 e5 1-0"
+        );
+
+        let args = vec!["cgf", "a_player", "--uci", "--strip-annotations"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_group_by() {
+        let args = vec!["cgf", "a_player", "--group-by", "opening", "--last", "50"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.group_by, Some(GroupKey::Opening));
+
+        let args = vec!["cgf", "a_player", "--group-by", "weekday"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+
+        let args = vec!["cgf", "a_player", "--group-by", "color", "--repertoire"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_speed_and_variant() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--api",
+            "lichess.org",
+            "--speed",
+            "blitz",
+            "--variant",
+            "chess960",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.speed, Some(Speed::Blitz));
+        assert_eq!(cgf.finder.variant, Some("chess960".to_string()));
+    }
+
+    #[test]
+    fn test_time_control() {
+        let args = vec!["cgf", "a_player", "--time-control", "rapid"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.time_class, Some(TimeClass::Rapid));
+        assert_eq!(cgf.finder.speed, None);
+
+        let args = vec!["cgf", "a_player", "-t", "bullet"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.time_class, Some(TimeClass::Bullet));
+
+        let args = vec!["cgf", "a_player", "-t", "correspondence"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_exact_opponent() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--opponent",
+            "Anonymous",
+            "--exact-opponent",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.exact_opponent);
+        assert_eq!(cgf.finder.opponents, vec!["Anonymous"]);
+
+        let args = vec!["cgf", "a_player", "--exact-opponent"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_max_total_retries() {
+        let args = vec!["cgf", "a_player", "--max-total-retries", "50"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.max_total_retries, Some(50));
+
+        let args = vec!["cgf", "a_player", "--max-total-retries", "many"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_lichess_params() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--api",
+            "lichess.org",
+            "--lichess-param",
+            "accuracy=true",
+            "--lichess-param",
+            "tags=false",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(
+            cgf.finder.lichess_params,
+            vec![
+                ("accuracy".to_string(), "true".to_string()),
+                ("tags".to_string(), "false".to_string())
+            ]
+        );
+
+        let args = vec!["cgf", "a_player", "--lichess-param", "until=0"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        let args = vec!["cgf", "a_player", "--lichess-param", "accuracy"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_unique() {
+        let args = vec!["cgf", "a_player", "--unique"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.unique);
+
+        let args = vec!["cgf", "a_player", "--unique", "--last", "2"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        let args = vec!["cgf", "1234567890", "--unique"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_rating_range() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--min-rating",
+            "2000",
+            "--max-rating",
+            "2200",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.min_rating, Some(2000));
+        assert_eq!(cgf.finder.max_rating, Some(2200));
+
+        let args = vec!["cgf", "a_player", "--min-rating", "strong"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_rating_change() {
+        let args = vec!["cgf", "a_player", "--min-rating-gain", "10"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.min_rating_gain, Some(10));
+
+        let args = vec!["cgf", "a_player", "--max-rating-loss", "8"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.max_rating_loss, Some(8));
+
+        let args = vec!["cgf", "a_player", "--max-rating-loss", "-8"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--min-rating-gain",
+            "10",
+            "--max-rating-loss",
+            "8",
+        ];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_exclude_provisional() {
+        let args = vec!["cgf", "a_player", "--exclude-provisional"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.finder.exclude_provisional);
+    }
+
+    #[test]
+    fn test_titled_opponents() {
+        let args = vec!["cgf", "a_player", "--titled"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.opponent_titles, Some(Vec::new()));
+
+        let args = vec!["cgf", "a_player", "--opponent-title", "gm,IM"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(
+            cgf.finder.opponent_titles,
+            Some(vec!["GM".to_string(), "IM".to_string()])
+        );
+
+        let args = vec!["cgf", "a_player", "--opponent-title", "XX"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_multi_game_output_support() {
+        assert_eq!(multi_game_support("pgn"), MultiGame::Separated);
+        assert_eq!(multi_game_support("table"), MultiGame::Separated);
+        assert_eq!(multi_game_support("json"), MultiGame::OnePerLine);
+        assert_eq!(multi_game_support("clocks-json"), MultiGame::OnePerLine);
+
+        for display in &["--pgn", "--json", "--report"] {
+            let args = vec!["cgf", "a_player", "--last", "5", display];
+            assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_ok());
+        }
+        for display in &["--json-pretty", "--evals"] {
+            let args = vec!["cgf", "a_player", "--last", "5", display];
+            assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+            // A single game can be displayed in any mode
+            let args = vec!["cgf", "a_player", display];
+            assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_line_output() {
+        let args = vec!["cgf", "a_player", "--line", "--last", "20", "--no-color"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.output, "line");
+        assert!(!cgf.color);
+    }
+
+    #[test]
+    fn test_sqlite() {
+        let args = vec!["cgf", "a_player", "--last", "10", "--sqlite", "games.db"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.sqlite, Some(PathBuf::from("games.db")));
+
+        let args = vec!["cgf", "a_player", "--sqlite", "games.db", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_duration_bounds() {
+        let args = vec![
+            "cgf",
+            "a_player",
+            "--min-duration",
+            "1h",
+            "--max-duration",
+            "7200",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.min_duration, Some(chrono::Duration::hours(1)));
+        assert_eq!(cgf.finder.max_duration, Some(chrono::Duration::hours(2)));
+
+        let args = vec!["cgf", "a_player", "--min-duration", "an hour"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_rating_history_subcommand() {
+        let args = vec![
+            "cgf",
+            "rating-history",
+            "a_player",
+            "--speed",
+            "blitz",
+            "--api",
+            "lichess.org",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.rating_history, Some(Some(Speed::Blitz)));
+        assert_eq!(cgf.finder, GameFinder::by_player("a_player", "lichess.org"));
+
+        let args = vec!["cgf", "rating-history", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.rating_history, Some(None));
+        assert_eq!(cgf.finder.api, "chess.com");
+
+        let args = vec!["cgf", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.rating_history, None);
+
+        let args = vec!["cgf"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_export_subcommand() {
+        let args = vec![
+            "cgf",
+            "export",
+            "a_player",
+            "--api",
+            "lichess.org",
+            "--to",
+            "games",
+            "--resume",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.export, Some((PathBuf::from("games"), true)));
+        assert_eq!(cgf.finder.api, "lichess.org");
+        assert_eq!(cgf.finder.search, Search::Player("a_player".to_string()));
+
+        let args = vec!["cgf", "export", "a_player"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_import_to_lichess_subcommand() {
+        let args = vec![
+            "cgf",
+            "import-to-lichess",
+            "1234567890",
+            "--token",
+            "lip_abc",
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.import_to_lichess);
+        assert_eq!(cgf.lichess_token, Some("lip_abc".to_string()));
+        assert_eq!(cgf.finder.api, "chess.com");
+        assert_eq!(cgf.finder.search, Search::ID("1234567890".to_string()));
+
+        let args = vec!["cgf", "import-to-lichess"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_tournaments_subcommand() {
+        let args = vec!["cgf", "tournaments", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.tournaments);
+        assert_eq!(cgf.finder.api, "chess.com");
+        assert_eq!(cgf.finder.search, Search::Player("a_player".to_string()));
+
+        let args = vec!["cgf", "tournaments"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_status_subcommand() {
+        let args = vec!["cgf", "status", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.status);
+        assert!(!cgf.tournaments);
+        assert_eq!(cgf.finder.api, "chess.com");
+        assert_eq!(cgf.finder.search, Search::Player("a_player".to_string()));
+
+        let args = vec!["cgf", "status"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_concurrency() {
+        let args = vec!["cgf", "a_player", "--concurrency", "8"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.concurrency, 8);
+
+        let args = vec!["cgf", "a_player"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.finder.concurrency, 1);
+
+        let args = vec!["cgf", "a_player", "--concurrency", "0"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_parse_id_entry() {
+        assert_eq!(
+            parse_id_entry("chess.com:123"),
+            Ok(("chess.com", "123".to_string()))
+        );
+        assert_eq!(
+            parse_id_entry(" lichess.org: q7ZvsdUF "),
+            Ok(("lichess.org", "q7ZvsdUF".to_string()))
+        );
+        assert_eq!(
+            parse_id_entry("1234567890"),
+            Ok(("chess.com", "1234567890".to_string()))
+        );
+        assert_eq!(
+            parse_id_entry("q7ZvsdUF"),
+            Ok(("lichess.org", "q7ZvsdUF".to_string()))
+        );
+
+        // Short numbers may be either API
+        assert!(parse_id_entry("12345678").is_err());
+        assert!(parse_id_entry("123456789").is_err());
+        assert!(parse_id_entry("lichess.org:").is_err());
+        assert!(parse_id_entry("chess.org:1234567890").is_err());
+        assert!(parse_id_entry("https://lichess.org/q7ZvsdUF").is_err());
+    }
+
+    #[test]
+    fn test_template_file() {
+        let path = std::env::temp_dir().join(format!("cgf_template_{}.tmpl", std::process::id()));
+        fs::write(&path, "{white} vs {black}\n{url}\n").unwrap();
+        let args = vec!["cgf", "a_player", "--template-file", path.to_str().unwrap()];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.output, "template-file");
+        assert!(cgf.template.is_some());
+
+        fs::write(&path, "{white} vs {opponent}\n").unwrap();
+        let args = vec!["cgf", "a_player", "--template-file", path.to_str().unwrap()];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        fs::remove_file(&path).unwrap();
+
+        let args = vec!["cgf", "a_player", "--template-file", "missing.tmpl"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        let args = vec!["cgf", "a_player", "--template-file", "a.tmpl", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_ids_file() {
+        let path = std::env::temp_dir().join(format!("cgf_ids_{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "# games to review\n1234567890\n\nlichess.org:q7ZvsdUF\n",
+        )
+        .unwrap();
+        assert_eq!(
+            read_ids_file(&path).unwrap(),
+            vec![
+                ("chess.com", "1234567890".to_string()),
+                ("lichess.org", "q7ZvsdUF".to_string())
+            ]
+        );
+
+        let args = vec!["cgf", "--ids-file", path.to_str().unwrap(), "--pgn"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.ids_file, Some(path.clone()));
+
+        fs::write(&path, "1234567890\n12345678\n").unwrap();
+        match read_ids_file(&path) {
+            Err(ChessError::ValidationError(e)) => assert!(e.ends_with(":2: can't tell the API of game ID 12345678, prefix it with chess.com: or lichess.org:")),
+            r => panic!("expected a validation error, got {:?}", r),
+        }
+        fs::remove_file(&path).unwrap();
+
+        let args = vec!["cgf", "--ids-file", "ids.txt", "--json-pretty"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        let args = vec!["cgf", "a_player", "--ids-file", "ids.txt"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_dump_fixtures() {
+        let args = vec!["cgf", "a_player", "--dump-fixtures", "fixtures"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(
+            cgf.finder.dump_fixtures,
+            Some(std::path::PathBuf::from("fixtures"))
+        );
+    }
+
+    #[test]
+    fn test_raw_conflicts_with_display() {
+        let args = vec!["cgf", "12345678910", "--raw"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.raw);
+
+        let args = vec!["cgf", "12345678910", "--raw", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_outcome_exit_code() {
+        assert_eq!(outcome_exit_code(Some(Outcome::Win)), 0);
+        assert_eq!(outcome_exit_code(Some(Outcome::Loss)), 1);
+        assert_eq!(outcome_exit_code(Some(Outcome::Draw)), 2);
+
+        let args = vec!["cgf", "a_player", "--exit-code"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.exit_code);
+    }
+
+    #[test]
+    fn test_unknown_outcome_exit_code() {
+        // Unfinished games must not read as a win
+        assert_eq!(outcome_exit_code(None), UNKNOWN_OUTCOME_EXIT_CODE);
+        assert_ne!(
+            outcome_exit_code(None),
+            outcome_exit_code(Some(Outcome::Win))
+        );
+        assert_ne!(outcome_exit_code(None), NOT_FOUND_EXIT_CODE);
+    }
+
+    #[test]
+    fn test_repertoire() {
+        let args = vec!["cgf", "a_player", "--repertoire"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.repertoire, Some(None));
+
+        let args = vec!["cgf", "a_player", "--repertoire", "--repertoire-plies", "4"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.repertoire, Some(Some(4)));
+
+        let args = vec!["cgf", "a_player", "--repertoire-plies", "4"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_report_output() {
+        let args = vec!["cgf", "a_player", "--last", "3", "--report"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.output, "report");
+    }
+
+    #[test]
+    fn test_validate_conflicts_with_display() {
+        let args = vec!["cgf", "a_player", "--validate"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.validate);
+
+        let args = vec!["cgf", "a_player", "--validate", "--pgn"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_write_to_buffer() {
+        let args = vec!["cgf", "a_player", "--pgn", "--encoding=latin1"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        let mut buffer: Vec<u8> = Vec::new();
+        let displayer = GameDisplayer::Default("[White \"Jesús\"]".to_string());
+        cgf.write(&mut buffer, &displayer).unwrap();
+
+        assert_eq!(buffer, b"[White \"Jes\xfas\"]\n".to_vec());
+    }
+
+    #[test]
+    fn test_clipboard_output() {
+        let args = vec!["cgf", "a_player", "--pgn", "--clipboard"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.clipboard);
+
+        let args = vec!["cgf", "a_player", "--pgn"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(!cgf.clipboard);
+    }
+
+    #[test]
+    fn test_output_file() {
+        let path = std::env::temp_dir().join(format!("cgf_output_{}.pgn", std::process::id()));
+        fs::write(&path, "an older and longer output").unwrap();
+        let args = vec![
+            "cgf".to_string(),
+            "a_player".to_string(),
+            "--pgn".to_string(),
+            "-O".to_string(),
+            path.display().to_string(),
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.output_file, Some(path.clone()));
+        assert!(!cgf.color);
+
+        // A search that fails leaves the file as it was
+        let args = vec![
+            "cgf".to_string(),
+            "a_player".to_string(),
+            "--min-rating=2000".to_string(),
+            "--max-rating=1000".to_string(),
+            "-O".to_string(),
+            path.display().to_string(),
+        ];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert!(cgf.run_to_file(&path).is_err());
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written, "an older and longer output");
+
+        let args = vec!["cgf", "a_player", "--output-file", "a.pgn", "--clipboard"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+        let args = vec!["cgf", "a_player", "--output-file", "a.pgn", "--watch"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
+    }
+
+    #[test]
+    fn test_latin1_encoding_requires_pgn() {
+        let args = vec!["cgf", "a_player", "--pgn", "--encoding=latin1"];
+        let cgf = ChessGameFinderCLI::new_from(args.into_iter()).unwrap();
+        assert_eq!(cgf.encoding, OutputEncoding::Latin1);
+
+        let args = vec!["cgf", "a_player", "--encoding=latin1"];
+        assert!(ChessGameFinderCLI::new_from(args.into_iter()).is_err());
     }
 
     #[test]
@@ -318,7 +3758,41 @@ mod tests {
             year: None,
             month: None,
             day: None,
-            opponent: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: 1,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
         };
         assert_eq!(cgf.finder, finder);
     }
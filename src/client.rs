@@ -1,12 +1,25 @@
 use std::fmt::Debug;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
 use std::time::Duration;
 
 use chrono::{self, DateTime, Datelike, TimeZone, Utc};
-use reqwest::{self, blocking::Client};
+use reqwest::{
+    self,
+    blocking::{Client, Request, Response},
+    header::{HeaderValue, ETAG, IF_NONE_MATCH, RETRY_AFTER},
+    StatusCode,
+};
+use serde::de::DeserializeOwned;
 use serde_json;
 use thiserror::Error;
 
 use crate::api::{self, chessdotcom, lichessdotorg, Api, Game, Games};
+use crate::cache::{CachedResponse, ResponseCache};
 
 #[derive(Error, Debug)]
 pub enum ClientError {
@@ -18,11 +31,185 @@ pub enum ClientError {
     ApiError(#[from] api::ApiError),
     #[error("Failed to deserialize JSON response")]
     JSONDeserializationError(#[from] serde_json::Error),
+    #[error("Failed to write fixture")]
+    FixtureWriteError(#[from] io::Error),
+    #[error("this profile's games are private: {username}")]
+    ProfileRestricted { username: String },
+    #[error("chess.com game {id} is not a live game, {reason}. Daily games can't be looked up by ID, find them with a search of their player instead")]
+    NotALiveGame { id: String, reason: String },
+    #[error("the lichess.org stream of games was interrupted after {read} games")]
+    StreamInterrupted {
+        read: usize,
+        #[source]
+        source: io::Error,
+    },
+}
+
+/// Endpoints whose raw response can be requested, for debugging payloads that fail to
+/// deserialize.
+#[derive(Debug, PartialEq)]
+pub enum RawRequest<'a> {
+    Game(&'a str),
+    Archives(&'a str),
+    MonthGames {
+        username: &'a str,
+        year: i32,
+        month: u32,
+    },
+    LastGames {
+        username: &'a str,
+        max: Option<usize>,
+    },
+    OngoingGames(&'a str),
+    RatingHistory(&'a str),
+    Stats(&'a str),
+    User(&'a str),
+    Tournament(&'a str),
+    TournamentRound {
+        id: &'a str,
+        round: u32,
+    },
+    PlayerTournaments(&'a str),
+    Status(&'a str),
+}
+
+impl<'a> RawRequest<'a> {
+    /// Name of the file the response to this request is saved to when dumping fixtures, e.g.
+    /// chesscom_month_user_2020_09.json.
+    pub fn fixture_name(&self, api: &Api) -> String {
+        let (prefix, extension) = match api {
+            Api::ChessDotCom => ("chesscom", "json"),
+            Api::LichessDotOrg => match self {
+                RawRequest::Game(_)
+                | RawRequest::RatingHistory(_)
+                | RawRequest::Stats(_)
+                | RawRequest::Status(_)
+                | RawRequest::User(_) => ("lichess", "json"),
+                _ => ("lichess", "ndjson"),
+            },
+        };
+        let name = match self {
+            RawRequest::Game(id) => format!("game_{}", id),
+            RawRequest::Archives(username) => format!("archives_{}", username),
+            RawRequest::MonthGames {
+                username,
+                year,
+                month,
+            } => format!("month_{}_{}_{:02}", username, year, month),
+            RawRequest::LastGames {
+                username,
+                max: Some(max),
+            } => format!("last_{}_{}", username, max),
+            RawRequest::LastGames {
+                username,
+                max: None,
+            } => format!("last_{}_all", username),
+            RawRequest::OngoingGames(username) => format!("ongoing_{}", username),
+            RawRequest::RatingHistory(username) => format!("rating_history_{}", username),
+            RawRequest::Stats(username) => format!("stats_{}", username),
+            RawRequest::User(username) => format!("user_{}", username),
+            RawRequest::Tournament(id) => format!("tournament_{}", id),
+            RawRequest::TournamentRound { id, round } => format!("tournament_{}_{}", id, round),
+            RawRequest::PlayerTournaments(username) => format!("tournaments_{}", username),
+            RawRequest::Status(username) => format!("status_{}", username),
+        };
+        format!("{}_{}.{}", prefix, name, extension)
+    }
+}
+
+/// lichess asks clients to wait a full minute after being rate limited. chess.com doesn't say,
+/// so the same wait is used for it unless it sends a Retry-After header.
+const RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// How many times a rate limited request is retried before giving up.
+const RATE_LIMIT_RETRIES: usize = 3;
+
+/// How many times an interrupted stream of lichess games is resumed before giving up.
+const STREAM_RESUMES: usize = 3;
+
+/// Longest delay inserted between requests while being rate limited.
+const MAX_PACING_DELAY: Duration = Duration::from_secs(30);
+
+/// Adaptive delay between requests. Every rate limited response doubles the delay, and every
+/// successful one halves it, so long scans slow down just enough to stay under the limit.
+#[derive(Debug, Default, PartialEq)]
+pub struct Pacer {
+    delay: Duration,
+    throttled: usize,
+}
+
+impl Pacer {
+    pub fn delay(&self) -> Duration {
+        self.delay
+    }
+
+    /// Number of rate limited responses seen so far.
+    pub fn throttled(&self) -> usize {
+        self.throttled
+    }
+
+    pub fn on_rate_limited(&mut self) {
+        self.throttled += 1;
+        self.delay = (self.delay * 2)
+            .max(Duration::from_secs(1))
+            .min(MAX_PACING_DELAY);
+    }
+
+    pub fn on_success(&mut self) {
+        self.delay /= 2;
+        if self.delay < Duration::from_millis(250) {
+            self.delay = Duration::from_secs(0);
+        }
+    }
+}
+
+/// How long to wait after a rate limited response: as long as its Retry-After header asks, in
+/// seconds, or else RATE_LIMIT_WAIT.
+fn rate_limit_wait(response: &Response) -> Duration {
+    response
+        .headers()
+        .get(RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map_or(RATE_LIMIT_WAIT, Duration::from_secs)
+}
+
+/// Games parsed, and games skipped because they could not be parsed, over the whole run.
+static GAMES_PARSED: AtomicUsize = AtomicUsize::new(0);
+static GAMES_SKIPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Retries of failed requests over the whole run, capped by ChessClient::max_total_retries.
+static RETRIES: AtomicUsize = AtomicUsize::new(0);
+
+/// Count a retry in retries unless that would exceed the budget, if there is one. Returns
+/// whether the retry can be made.
+fn take_retry(retries: &AtomicUsize, budget: Option<usize>) -> bool {
+    retries
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| match budget {
+            Some(budget) if used >= budget => None,
+            _ => Some(used + 1),
+        })
+        .is_ok()
+}
+
+/// Number of games skipped because they could not be parsed, and the number of games seen in
+/// total, over the whole run.
+pub fn skipped_games() -> (usize, usize) {
+    let skipped = GAMES_SKIPPED.load(Ordering::SeqCst);
+    (skipped, skipped + GAMES_PARSED.load(Ordering::SeqCst))
 }
 
 pub struct ChessClient {
     client: Client,
     api: Api,
+    pacer: Arc<Mutex<Pacer>>,
+    fixtures_dir: Option<PathBuf>,
+    responses: Option<ResponseCache>,
+    perf_type: Option<String>,
+    lichess_params: Vec<(String, String)>,
+    max_total_retries: Option<usize>,
+    games_between: (Option<DateTime<Utc>>, Option<DateTime<Utc>>),
+    quiet_errors: bool,
 }
 
 impl ChessClient {
@@ -35,9 +222,301 @@ impl ChessClient {
                 .build()
                 .map_err(|source| ClientError::ClientBuildError(source))?,
             api: Api::from_str(api).expect("Unsupported API"),
+            pacer: Arc::new(Mutex::new(Pacer::default())),
+            fixtures_dir: None,
+            responses: None,
+            perf_type: None,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            games_between: (None, None),
+            quiet_errors: false,
         })
     }
 
+    /// Pace requests together with other, sharing its delay, for clients making requests at
+    /// the same time.
+    pub fn share_pacer(mut self, other: &ChessClient) -> Self {
+        self.pacer = Arc::clone(&other.pacer);
+        self
+    }
+
+    /// Save the body of every response into a file under dir, to be used as test fixtures.
+    pub fn dump_fixtures(mut self, dir: PathBuf) -> Self {
+        self.fixtures_dir = Some(dir);
+        self
+    }
+
+    /// Cache chess.com responses under dir along with their ETag, and make repeated requests
+    /// conditional on the response having changed.
+    pub fn cache_responses(mut self, dir: PathBuf) -> Self {
+        self.responses = Some(ResponseCache::new(dir));
+        self
+    }
+
+    /// Only request lichess games of the given perfType, e.g. "blitz" or "chess960".
+    pub fn perf_type(mut self, perf_type: String) -> Self {
+        self.perf_type = Some(perf_type);
+        self
+    }
+
+    /// Add extra query parameters to the requests of the lichess games endpoints.
+    pub fn lichess_params(mut self, params: Vec<(String, String)>) -> Self {
+        self.lichess_params = params;
+        self
+    }
+
+    /// Retry failed requests at most retries times over the whole run, counting the retries of
+    /// every client.
+    pub fn max_total_retries(mut self, retries: usize) -> Self {
+        self.max_total_retries = Some(retries);
+        self
+    }
+
+    /// Only request the last lichess games played between since and until.
+    pub fn games_between(
+        mut self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> Self {
+        self.games_between = (since, until);
+        self
+    }
+
+    /// Whether a failed request can be retried without exceeding the retries of the run.
+    fn take_retry(&self) -> bool {
+        let taken = take_retry(&RETRIES, self.max_total_retries);
+        if !taken {
+            eprintln!(
+                "Warning: the {} retries allowed by --max-total-retries are spent, not retrying",
+                self.max_total_retries.unwrap_or_default()
+            );
+        }
+        taken
+    }
+
+    /// A request of a games endpoint with the extra lichess parameters, if any.
+    fn games_request(&self, request: Request) -> Request {
+        match self.api {
+            Api::LichessDotOrg => api::with_lichess_params(request, &self.lichess_params),
+            Api::ChessDotCom => request,
+        }
+    }
+
+    /// Don't warn about each game skipped because it could not be parsed.
+    pub fn quiet_errors(mut self) -> Self {
+        self.quiet_errors = true;
+        self
+    }
+
+    /// Keep the games that were parsed, skipping those that could not be, so that one malformed
+    /// game doesn't fail a whole month.
+    fn tolerant<T, I>(&self, parsed: I) -> Vec<T>
+    where
+        I: IntoIterator<Item = Result<T, serde_json::Error>>,
+    {
+        let mut games = Vec::new();
+        for result in parsed {
+            match result {
+                Ok(game) => {
+                    GAMES_PARSED.fetch_add(1, Ordering::SeqCst);
+                    games.push(game);
+                }
+                Err(e) => {
+                    GAMES_SKIPPED.fetch_add(1, Ordering::SeqCst);
+                    if !self.quiet_errors {
+                        eprintln!("Warning: skipping a game that could not be parsed: {}", e);
+                    }
+                }
+            }
+        }
+        games
+    }
+
+    /// Read the body of the response to a request, saving it as a fixture if requested.
+    fn body(&self, response: Response, kind: RawRequest) -> Result<String, ClientError> {
+        let text = response.text()?;
+        self.save_fixture(kind, &text)?;
+        Ok(text)
+    }
+
+    fn save_fixture(&self, kind: RawRequest, text: &str) -> Result<(), ClientError> {
+        if let Some(dir) = &self.fixtures_dir {
+            fs::create_dir_all(dir)?;
+            let path = dir.join(kind.fixture_name(&self.api));
+            log::info!("Saving fixture {:?}", path);
+            fs::write(path, text)?;
+        }
+        Ok(())
+    }
+
+    /// Stream the lichess games of a request, reading them as they arrive. When the connection
+    /// drops mid-stream, the request is sent again for the games after the last one read, which
+    /// are older ones unless the games were sorted with sort=dateAsc.
+    fn stream_games(
+        &self,
+        request: Request,
+        max: Option<usize>,
+        kind: RawRequest,
+    ) -> Result<Games, ClientError> {
+        let ascending = self
+            .lichess_params
+            .iter()
+            .any(|(key, value)| key == "sort" && value == "dateAsc");
+        let (games, raw) = self.read_games(|last, read| {
+            let mut request = request
+                .try_clone()
+                .expect("GET requests without a body can be cloned");
+            if let Some(last) = last {
+                request = if ascending {
+                    with_query_param(request, "since", last.created_at.timestamp_millis() + 1)
+                } else {
+                    with_query_param(request, "until", last.created_at.timestamp_millis() - 1)
+                };
+            }
+            if let Some(max) = max {
+                request = with_query_param(request, "max", max.saturating_sub(read));
+            }
+            let response = self.execute(request)?;
+            log::debug!("Response: {:?}", response);
+            Ok(BufReader::new(response))
+        })?;
+        self.save_fixture(kind, &raw)?;
+        Ok(Games::LichessDotOrg(games))
+    }
+
+    /// Read NDJSON games one line at a time from the readers returned by open, which is called
+    /// again with the last game read and the number of games read so far whenever a reader fails,
+    /// up to STREAM_RESUMES times. Returns the games along with the lines read, kept only when
+    /// saving fixtures.
+    fn read_games<R, F>(
+        &self,
+        mut open: F,
+    ) -> Result<(Vec<lichessdotorg::Game>, String), ClientError>
+    where
+        R: BufRead,
+        F: FnMut(Option<&lichessdotorg::Game>, usize) -> Result<R, ClientError>,
+    {
+        let mut games: Vec<lichessdotorg::Game> = Vec::new();
+        let mut raw = String::new();
+        let mut resumes = 0;
+        let mut reader = open(None, 0)?;
+        loop {
+            let mut line = String::new();
+            match reader.read_line(&mut line) {
+                Ok(0) => return Ok((games, raw)),
+                Ok(_) => {
+                    if self.fixtures_dir.is_some() {
+                        raw.push_str(&line);
+                    }
+                    games.extend(self.tolerant(games_from_ndjson(&line)));
+                }
+                Err(e) if resumes < STREAM_RESUMES && self.take_retry() => {
+                    resumes += 1;
+                    eprintln!(
+                        "Warning: the lichess.org stream was interrupted after {} games ({}), resuming ({}/{})",
+                        games.len(),
+                        e,
+                        resumes,
+                        STREAM_RESUMES
+                    );
+                    reader = open(games.last(), games.len())?;
+                }
+                Err(e) => {
+                    return Err(ClientError::StreamInterrupted {
+                        read: games.len(),
+                        source: e,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Execute a request and read its body. When caching responses, chess.com requests send the
+    /// ETag of the cached response, and a 304 Not Modified is answered with the cached body.
+    fn fetch(&self, mut request: Request, kind: RawRequest) -> Result<String, ClientError> {
+        let cache = match (&self.responses, &self.api) {
+            (Some(cache), Api::ChessDotCom) => Some(cache),
+            _ => None,
+        };
+        let name = kind.fixture_name(&self.api);
+        let cached = cache.and_then(|c| c.get(&name));
+        if let Some(cached) = &cached {
+            if let Ok(etag) = HeaderValue::from_str(&cached.etag) {
+                request.headers_mut().insert(IF_NONE_MATCH, etag);
+            }
+        }
+
+        let response = self.execute(request)?;
+        log::debug!("Response: {:?}", response);
+        log::debug!(
+            "Response length: {}",
+            response.content_length().unwrap_or(0)
+        );
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (response.status(), cached) {
+            log::info!("{} not modified, using the cached response", name);
+            return Ok(cached.body);
+        }
+
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = self.body(response, kind)?;
+        if let (Some(cache), Some(etag)) = (cache, etag) {
+            let cached = CachedResponse {
+                etag,
+                body: body.clone(),
+            };
+            if let Err(e) = cache.put(&name, &cached) {
+                log::warn!("Failed to cache the response to {}: {}", name, e);
+            }
+        }
+        Ok(body)
+    }
+
+    /// Execute a request. Requests are paced, and retried after waiting when they are rate
+    /// limited.
+    fn execute(&self, mut request: Request) -> Result<Response, ClientError> {
+        let mut retries = 0;
+        loop {
+            let delay = self.pacer().delay();
+            if delay > Duration::from_secs(0) {
+                log::info!("Pacing requests, waiting {:?}", delay);
+                thread::sleep(delay);
+            }
+
+            let retry = request.try_clone();
+            let response = self.client.execute(request)?;
+            if response.status() != StatusCode::TOO_MANY_REQUESTS {
+                self.pacer().on_success();
+                return Ok(response);
+            }
+
+            self.pacer().on_rate_limited();
+            match retry {
+                Some(r) if retries < RATE_LIMIT_RETRIES && self.take_retry() => {
+                    retries += 1;
+                    let wait = rate_limit_wait(&response);
+                    eprintln!(
+                        "Rate limited by {}, waiting {}s before retrying ({}/{})",
+                        self.api.name(),
+                        wait.as_secs(),
+                        retries,
+                        RATE_LIMIT_RETRIES
+                    );
+                    thread::sleep(wait);
+                    request = r;
+                }
+                _ => return Ok(response.error_for_status()?),
+            }
+        }
+    }
+
+    fn pacer(&self) -> std::sync::MutexGuard<'_, Pacer> {
+        self.pacer.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
     pub fn get_user_month_games(
         &self,
         username: &str,
@@ -48,28 +527,27 @@ impl ChessClient {
         let from = Utc.ymd(year, month, 1 as u32).and_hms(0, 0, 0);
         let to = first_day_next_month(from);
 
-        let request = self.api.user_games(username, from, to)?;
+        let request = self.games_request(self.api.user_games(
+            username,
+            from,
+            to,
+            self.perf_type.as_deref(),
+        )?);
 
-        let response = self.client.execute(request)?;
-        log::debug!("Response: {:?}", response);
-        log::debug!(
-            "Response length: {}",
-            response.content_length().unwrap_or(0 as u64)
-        );
+        let kind = RawRequest::MonthGames {
+            username,
+            year,
+            month,
+        };
 
         match self.api {
             Api::ChessDotCom => {
-                let games = response.json::<chessdotcom::Games>()?;
-                Ok(Games::ChessDotCom(games.games))
-            }
-            Api::LichessDotOrg => {
-                let games = response
-                    .text()?
-                    .split("\n")
-                    .map(|s| serde_json::from_str(s).unwrap())
-                    .collect::<Vec<lichessdotorg::Game>>();
-                Ok(Games::LichessDotOrg(games))
+                let text = self.fetch(request, kind)?;
+                let games = serde_json::from_str::<chessdotcom::Games<serde_json::Value>>(&text)?;
+                let games = games.games.into_iter().map(serde_json::from_value);
+                Ok(Games::ChessDotCom(self.tolerant(games)))
             }
+            Api::LichessDotOrg => self.stream_games(request, None, kind),
         }
     }
 
@@ -79,52 +557,237 @@ impl ChessClient {
     ) -> Result<chessdotcom::GameArchives, ClientError> {
         log::info!("Requesting archives for {}", username);
         let request = self.api.user_archives(username)?;
-        let response = self.client.execute(request)?;
-        log::debug!("Response: {:?}", response);
-        log::debug!(
-            "Response length: {}",
-            response.content_length().unwrap_or(0 as u64)
-        );
-        let archives: chessdotcom::GameArchives = response.json()?;
+        let text = self.fetch(request, RawRequest::Archives(username))?;
+        let archives: chessdotcom::GameArchives = serde_json::from_str(&text)?;
         log::debug!("Archives: {:?}", archives);
+        if archives.archives.is_empty() {
+            // Private profiles list no archives, tell them apart from players without games
+            log::info!("No archives for {}, checking their stats", username);
+            let request = self.api.player_stats(username)?;
+            let text = self.fetch(request, RawRequest::Stats(username))?;
+            let stats: chessdotcom::PlayerStats = serde_json::from_str(&text)?;
+            if archives.is_restricted(&stats) {
+                return Err(ClientError::ProfileRestricted {
+                    username: username.to_string(),
+                });
+            }
+        }
         Ok(archives)
     }
 
-    pub fn get_last_user_game(&self, username: &str) -> Result<Game, ClientError> {
-        log::info!("Requesting last game for {}", username);
-        let request = self.api.last_user_game(username)?;
+    pub fn get_last_user_games(
+        &self,
+        username: &str,
+        max: Option<usize>,
+    ) -> Result<Games, ClientError> {
+        log::info!("Requesting last {:?} games for {}", max, username);
+        let request = self.last_games_request(username, max)?;
+
+        self.stream_games(request, max, RawRequest::LastGames { username, max })
+    }
+
+    fn last_games_request(
+        &self,
+        username: &str,
+        max: Option<usize>,
+    ) -> Result<Request, ClientError> {
+        let mut request = self.games_request(self.api.last_user_games(
+            username,
+            max,
+            self.perf_type.as_deref(),
+        )?);
+        if let Some(since) = self.games_between.0 {
+            request = with_query_param(request, "since", since.timestamp_millis());
+        }
+        if let Some(until) = self.games_between.1 {
+            request = with_query_param(request, "until", until.timestamp_millis());
+        }
+        Ok(request)
+    }
+
+    pub fn get_user_ongoing_games(
+        &self,
+        username: &str,
+    ) -> Result<Vec<chessdotcom::OngoingGame>, ClientError> {
+        log::info!("Requesting ongoing games for {}", username);
+        let request = self.api.ongoing_games(username)?;
+        let text = self.fetch(request, RawRequest::OngoingGames(username))?;
+        let ongoing: chessdotcom::OngoingGames = serde_json::from_str(&text)?;
+        Ok(ongoing.games)
+    }
+
+    pub fn get_tournament(&self, id: &str) -> Result<chessdotcom::Tournament, ClientError> {
+        log::info!("Requesting tournament {}", id);
+        let request = self.api.tournament(id)?;
+        let text = self.fetch(request, RawRequest::Tournament(id))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn get_tournament_round(
+        &self,
+        id: &str,
+        round: u32,
+    ) -> Result<chessdotcom::TournamentRound, ClientError> {
+        log::info!("Requesting round {} of tournament {}", round, id);
+        let request = self.api.tournament_round(id, round)?;
+        let text = self.fetch(request, RawRequest::TournamentRound { id, round })?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn get_player_tournaments(
+        &self,
+        username: &str,
+    ) -> Result<chessdotcom::PlayerTournaments, ClientError> {
+        log::info!("Requesting tournaments of {}", username);
+        let request = self.api.player_tournaments(username)?;
+        let text = self.fetch(request, RawRequest::PlayerTournaments(username))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    pub fn get_player_status(
+        &self,
+        username: &str,
+    ) -> Result<chessdotcom::PlayerStatus, ClientError> {
+        log::info!("Requesting status of {}", username);
+        let request = self.api.player_status(username)?;
+        let text = self.fetch(request, RawRequest::Status(username))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// The username of a player with the casing they chose, which games may not use.
+    pub fn get_display_username(&self, username: &str) -> Result<String, ClientError> {
+        log::info!("Requesting profile of {}", username);
+        let request = self.api.user(username)?;
+        let text = self.fetch(request, RawRequest::User(username))?;
+        match self.api {
+            Api::ChessDotCom => {
+                let profile: chessdotcom::Profile = serde_json::from_str(&text)?;
+                Ok(profile.display_username())
+            }
+            Api::LichessDotOrg => {
+                let profile: lichessdotorg::UserProfile = serde_json::from_str(&text)?;
+                Ok(profile.username)
+            }
+        }
+    }
 
-        let response = self.client.execute(request)?;
+    /// Import a game into lichess.org from its PGN, returning the game created.
+    pub fn import_game(
+        &self,
+        pgn: &str,
+        token: Option<&str>,
+    ) -> Result<lichessdotorg::ImportedGame, ClientError> {
+        log::info!("Importing game");
+        let request = self.api.import_game(pgn, token)?;
+        let response = self.execute(request)?.error_for_status()?;
         log::debug!("Response: {:?}", response);
-        log::debug!(
-            "Response length: {}",
-            response.content_length().unwrap_or(0 as u64)
-        );
-        let text = response.text()?;
-        log::debug!("Response text: {}", text);
-        let game: lichessdotorg::Game = serde_json::from_str(&text)?;
-        Ok(Game::LichessDotOrg(game))
+        Ok(serde_json::from_str(&response.text()?)?)
+    }
+
+    pub fn get_rating_history(
+        &self,
+        username: &str,
+    ) -> Result<Vec<lichessdotorg::RatingHistory>, ClientError> {
+        log::info!("Requesting rating history for {}", username);
+        let request = self.api.rating_history(username)?;
+        let response = self.execute(request)?;
+        log::debug!("Response: {:?}", response);
+        let text = self.body(response, RawRequest::RatingHistory(username))?;
+        Ok(serde_json::from_str(&text)?)
     }
 
     pub fn get_game(&self, id: &str) -> Result<Game, ClientError> {
         log::info!("Requesting game id {}", id);
         let request = self.api.game(id)?;
-        let response = self.client.execute(request)?;
-        log::debug!("Response: {:?}", response);
-        log::debug!(
-            "Response length: {}",
-            response.content_length().unwrap_or(0 as u64)
-        );
+        let text = self.fetch(request, RawRequest::Game(id))?;
         let game = match self.api {
             Api::ChessDotCom => {
-                Game::ChessDotComLive(response.json::<chessdotcom::CallbackLiveGame>()?)
+                if let Some(reason) = chessdotcom::CallbackLiveGame::not_live_reason(&text) {
+                    return Err(ClientError::NotALiveGame {
+                        id: id.to_string(),
+                        reason,
+                    });
+                }
+                Game::ChessDotComLive(serde_json::from_str::<chessdotcom::CallbackLiveGame>(
+                    &text,
+                )?)
+            }
+            Api::LichessDotOrg => {
+                Game::LichessDotOrg(serde_json::from_str::<lichessdotorg::Game>(&text)?)
             }
-            Api::LichessDotOrg => Game::LichessDotOrg(response.json::<lichessdotorg::Game>()?),
         };
         Ok(game)
     }
 }
 
+impl ChessClient {
+    /// Request an endpoint and return the response body untouched, without attempting to
+    /// deserialize it.
+    pub fn get_raw(&self, kind: RawRequest) -> Result<String, ClientError> {
+        log::info!("Requesting raw response for {:?}", kind);
+        let request = match &kind {
+            RawRequest::Game(id) => self.api.game(id)?,
+            RawRequest::Archives(username) => self.api.user_archives(username)?,
+            RawRequest::MonthGames {
+                username,
+                year,
+                month,
+            } => {
+                let from = Utc.ymd(*year, *month, 1).and_hms(0, 0, 0);
+                let to = first_day_next_month(from);
+                self.games_request(self.api.user_games(
+                    username,
+                    from,
+                    to,
+                    self.perf_type.as_deref(),
+                )?)
+            }
+            RawRequest::LastGames { username, max } => self.games_request(
+                self.api
+                    .last_user_games(username, *max, self.perf_type.as_deref())?,
+            ),
+            RawRequest::OngoingGames(username) => self.api.ongoing_games(username)?,
+            RawRequest::RatingHistory(username) => self.api.rating_history(username)?,
+            RawRequest::Stats(username) => self.api.player_stats(username)?,
+            RawRequest::User(username) => self.api.user(username)?,
+            RawRequest::Tournament(id) => self.api.tournament(id)?,
+            RawRequest::TournamentRound { id, round } => self.api.tournament_round(id, *round)?,
+            RawRequest::PlayerTournaments(username) => self.api.player_tournaments(username)?,
+            RawRequest::Status(username) => self.api.player_status(username)?,
+        };
+
+        let response = self.execute(request)?;
+        log::debug!("Response: {:?}", response);
+        self.body(response, kind)
+    }
+}
+
+/// Parse each game of a newline-delimited JSON response, skipping blank lines.
+fn games_from_ndjson<'a, T: DeserializeOwned + 'a>(
+    text: &'a str,
+) -> impl Iterator<Item = Result<T, serde_json::Error>> + 'a {
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(serde_json::from_str)
+}
+
+/// Set a query parameter of a request, replacing any previous value.
+fn with_query_param<T: ToString>(mut request: Request, key: &str, value: T) -> Request {
+    let pairs: Vec<(String, String)> = request
+        .url()
+        .query_pairs()
+        .filter(|(k, _)| k != key)
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect();
+    request
+        .url_mut()
+        .query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(key, &value.to_string());
+    request
+}
+
 fn first_day_next_month<D: Datelike>(d: D) -> DateTime<Utc> {
     if d.month() == 12 {
         Utc.ymd(d.year() + 1, 1, 1).and_hms(0, 0, 0)
@@ -136,6 +799,281 @@ fn first_day_next_month<D: Datelike>(d: D) -> DateTime<Utc> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::lichess_line;
+    use std::io::Read;
+
+    #[test]
+    fn test_games_from_empty_ndjson() {
+        assert_eq!(games_from_ndjson::<lichessdotorg::Game>("\n\n").count(), 0);
+    }
+
+    /// A connection that drops after the bytes before it were read.
+    struct Dropped;
+
+    impl Read for Dropped {
+        fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(
+                io::ErrorKind::ConnectionReset,
+                "connection reset",
+            ))
+        }
+    }
+
+    #[test]
+    fn test_read_games_resumes_a_truncated_stream() {
+        let client = ChessClient::new(10, "lichess.org").unwrap();
+        let first = lichess_line("game3", 1604232000) + &lichess_line("game2", 1604231000);
+        // The stream drops in the middle of the third game
+        let truncated = first.clone() + &lichess_line("game1", 1604230000)[..40];
+        let mut opened = Vec::new();
+
+        let (games, _) = client
+            .read_games(|last, read| {
+                opened.push((last.map(|g| g.id.clone()), read));
+                let reader: Box<dyn Read> = match last {
+                    None => Box::new(io::Cursor::new(truncated.clone()).chain(Dropped)),
+                    Some(_) => Box::new(io::Cursor::new(lichess_line("game1", 1604230000))),
+                };
+                Ok(BufReader::new(reader))
+            })
+            .unwrap();
+
+        let ids: Vec<_> = games.iter().map(|g| g.id.as_str()).collect();
+        assert_eq!(ids, vec!["game3", "game2", "game1"]);
+        assert_eq!(opened, vec![(None, 0), (Some("game2".to_string()), 2)]);
+    }
+
+    #[test]
+    fn test_read_games_gives_up_after_resuming_too_many_times() {
+        let client = ChessClient::new(10, "lichess.org").unwrap();
+        let mut opened = 0;
+
+        let result = client.read_games(|_, _| {
+            opened += 1;
+            Ok(BufReader::new(
+                io::Cursor::new(lichess_line("game", 1604232000)).chain(Dropped),
+            ))
+        });
+
+        match result {
+            Err(ClientError::StreamInterrupted { read, .. }) => {
+                assert_eq!(read, STREAM_RESUMES + 1)
+            }
+            other => panic!(
+                "expected an interrupted stream, got {:?}",
+                other.map(|r| r.0.len())
+            ),
+        }
+        assert_eq!(opened, STREAM_RESUMES + 1);
+    }
+
+    #[test]
+    fn test_retries_stop_once_the_budget_is_spent() {
+        let retries = AtomicUsize::new(0);
+        assert!(take_retry(&retries, Some(2)));
+        assert!(take_retry(&retries, Some(2)));
+        assert!(!take_retry(&retries, Some(2)));
+        assert!(!take_retry(&retries, Some(2)));
+        assert_eq!(retries.load(Ordering::SeqCst), 2);
+
+        let retries = AtomicUsize::new(0);
+        assert!((0..100).all(|_| take_retry(&retries, None)));
+        assert!(!take_retry(&retries, Some(100)));
+    }
+
+    #[test]
+    fn test_read_games_gives_up_once_the_retry_budget_is_spent() {
+        // A budget of 0 surfaces the first failure
+        let client = ChessClient::new(10, "lichess.org")
+            .unwrap()
+            .max_total_retries(0);
+        let mut opened = 0;
+
+        let result = client.read_games(|_, _| {
+            opened += 1;
+            Ok(BufReader::new(
+                io::Cursor::new(lichess_line("game", 1604232000)).chain(Dropped),
+            ))
+        });
+
+        assert!(matches!(
+            result,
+            Err(ClientError::StreamInterrupted { read: 1, .. })
+        ));
+        assert_eq!(opened, 1);
+    }
+
+    #[test]
+    fn test_last_games_between_dates() {
+        let client = ChessClient::new(10, "lichess.org").unwrap().games_between(
+            Some(Utc.ymd(2021, 5, 31).and_hms(0, 0, 0)),
+            Some(Utc.ymd(2021, 6, 6).and_hms_milli(23, 59, 59, 999)),
+        );
+        let request = client.last_games_request("alice", Some(5)).unwrap();
+        let query: Vec<_> = request.url().query_pairs().into_owned().collect();
+        // lichess reads since and until as milliseconds
+        assert!(query.contains(&("since".to_string(), "1622419200000".to_string())));
+        assert!(query.contains(&("until".to_string(), "1623023999999".to_string())));
+        assert!(query.contains(&("max".to_string(), "5".to_string())));
+    }
+
+    #[test]
+    fn test_with_query_param_replaces_the_previous_value() {
+        let request = Request::new(
+            reqwest::Method::GET,
+            url::Url::parse("https://lichess.org/api/games/user/alice?max=10&until=5&clocks=true")
+                .unwrap(),
+        );
+        let request = with_query_param(with_query_param(request, "until", 3), "max", 8);
+        assert_eq!(request.url().query(), Some("clocks=true&until=3&max=8"));
+    }
+
+    #[test]
+    fn test_tolerant_skips_games_that_cannot_be_parsed() {
+        let client = ChessClient::new(10, "lichess.org").unwrap().quiet_errors();
+        let text = "{\"id\": 1}\nnot json\n\n{\"id\": 2}\n";
+        let (skipped_before, total_before) = skipped_games();
+
+        let games: Vec<serde_json::Value> = client.tolerant(games_from_ndjson(text));
+        assert_eq!(games.len(), 2);
+
+        let (skipped, total) = skipped_games();
+        assert!(skipped > skipped_before);
+        assert!(total >= total_before + 3);
+    }
+
+    #[test]
+    fn test_not_modified_serves_the_cached_body() {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buffer = [0; 4096];
+            let read = stream.read(&mut buffer).unwrap();
+            let request = String::from_utf8_lossy(&buffer[..read]).to_lowercase();
+            assert!(request.contains("if-none-match: \"abc\""));
+            stream
+                .write_all(b"HTTP/1.1 304 Not Modified\r\nContent-Length: 0\r\n\r\n")
+                .unwrap();
+        });
+
+        let dir = std::env::temp_dir().join(format!("cgf_not_modified_{}", std::process::id()));
+        let client = ChessClient::new(10, "chess.com")
+            .unwrap()
+            .cache_responses(dir.clone());
+        let kind = RawRequest::Archives("user");
+        let cached = CachedResponse {
+            etag: "\"abc\"".to_string(),
+            body: "{\"archives\": []}".to_string(),
+        };
+        ResponseCache::new(dir.clone())
+            .put(&kind.fixture_name(&Api::ChessDotCom), &cached)
+            .unwrap();
+
+        let request = client
+            .client
+            .get(&format!(
+                "http://{}/pub/player/user/games/archives",
+                address
+            ))
+            .build()
+            .unwrap();
+        let body = client.fetch(request, kind).unwrap();
+        server.join().unwrap();
+        assert_eq!(body, cached.body);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_rate_limited_chess_com_requests_are_retried() {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let responses: [&[u8]; 2] = [
+                b"HTTP/1.1 429 Too Many Requests\r\nRetry-After: 0\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            ];
+            for response in responses.iter() {
+                let (mut stream, _) = listener.accept().unwrap();
+                let read = stream.read(&mut [0; 4096]).unwrap();
+                assert!(read > 0, "the request was empty");
+                stream.write_all(response).unwrap();
+            }
+        });
+
+        let paced = ChessClient::new(10, "chess.com").unwrap();
+        let client = ChessClient::new(10, "chess.com")
+            .unwrap()
+            .share_pacer(&paced);
+        let request = client
+            .client
+            .get(&format!("http://{}/pub/player/user/games/2021/01", address))
+            .build()
+            .unwrap();
+        let response = client.execute(request).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.text().unwrap(), "ok");
+        assert_eq!(paced.pacer().throttled(), 1);
+    }
+
+    #[test]
+    fn test_fixture_name() {
+        let kind = RawRequest::MonthGames {
+            username: "user",
+            year: 2020,
+            month: 9,
+        };
+        assert_eq!(
+            kind.fixture_name(&Api::ChessDotCom),
+            "chesscom_month_user_2020_09.json"
+        );
+        assert_eq!(
+            kind.fixture_name(&Api::LichessDotOrg),
+            "lichess_month_user_2020_09.ndjson"
+        );
+
+        let kind = RawRequest::LastGames {
+            username: "user",
+            max: None,
+        };
+        assert_eq!(
+            kind.fixture_name(&Api::LichessDotOrg),
+            "lichess_last_user_all.ndjson"
+        );
+        assert_eq!(
+            RawRequest::Game("abcd1234").fixture_name(&Api::LichessDotOrg),
+            "lichess_game_abcd1234.json"
+        );
+    }
+
+    #[test]
+    fn test_pacer_backs_off_and_recovers() {
+        let mut pacer = Pacer::default();
+        assert_eq!(pacer.delay(), Duration::from_secs(0));
+
+        pacer.on_rate_limited();
+        pacer.on_rate_limited();
+        assert_eq!(pacer.delay(), Duration::from_secs(2));
+        assert_eq!(pacer.throttled(), 2);
+
+        for _ in 0..10 {
+            pacer.on_rate_limited();
+        }
+        assert_eq!(pacer.delay(), MAX_PACING_DELAY);
+
+        for _ in 0..8 {
+            pacer.on_success();
+        }
+        assert_eq!(pacer.delay(), Duration::from_secs(0));
+        assert_eq!(pacer.throttled(), 12);
+    }
 
     #[test]
     fn test_first_day_next_month() {
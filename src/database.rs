@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use rusqlite::{params, Connection};
+
+use crate::api::{normalized::NormalizedGame, ChessGame};
+use crate::error::ChessError;
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS games (
+    url TEXT PRIMARY KEY,
+    white TEXT NOT NULL,
+    white_title TEXT,
+    white_rating INTEGER,
+    white_result TEXT,
+    black TEXT NOT NULL,
+    black_title TEXT,
+    black_rating INTEGER,
+    black_result TEXT,
+    end_time TEXT NOT NULL,
+    speed TEXT NOT NULL,
+    eco TEXT,
+    opening TEXT,
+    pgn TEXT NOT NULL
+)";
+
+const UPSERT: &str = "INSERT INTO games (
+    url, white, white_title, white_rating, white_result,
+    black, black_title, black_rating, black_result,
+    end_time, speed, eco, opening, pgn
+) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)
+ON CONFLICT(url) DO UPDATE SET
+    white = excluded.white,
+    white_title = excluded.white_title,
+    white_rating = excluded.white_rating,
+    white_result = excluded.white_result,
+    black = excluded.black,
+    black_title = excluded.black_title,
+    black_rating = excluded.black_rating,
+    black_result = excluded.black_result,
+    end_time = excluded.end_time,
+    speed = excluded.speed,
+    eco = excluded.eco,
+    opening = excluded.opening,
+    pgn = excluded.pgn";
+
+/// A SQLite database of games, with a row per game keyed by its URL. Each row has the fields of
+/// the normalized game, and the full PGN of the game.
+pub struct GameDatabase {
+    connection: Connection,
+}
+
+impl GameDatabase {
+    /// Open the database at path, creating it and its games table if they don't exist.
+    pub fn open(path: &Path) -> Result<Self, ChessError> {
+        GameDatabase::with_connection(Connection::open(path)?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Self, ChessError> {
+        connection.execute(SCHEMA, [])?;
+        Ok(GameDatabase { connection })
+    }
+
+    /// Write a game, replacing the row of a game with the same URL written before.
+    pub fn upsert(&self, game: &mut impl ChessGame) -> Result<(), ChessError> {
+        let normalized = NormalizedGame::from_game(game);
+        self.connection.execute(
+            UPSERT,
+            params![
+                normalized.url,
+                normalized.white.name,
+                normalized.white.title,
+                normalized.white.rating,
+                normalized.white.result,
+                normalized.black.name,
+                normalized.black.title,
+                normalized.black.rating,
+                normalized.black.result,
+                normalized.end_time.to_rfc3339(),
+                normalized.speed.to_string(),
+                normalized.eco,
+                normalized.opening,
+                game.pgn(),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::lichessdotorg;
+    use crate::fixtures;
+
+    fn lichess_game(rating: u32) -> lichessdotorg::Game {
+        let mut game = fixtures::lichess_game();
+        game.players.white.rating = Some(rating);
+        game
+    }
+
+    #[test]
+    fn test_upsert_by_url() {
+        let database =
+            GameDatabase::with_connection(Connection::open_in_memory().unwrap()).unwrap();
+        database.upsert(&mut lichess_game(2389)).unwrap();
+        database.upsert(&mut lichess_game(2400)).unwrap();
+
+        let (count, rating, speed, pgn): (i64, u32, String, String) = database
+            .connection
+            .query_row(
+                "SELECT COUNT(*), white_rating, speed, pgn FROM games",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(rating, 2400);
+        assert_eq!(speed, "blitz");
+        assert_eq!(
+            pgn,
+            "[Event \"Rated Blitz game\"]\n[Site \"https://lichess.org/q7ZvsdUF\"]\n\
+             [White \"Lance5500\"]\n[Black \"TryingHard87\"]\n[Result \"1-0\"]\n\
+             [WhiteElo \"2389\"]\n[ECO \"D00\"]\n\n1. d4 d5 1-0"
+        );
+    }
+}
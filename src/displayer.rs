@@ -1,9 +1,97 @@
+use std::collections::BTreeMap;
 use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
 
 use prettytable::Table;
 
-use crate::api::{ChessPlayer, DisplayableChessGame};
+use crate::api::{
+    chessdotcom::{CallbackLiveGame, OngoingGame, PlayerTournament},
+    normalized::NormalizedGame,
+    ChessPlayer, DisplayableChessGame, Outcome, Termination,
+};
 use crate::error::ChessError;
+use crate::utils::{chess_com_analysis_url, encode_latin1, format_duration, lichess_analysis_url};
+
+#[derive(PartialEq, Debug)]
+pub enum OutputEncoding {
+    Utf8,
+    Latin1,
+}
+
+impl FromStr for OutputEncoding {
+    type Err = ChessError;
+
+    fn from_str(encoding: &str) -> Result<Self, Self::Err> {
+        match encoding {
+            "utf-8" => Ok(OutputEncoding::Utf8),
+            "latin1" => Ok(OutputEncoding::Latin1),
+            enc => Err(ChessError::UnsupportedEncodingError(enc.to_string())),
+        }
+    }
+}
+
+/// A row of the summary table of a game.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TableColumn {
+    Players,
+    Result,
+    Termination,
+    Opening,
+    Speed,
+    Moves,
+    Duration,
+    Url,
+    Profiles,
+}
+
+/// Names of the rows that can be chosen with --columns.
+pub const TABLE_COLUMNS: &[&str] = &[
+    "players",
+    "result",
+    "termination",
+    "opening",
+    "speed",
+    "moves",
+    "duration",
+    "url",
+    "profiles",
+];
+
+/// Rows of the summary table when none are chosen.
+pub const DEFAULT_COLUMNS: &[TableColumn] = &[
+    TableColumn::Players,
+    TableColumn::Result,
+    TableColumn::Termination,
+    TableColumn::Opening,
+    TableColumn::Speed,
+    TableColumn::Moves,
+    TableColumn::Duration,
+    TableColumn::Url,
+];
+
+impl FromStr for TableColumn {
+    type Err = ChessError;
+
+    fn from_str(column: &str) -> Result<Self, Self::Err> {
+        match column.trim().to_lowercase().as_str() {
+            "players" => Ok(TableColumn::Players),
+            "result" => Ok(TableColumn::Result),
+            "termination" => Ok(TableColumn::Termination),
+            "opening" => Ok(TableColumn::Opening),
+            "speed" => Ok(TableColumn::Speed),
+            "moves" => Ok(TableColumn::Moves),
+            "duration" => Ok(TableColumn::Duration),
+            "url" => Ok(TableColumn::Url),
+            "profiles" => Ok(TableColumn::Profiles),
+            _ => Err(ChessError::ValidationError(format!(
+                "unknown column {}, valid columns are: {}",
+                column,
+                TABLE_COLUMNS.join(", ")
+            ))),
+        }
+    }
+}
 
 pub enum GameDisplayer {
     Default(String),
@@ -14,6 +102,15 @@ impl GameDisplayer {
     pub fn from_str(
         game: &mut impl DisplayableChessGame,
         output: &str,
+    ) -> Result<Self, ChessError> {
+        Self::with_columns(game, output, DEFAULT_COLUMNS)
+    }
+
+    /// Like from_str, with the rows of tables, including those of reports, chosen by columns.
+    pub fn with_columns(
+        game: &mut impl DisplayableChessGame,
+        output: &str,
+        columns: &[TableColumn],
     ) -> Result<Self, ChessError> {
         match output {
             "json" => match game.to_json() {
@@ -24,11 +121,258 @@ impl GameDisplayer {
                 Ok(json) => Ok(GameDisplayer::Default(json)),
                 Err(e) => Err(ChessError::JSONError(e)),
             },
+            "json-compact" => match NormalizedGame::from_game(game).to_json() {
+                Ok(json) => Ok(GameDisplayer::Default(json)),
+                Err(e) => Err(ChessError::JSONError(e)),
+            },
+            "clocks-json" => match serde_json::to_string(&game.clocks()) {
+                Ok(json) => Ok(GameDisplayer::Default(json)),
+                Err(e) => Err(ChessError::JSONError(e)),
+            },
+            "headers-json" => match serde_json::to_string(&game.tags()) {
+                Ok(json) => Ok(GameDisplayer::Default(json)),
+                Err(e) => Err(ChessError::JSONError(e)),
+            },
+            "headers" => Ok(GameDisplayer::Default(header_tags(&game.tags()))),
             "pgn" => Ok(GameDisplayer::Default(game.pgn().to_string())),
-            "table" => {
-                let mut game_table = Table::new();
+            "minimal-pgn" => Ok(GameDisplayer::Default(game.minimal_pgn())),
+            "uci" => Ok(GameDisplayer::Default(game.uci_moves()?.join(" "))),
+            "evals" => match game.moves_with_evals() {
+                Some(moves) => Ok(GameDisplayer::Default(moves)),
+                None => Err(ChessError::AnalysisNotAvailableError),
+            },
+            "table" => Ok(GameDisplayer::Table(game_table(game, columns))),
+            "report" => {
                 let white = game.white();
                 let black = game.black();
+                let mut report = format!(
+                    "## {} vs {} ({})\n\n",
+                    white.name(),
+                    black.name(),
+                    game.end_time().format("%Y-%m-%d")
+                );
+                report.push_str(&format!("```\n{}```\n\n", game_table(game, columns)));
+                report.push_str(&format!("```pgn\n{}\n```\n\n---", game.pgn()));
+                Ok(GameDisplayer::Default(report))
+            }
+            out => {
+                return Err(ChessError::UnsupportedOutputError(out.to_string()));
+            }
+        }
+    }
+
+    /// Render the displayer as bytes in the requested encoding.
+    pub fn encode(&self, encoding: &OutputEncoding) -> Vec<u8> {
+        match encoding {
+            OutputEncoding::Utf8 => self.to_string().into_bytes(),
+            OutputEncoding::Latin1 => encode_latin1(&self.to_string()),
+        }
+    }
+}
+
+/// Summary table of a game with the given rows, in order. Rows without data for the game, like
+/// the result of a game without one, are left out.
+/// Glyph of the outcome of a game for a player, in green, red, or yellow when color is set.
+/// Games without an outcome for the player get a plain dot.
+pub fn result_glyph(outcome: Option<Outcome>, color: bool) -> String {
+    let (glyph, ansi) = match outcome {
+        Some(Outcome::Win) => ("✓", "32"),
+        Some(Outcome::Loss) => ("✗", "31"),
+        Some(Outcome::Draw) => ("=", "33"),
+        None => return "·".to_string(),
+    };
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", ansi, glyph)
+    } else {
+        glyph.to_string()
+    }
+}
+
+/// A game in a single line: the result for the searched player, the date, the players and their
+/// ratings, the speed, and the URL of the game.
+pub fn game_line(
+    game: &mut impl DisplayableChessGame,
+    player: Option<&str>,
+    color: bool,
+) -> String {
+    let outcome = player.and_then(|p| game.result_for(p));
+    let white = game.white();
+    let black = game.black();
+    let rating = |r: Option<u32>| r.map_or("N/A".to_string(), |r| r.to_string());
+    format!(
+        "{} {}  {} ({}) vs {} ({})  {}  {}",
+        result_glyph(outcome, color),
+        game.end_time().format("%Y-%m-%d"),
+        white.name(),
+        rating(white.rating()),
+        black.name(),
+        rating(black.rating()),
+        game.speed(),
+        game.url()
+    )
+}
+
+/// Rating of the player minus the rating of their opponent, if the player played the game and
+/// both ratings are known.
+pub fn rating_gap(game: &mut impl DisplayableChessGame, player: &str) -> Option<i64> {
+    let white = game.white();
+    let black = game.black();
+    let (own, opponent) = if white.name().eq_ignore_ascii_case(player) {
+        (white, black)
+    } else if black.name().eq_ignore_ascii_case(player) {
+        (black, white)
+    } else {
+        return None;
+    };
+    Some(own.rating()? as i64 - opponent.rating()? as i64)
+}
+
+/// A rating gap signed, e.g. +120 or -35, or ? when unknown.
+pub fn format_rating_gap(gap: Option<i64>) -> String {
+    gap.map_or("?".to_string(), |gap| format!("{:+}", gap))
+}
+
+/// FEN of the position after the first ply moves of the game, or of its final position, followed
+/// by the URLs of the lichess and chess.com analysis boards at it.
+pub fn analysis_urls(
+    game: &mut impl DisplayableChessGame,
+    ply: Option<usize>,
+) -> Result<String, ChessError> {
+    let fen = game.fen_at(ply)?;
+    Ok(format!(
+        "FEN: {}\nlichess.org: {}\nchess.com: {}",
+        fen,
+        lichess_analysis_url(&fen),
+        chess_com_analysis_url(&fen)
+    ))
+}
+
+/// The moves of a chess.com live game as they are decoded, one per line: the ply, the pair of
+/// characters it was encoded as, its squares, its SAN, and whether it captures, castles, or
+/// promotes. A move that fails to decode ends the list with its error.
+pub fn explain_pgn(game: &CallbackLiveGame) -> String {
+    let (moves, error) = game.explain_moves();
+    let mut lines: Vec<String> = moves
+        .iter()
+        .enumerate()
+        .map(|(i, m)| format!("{:>4}  {}", i + 1, m))
+        .collect();
+    if let Some(e) = error {
+        lines.push(format!("{:>4}  error: {}", moves.len() + 1, e));
+    }
+    lines.join("\n")
+}
+
+/// Tags of the --headers output, in order: the Seven Tag Roster, followed by the opening, the
+/// time control, and the link to the game.
+pub const HEADER_TAGS: &[&str] = &[
+    "Event",
+    "Site",
+    "Date",
+    "Round",
+    "White",
+    "Black",
+    "Result",
+    "ECO",
+    "TimeControl",
+    "Link",
+];
+
+/// The PGN tag pairs of HEADER_TAGS among tags, one per line, leaving out those the game lacks.
+pub fn header_tags(tags: &BTreeMap<String, String>) -> String {
+    HEADER_TAGS
+        .iter()
+        .filter_map(|&tag| {
+            let value = tags.get(tag)?;
+            Some(format!(
+                "[{} \"{}\"]",
+                tag,
+                value.replace('\\', "\\\\").replace('"', "\\\"")
+            ))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Which boards --board-every-move prints: those after the plies from from_ply to to_ply,
+/// counted from 1, drawn with ASCII letters or, when fancy, chess symbols.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BoardSteps {
+    pub from_ply: Option<usize>,
+    pub to_ply: Option<usize>,
+    pub fancy: bool,
+}
+
+/// Each move of the game followed by the board after it, within the plies of steps.
+pub fn boards_every_move(
+    game: &mut impl DisplayableChessGame,
+    steps: BoardSteps,
+) -> Result<String, ChessError> {
+    let moves = game.moves();
+    let first = steps.from_ply.unwrap_or(1).max(1);
+    let last = steps.to_ply.unwrap_or(moves.len()).min(moves.len());
+    let mut boards = Vec::new();
+    for ply in first..=last {
+        let number = ply.div_ceil(2);
+        let dots = if ply % 2 == 1 { "." } else { "..." };
+        let fen = game.fen_at(Some(ply))?;
+        boards.push(format!(
+            "{}{} {}\n{}",
+            number,
+            dots,
+            moves[ply - 1],
+            board_diagram(&fen, steps.fancy)
+        ));
+    }
+    Ok(boards.join("\n\n"))
+}
+
+/// The board of a position given as FEN, with rank 8 at the top and the files below.
+pub fn board_diagram(fen: &str, fancy: bool) -> String {
+    let placement = fen.split(' ').next().unwrap_or_default();
+    let mut diagram = String::new();
+    for (i, rank) in placement.split('/').enumerate() {
+        let mut squares = Vec::new();
+        for c in rank.chars() {
+            match c.to_digit(10) {
+                Some(empty) => squares.extend((0..empty).map(|_| if fancy { '·' } else { '.' })),
+                None if fancy => squares.push(piece_symbol(c)),
+                None => squares.push(c),
+            }
+        }
+        let squares: Vec<String> = squares.iter().map(char::to_string).collect();
+        diagram.push_str(&format!("{} {}\n", 8 - i, squares.join(" ")));
+    }
+    diagram.push_str("  a b c d e f g h");
+    diagram
+}
+
+fn piece_symbol(piece: char) -> char {
+    match piece {
+        'K' => '♔',
+        'Q' => '♕',
+        'R' => '♖',
+        'B' => '♗',
+        'N' => '♘',
+        'P' => '♙',
+        'k' => '♚',
+        'q' => '♛',
+        'r' => '♜',
+        'b' => '♝',
+        'n' => '♞',
+        'p' => '♟',
+        c => c,
+    }
+}
+
+fn game_table(game: &mut impl DisplayableChessGame, columns: &[TableColumn]) -> Table {
+    let mut game_table = Table::new();
+    let white = game.white();
+    let black = game.black();
+
+    for column in columns {
+        match column {
+            TableColumn::Players => {
                 let white_rating = white.rating().map_or("N/A".to_string(), |i| i.to_string());
                 let black_rating = black.rating().map_or("N/A".to_string(), |i| i.to_string());
                 game_table.add_row(row![
@@ -36,27 +380,217 @@ impl GameDisplayer {
                     format!("{} ({}) ♔", white.name(), white_rating),
                     format!("{} ({}) ♚", black.name(), black_rating),
                 ]);
-
-                if white.result().is_some() && black.result().is_some() {
+            }
+            TableColumn::Result => {
+                if let (Some(white_result), Some(black_result)) = (white.result(), black.result()) {
+                    game_table.add_row(row!["Result", white_result, black_result]);
+                }
+            }
+            TableColumn::Termination => {
+                let termination = game.termination();
+                if termination != Termination::Unknown {
                     game_table.add_row(row![
-                        "Result",
-                        // Safe to unwrap as we have checked for is_some
-                        format!("{}", white.result().unwrap()),
-                        format!("{}", black.result().unwrap()),
+                        "Termination",
+                        H2 -> termination,
                     ]);
                 }
-
+            }
+            TableColumn::Opening => {
+                if let Some(opening) = game.opening() {
+                    let opening = match game.eco() {
+                        Some(eco) => format!("{} ({})", opening, eco),
+                        None => opening,
+                    };
+                    game_table.add_row(row![
+                        "Opening",
+                        H2 -> opening,
+                    ]);
+                }
+            }
+            TableColumn::Speed => {
+                game_table.add_row(row![
+                    "Speed",
+                    H2 -> game.speed(),
+                ]);
+            }
+            TableColumn::Moves => {
+                // Moves are counted in full moves, a ply of white and a ply of black
+                let plies = game.moves().len();
+                if plies > 0 {
+                    game_table.add_row(row![
+                        "Moves",
+                        H2 -> plies.div_ceil(2),
+                    ]);
+                }
+            }
+            TableColumn::Duration => {
+                if let Some(start) = game.start_time() {
+                    game_table.add_row(row![
+                        "Duration",
+                        H2 -> format_duration(game.end_time() - start),
+                    ]);
+                }
+            }
+            TableColumn::Url => {
                 game_table.add_row(row![
                     "URL",
                     H2 -> game.url(),
                 ]);
-                Ok(GameDisplayer::Table(game_table))
             }
-            out => {
-                return Err(ChessError::UnsupportedOutputError(out.to_string()));
+            TableColumn::Profiles => {
+                let profile = |url: Option<String>| url.unwrap_or_else(|| "N/A".to_string());
+                game_table.add_row(row![
+                    "Profiles",
+                    profile(white.profile_url()),
+                    profile(black.profile_url()),
+                ]);
             }
         }
     }
+    game_table
+}
+
+/// Table of games in progress, with whose turn it is and when they have to move by.
+pub fn ongoing_table(games: &[OngoingGame]) -> Table {
+    let mut table = Table::new();
+    table.set_titles(row![
+        "Status",
+        "White ♔",
+        "Black ♚",
+        "Turn",
+        "Move by",
+        "URL"
+    ]);
+
+    for game in games.iter() {
+        table.add_row(row![
+            "In progress",
+            game.white_name(),
+            game.black_name(),
+            game.turn,
+            game.move_by.format("%Y-%m-%d %H:%M UTC"),
+            game.url,
+        ]);
+    }
+    table
+}
+
+/// Table of a player's tournaments, with their results in the finished ones.
+pub fn tournaments_table(tournaments: &[PlayerTournament]) -> Table {
+    let mut table = Table::new();
+    table.set_titles(row!["Status", "Tournament", "Placement", "W/L/D", "ID"]);
+
+    for tournament in tournaments.iter() {
+        let placement = match (tournament.placement, tournament.total_players) {
+            (Some(placement), Some(total)) => format!("{} of {}", placement, total),
+            (Some(placement), None) => placement.to_string(),
+            _ => String::new(),
+        };
+        let record = match (tournament.wins, tournament.losses, tournament.draws) {
+            (Some(w), Some(l), Some(d)) => format!("{}/{}/{}", w, l, d),
+            _ => String::new(),
+        };
+        table.add_row(row![
+            tournament.status,
+            tournament.name(),
+            placement,
+            record,
+            tournament.id(),
+        ]);
+    }
+    table
+}
+
+/// Set in the environment of the process that keeps serving the clipboard after cgf exits.
+pub const CLIPBOARD_HOLDER_ENV: &str = "CGF_CLIPBOARD_HOLDER";
+
+/// Output target that collects everything written to it and copies it to the system clipboard
+/// when flushed. If no clipboard is available, e.g. in a headless session, the output is written
+/// to stdout instead.
+#[derive(Default)]
+pub struct ClipboardWriter {
+    buffer: Vec<u8>,
+}
+
+impl ClipboardWriter {
+    pub fn new() -> Self {
+        ClipboardWriter { buffer: Vec::new() }
+    }
+}
+
+impl Write for ClipboardWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        let text = String::from_utf8_lossy(&self.buffer).into_owned();
+        let copied =
+            arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.clone()));
+        match copied {
+            Ok(()) => hold_clipboard(&text),
+            Err(e) => {
+                log::warn!("could not copy to clipboard ({}), printing instead", e);
+                let mut stdout = io::stdout();
+                stdout.write_all(&self.buffer)?;
+                stdout.flush()?;
+            }
+        }
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+/// On X11 and Wayland the clipboard is served by the process that set it, so its contents are
+/// gone once cgf exits. Start cgf again in the background to keep serving them, until something
+/// else is copied.
+#[cfg(target_os = "linux")]
+fn hold_clipboard(text: &str) {
+    use std::process::{Command, Stdio};
+
+    let held = std::env::current_exe().and_then(|exe| {
+        let mut holder = Command::new(exe)
+            .env(CLIPBOARD_HOLDER_ENV, "1")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let mut stdin = holder.stdin.take().expect("stdin is piped");
+        stdin.write_all(text.as_bytes())
+    });
+    if let Err(e) = held {
+        log::warn!(
+            "could not keep serving the clipboard ({}), it will be emptied when cgf exits",
+            e
+        );
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn hold_clipboard(_text: &str) {}
+
+/// Copy what is read from stdin to the clipboard and serve it until something else is copied.
+/// Run by the process ClipboardWriter starts when CLIPBOARD_HOLDER_ENV is set.
+#[cfg(target_os = "linux")]
+pub fn serve_clipboard() -> Result<(), ChessError> {
+    use arboard::SetExtLinux;
+    use std::io::Read;
+
+    let mut text = String::new();
+    io::stdin().read_to_string(&mut text)?;
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set().wait().text(text))
+        .map_err(|e| io::Error::other(e.to_string()))?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn serve_clipboard() -> Result<(), ChessError> {
+    Ok(())
 }
 
 impl fmt::Display for GameDisplayer {
@@ -67,3 +601,195 @@ impl fmt::Display for GameDisplayer {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::chessdotcom;
+    use crate::fixtures::{lichess_game, lichess_game_with};
+    use serde_json::json;
+
+    #[test]
+    fn test_headers() {
+        let mut game = lichess_game();
+        match GameDisplayer::from_str(&mut game, "headers").unwrap() {
+            GameDisplayer::Default(headers) => assert_eq!(
+                headers,
+                "[Event \"Rated Blitz game\"]\n\
+                 [Site \"https://lichess.org/q7ZvsdUF\"]\n\
+                 [White \"Lance5500\"]\n\
+                 [Black \"TryingHard87\"]\n\
+                 [Result \"1-0\"]\n\
+                 [ECO \"D00\"]"
+            ),
+            _ => panic!("expected headers"),
+        }
+
+        let json = r#"{
+            "white": {"username": "Alice", "rating": 1500, "result": "win", "@id": ""},
+            "black": {"username": "Bob", "rating": 1500, "result": "resigned", "@id": ""},
+            "url": "https://www.chess.com/game/live/1234567890",
+            "fen": "",
+            "pgn": "[Event \"Live Chess\"]\n[Site \"Chess.com\"]\n[Date \"2021.02.05\"]\n[Round \"-\"]\n[White \"Alice\"]\n[Black \"Bob \\\"B\\\"\"]\n[Result \"1-0\"]\n[CurrentPosition \"8/8/8/8/8/8/8/8 w - -\"]\n[ECO \"C20\"]\n[TimeControl \"180\"]\n[Termination \"Alice won by resignation\"]\n[Link \"https://www.chess.com/game/live/1234567890\"]\n\n1. e4 1-0",
+            "end_time": 1612540800,
+            "time_control": "180",
+            "rules": "chess",
+            "eco": null,
+            "tournament": null,
+            "match": null
+        }"#;
+        let mut game: chessdotcom::Game = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            GameDisplayer::from_str(&mut game, "headers")
+                .unwrap()
+                .to_string(),
+            "[Event \"Live Chess\"]\n\
+             [Site \"Chess.com\"]\n\
+             [Date \"2021.02.05\"]\n\
+             [Round \"-\"]\n\
+             [White \"Alice\"]\n\
+             [Black \"Bob \\\"B\\\"\"]\n\
+             [Result \"1-0\"]\n\
+             [ECO \"C20\"]\n\
+             [TimeControl \"180\"]\n\
+             [Link \"https://www.chess.com/game/live/1234567890\"]"
+        );
+    }
+
+    #[test]
+    fn test_boards_every_move() {
+        let mut game = lichess_game_with(json!({
+            "status": "mate",
+            "winner": "black",
+            "pgn": "1. f3 e5 2. g4 Qh4# 0-1",
+            "moves": "f3 e5 g4 Qh4#"
+        }));
+
+        let boards = boards_every_move(&mut game, BoardSteps::default()).unwrap();
+        assert_eq!(boards.matches("  a b c d e f g h").count(), 4);
+        assert!(boards.starts_with("1. f3\n8 r n b q k b n r\n"));
+        assert!(boards.contains("2... Qh4#\n"));
+        assert!(boards.ends_with(
+            "4 . . . . . . P q\n3 . . . . . P . .\n2 P P P P P . . P\n1 R N B Q K B N R\n  a b c d e f g h"
+        ));
+
+        let steps = BoardSteps {
+            from_ply: Some(2),
+            to_ply: Some(3),
+            fancy: true,
+        };
+        let boards = boards_every_move(&mut game, steps).unwrap();
+        assert_eq!(boards.matches("  a b c d e f g h").count(), 2);
+        assert!(boards.starts_with("1... e5\n8 ♜ ♞ ♝ ♛ ♚ ♝ ♞ ♜\n"));
+    }
+
+    #[test]
+    fn test_game_table_columns() {
+        let mut game = lichess_game_with(json!({"winner": null, "pgn": "1. d4 d5 1-0"}));
+
+        let table = game_table(&mut game, &[TableColumn::Url, TableColumn::Players]);
+        let rows: Vec<String> = table
+            .row_iter()
+            .map(|r| r.get_cell(0).unwrap().get_content())
+            .collect();
+        assert_eq!(rows, vec!["URL", "Players"]);
+
+        // Games without a result or opening have no row for them
+        let table = game_table(&mut game, DEFAULT_COLUMNS);
+        assert_eq!(table.len(), 6);
+
+        let table = game_table(&mut game, &[TableColumn::Termination]);
+        assert_eq!(
+            table.get_row(0).unwrap().get_cell(1).unwrap().get_content(),
+            "Resignation"
+        );
+        game.status = "unknownFinish".to_string();
+        assert!(game_table(&mut game, &[TableColumn::Termination]).is_empty());
+    }
+
+    #[test]
+    fn test_game_table_moves_and_duration() {
+        let mut game = lichess_game_with(json!({
+            "lastMoveAt": 1514506022384i64,
+            "pgn": "1. d4 d5 2. c4 1-0",
+            "moves": "d4 d5 c4"
+        }));
+
+        let table = game_table(&mut game, &[TableColumn::Moves, TableColumn::Duration]);
+        let rows: Vec<(String, String)> = table
+            .row_iter()
+            .map(|r| {
+                (
+                    r.get_cell(0).unwrap().get_content(),
+                    r.get_cell(1).unwrap().get_content(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                ("Moves".to_string(), "2".to_string()),
+                ("Duration".to_string(), "14m 32s".to_string())
+            ]
+        );
+
+        // Games without moves have no row for them
+        game.moves = String::new();
+        let table = game_table(&mut game, &[TableColumn::Moves]);
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn test_game_line() {
+        let mut game = lichess_game();
+
+        assert_eq!(
+            game_line(&mut game, Some("lance5500"), false),
+            "✓ 2017-12-28  Lance5500 (2389) vs TryingHard87 (2498)  blitz  https://lichess.org/q7ZvsdUF"
+        );
+        assert!(game_line(&mut game, Some("TryingHard87"), false).starts_with("✗ "));
+        assert!(game_line(&mut game, None, false).starts_with("· "));
+        assert!(game_line(&mut game, Some("lance5500"), true).starts_with("\x1b[32m✓\x1b[0m "));
+    }
+
+    #[test]
+    fn test_analysis_urls() {
+        let mut game = lichess_game();
+
+        assert_eq!(
+            analysis_urls(&mut game, Some(1)).unwrap(),
+            "FEN: rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR b KQkq - 0 1\n\
+             lichess.org: https://lichess.org/analysis/rnbqkbnr/pppppppp/8/8/3P4/8/PPP1PPPP/RNBQKBNR_b_KQkq_-_0_1\n\
+             chess.com: https://www.chess.com/analysis?fen=rnbqkbnr%2Fpppppppp%2F8%2F8%2F3P4%2F8%2FPPP1PPPP%2FRNBQKBNR+b+KQkq+-+0+1"
+        );
+    }
+
+    #[test]
+    fn test_rating_gap() {
+        let mut game = lichess_game();
+
+        assert_eq!(rating_gap(&mut game, "lance5500"), Some(-109));
+        assert_eq!(rating_gap(&mut game, "TryingHard87"), Some(109));
+        assert_eq!(rating_gap(&mut game, "someone_else"), None);
+        assert_eq!(format_rating_gap(Some(109)), "+109");
+        assert_eq!(format_rating_gap(Some(-109)), "-109");
+
+        game.players.black.rating = None;
+        assert_eq!(format_rating_gap(rating_gap(&mut game, "lance5500")), "?");
+    }
+
+    #[test]
+    fn test_headers_json() {
+        let mut game = lichess_game_with(json!({
+            "pgn": "[Event \"Rated Blitz game\"]\n[White \"Lance5500\"]\n\n1. d4 d5 1-0"
+        }));
+
+        match GameDisplayer::from_str(&mut game, "headers-json").unwrap() {
+            GameDisplayer::Default(headers) => assert_eq!(
+                headers,
+                r#"{"Event":"Rated Blitz game","White":"Lance5500"}"#
+            ),
+            _ => panic!("expected headers as JSON"),
+        }
+    }
+}
@@ -1,18 +1,29 @@
 use std::error;
 use std::fmt;
+use std::io;
 
 use reqwest;
 use serde_json;
 
 use crate::client;
+use crate::finder::FinderError;
+use crate::utils::MoveDecodeError;
 
 #[derive(Debug)]
 pub enum ChessError {
     GameNotFoundError,
+    AnalysisNotAvailableError,
+    ValidationError(String),
     UnsupportedOutputError(String),
+    UnsupportedEncodingError(String),
+    IOError(io::Error),
     RequestError(reqwest::Error),
     JSONError(serde_json::Error),
     ChessClientError(client::ClientError),
+    MoveDecodeError(MoveDecodeError),
+    DatabaseError(rusqlite::Error),
+    FinderError(FinderError),
+    AmbiguousMatch { count: usize },
 }
 
 impl fmt::Display for ChessError {
@@ -21,12 +32,28 @@ impl fmt::Display for ChessError {
             ChessError::GameNotFoundError => {
                 write!(f, "no game found that matches requested parameters")
             }
+            ChessError::AnalysisNotAvailableError => {
+                write!(f, "no computer analysis available for the game")
+            }
+            ChessError::ValidationError(reason) => write!(f, "validation failed: {}", reason),
             ChessError::RequestError(..) => write!(f, "a request to the chess api failed"),
             ChessError::JSONError(..) => {
                 write!(f, "JSON game serialization or deserialization failed")
             }
             ChessError::UnsupportedOutputError(out) => write!(f, "{} output is not supported", out),
+            ChessError::UnsupportedEncodingError(enc) => {
+                write!(f, "{} encoding is not supported", enc)
+            }
+            ChessError::IOError(..) => write!(f, "writing output failed"),
             ChessError::ChessClientError(e) => write!(f, "Chess API client failed: {}", e),
+            ChessError::MoveDecodeError(e) => write!(f, "decoding a move failed: {}", e),
+            ChessError::DatabaseError(e) => write!(f, "writing to the database failed: {}", e),
+            ChessError::FinderError(e) => write!(f, "finding games failed: {}", e),
+            ChessError::AmbiguousMatch { count } => write!(
+                f,
+                "{} games match the requested parameters, expected exactly one",
+                count
+            ),
         }
     }
 }
@@ -35,10 +62,18 @@ impl error::Error for ChessError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             ChessError::GameNotFoundError => None,
+            ChessError::AnalysisNotAvailableError => None,
+            ChessError::ValidationError(_) => None,
             ChessError::UnsupportedOutputError(_) => None,
+            ChessError::UnsupportedEncodingError(_) => None,
+            ChessError::IOError(ref e) => Some(e),
             ChessError::JSONError(ref e) => Some(e),
             ChessError::RequestError(ref e) => Some(e),
             ChessError::ChessClientError(ref e) => Some(e),
+            ChessError::MoveDecodeError(ref e) => Some(e),
+            ChessError::DatabaseError(ref e) => Some(e),
+            ChessError::FinderError(ref e) => Some(e),
+            ChessError::AmbiguousMatch { .. } => None,
         }
     }
 }
@@ -49,6 +84,12 @@ impl From<reqwest::Error> for ChessError {
     }
 }
 
+impl From<io::Error> for ChessError {
+    fn from(err: io::Error) -> ChessError {
+        ChessError::IOError(err)
+    }
+}
+
 impl From<client::ClientError> for ChessError {
     fn from(err: client::ClientError) -> ChessError {
         ChessError::ChessClientError(err)
@@ -60,3 +101,21 @@ impl From<serde_json::Error> for ChessError {
         ChessError::JSONError(err)
     }
 }
+
+impl From<MoveDecodeError> for ChessError {
+    fn from(err: MoveDecodeError) -> ChessError {
+        ChessError::MoveDecodeError(err)
+    }
+}
+
+impl From<rusqlite::Error> for ChessError {
+    fn from(err: rusqlite::Error) -> ChessError {
+        ChessError::DatabaseError(err)
+    }
+}
+
+impl From<FinderError> for ChessError {
+    fn from(err: FinderError) -> ChessError {
+        ChessError::FinderError(err)
+    }
+}
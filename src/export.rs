@@ -0,0 +1,95 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log;
+use serde::{Deserialize, Serialize};
+use serde_json;
+
+/// lichess opened in 2010, so no lichess player has games from before then.
+pub const LICHESS_FIRST_YEAR: u32 = 2010;
+
+/// Progress of a bulk export, saved in the export directory after every month so that an
+/// interrupted export can be resumed where it stopped.
+#[derive(Serialize, Deserialize, Default, Debug, PartialEq)]
+pub struct ExportCursor {
+    /// Last month, as (year, month), whose games were all written. Months are exported oldest
+    /// first, so every month before it was written too.
+    last_completed: Option<(u32, u32)>,
+}
+
+impl ExportCursor {
+    /// Where the cursor of an export is stored in its directory.
+    pub fn path(dir: &Path) -> PathBuf {
+        dir.join(".cgf-export-cursor.json")
+    }
+
+    /// Load a cursor, starting from the beginning if there is none or it can't be read.
+    pub fn load(path: &Path) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return ExportCursor::default(),
+        };
+        serde_json::from_str(&contents).unwrap_or_else(|e| {
+            log::warn!("Ignoring unreadable export cursor {:?}: {}", path, e);
+            ExportCursor::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    pub fn complete(&mut self, month: (u32, u32)) {
+        self.last_completed = Some(month);
+    }
+
+    /// Whether the month was already written by a previous run.
+    pub fn is_done(&self, month: (u32, u32)) -> bool {
+        match self.last_completed {
+            Some(last) => month <= last,
+            None => false,
+        }
+    }
+}
+
+/// Name of the file the games of a player in a month are exported to, e.g.
+/// a_player_2020_09.pgn.
+pub fn month_file_name(player: &str, (year, month): (u32, u32)) -> String {
+    format!("{}_{}_{:02}.pgn", player.to_lowercase(), year, month)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_skips_completed_months() {
+        let mut cursor = ExportCursor::default();
+        assert!(!cursor.is_done((2020, 1)));
+
+        cursor.complete((2020, 12));
+        assert!(cursor.is_done((2020, 1)));
+        assert!(cursor.is_done((2020, 12)));
+        assert!(!cursor.is_done((2021, 1)));
+    }
+
+    #[test]
+    fn test_cursor_save_and_load() {
+        let dir = std::env::temp_dir().join(format!("cgf_export_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = ExportCursor::path(&dir);
+        assert_eq!(ExportCursor::load(&path), ExportCursor::default());
+
+        let mut cursor = ExportCursor::default();
+        cursor.complete((2021, 3));
+        cursor.save(&path).unwrap();
+        assert_eq!(ExportCursor::load(&path), cursor);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            month_file_name("A_Player", (2020, 9)),
+            "a_player_2020_09.pgn"
+        );
+    }
+}
@@ -1,21 +1,255 @@
 use log;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread;
+use std::time::Instant;
 
-use chrono::{self, DateTime, Datelike, Utc};
-use reqwest::Url;
+use chrono::{self, DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use thiserror::Error;
 
 use crate::api::{
-    chessdotcom::GameArchives, ChessGame, ChessPlayer, DisplayableChessGame, Game, Games,
+    chessdotcom::{self, GameArchives, OngoingGame, PlayerStatus, PlayerTournament},
+    lichess_perf_type,
+    lichessdotorg::{self, ImportedGame},
+    normalized::GameIdentity,
+    ChessGame, ChessPlayer, Color, DisplayableChessGame, Game, Games, Outcome, Speed, TimeClass,
 };
-use crate::client::ChessClient;
+use crate::cache::{current_month, month_is_over, OpponentIndex};
+use crate::client::{ChessClient, RawRequest};
 use crate::error::ChessError;
+use crate::export::{month_file_name, ExportCursor, LICHESS_FIRST_YEAR};
+use crate::progress::Progress;
+use crate::sample::Reservoir;
 
-#[derive(PartialEq, Debug)]
+/// How many chess.com archives are requested at the same time by default.
+pub const DEFAULT_CONCURRENCY: usize = 1;
+
+/// Set when the user interrupts a scan, so that finders stop fetching further archives.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Install a Ctrl-C handler that makes running scans stop after the current request and return
+/// the games found so far. A second Ctrl-C exits immediately.
+pub fn install_interrupt_handler() -> Result<(), ctrlc::Error> {
+    ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            process::exit(130);
+        }
+        log::warn!("Interrupted, stopping scan. Press Ctrl-C again to exit immediately.");
+    })
+}
+
+/// Whether the user asked to stop with Ctrl-C.
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Inconsistencies between the API a finder searches and what it gets back, which are bugs in
+/// the finder rather than problems with the search.
+#[derive(Error, Debug, PartialEq)]
+pub enum FinderError {
+    #[error("{0:?} is not supported")]
+    UnsupportedApi(String),
+    #[error("expected games of {expected}, got games of {got}")]
+    UnexpectedGames {
+        expected: &'static str,
+        got: &'static str,
+    },
+    #[error("found a game of {white} and {black}, which {player} didn't play")]
+    NotPlayersGame {
+        player: String,
+        white: String,
+        black: String,
+    },
+}
+
+fn chess_dot_com_games(games: Games) -> Result<Vec<chessdotcom::Game>, FinderError> {
+    match games {
+        Games::ChessDotCom(v) => Ok(v),
+        games => Err(FinderError::UnexpectedGames {
+            expected: "chess.com",
+            got: games.api(),
+        }),
+    }
+}
+
+/// A visitor of scans that collects the games visited, until max games are collected.
+fn collector(found: &mut Vec<Game>, max: Option<usize>) -> impl FnMut(Game) -> bool + '_ {
+    move |game| {
+        found.push(game);
+        Some(found.len()) != max
+    }
+}
+
+fn oldest_first(mut games: Vec<Game>) -> Vec<Game> {
+    games.sort_by_key(|g| g.end_time());
+    games
+}
+
+fn lichess_games(games: Games) -> Result<Vec<lichessdotorg::Game>, FinderError> {
+    match games {
+        Games::LichessDotOrg(v) => Ok(v),
+        games => Err(FinderError::UnexpectedGames {
+            expected: "lichess.org",
+            got: games.api(),
+        }),
+    }
+}
+
+/// The one game found, or an error telling how many were found when there are several.
+fn only_game(mut games: Vec<Game>) -> Result<Game, ChessError> {
+    match games.len() {
+        0 => Err(ChessError::GameNotFoundError),
+        1 => Ok(games.remove(0)),
+        count => Err(ChessError::AmbiguousMatch { count }),
+    }
+}
+
+/// Apply f to every item with a thread for each state, each taking the next item once it's done
+/// with the last one, and return the results in the order of the items. States are e.g.
+/// clients, which can't be shared between threads.
+fn in_parallel<T, S, R, F>(items: &[T], states: Vec<S>, f: F) -> Vec<R>
+where
+    T: Sync,
+    S: Send,
+    R: Send,
+    F: Fn(&mut S, &T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let (next, f) = (&next, &f);
+    let mut results: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = states
+            .into_iter()
+            .take(items.len())
+            .map(|mut state| {
+                scope.spawn(move || {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::SeqCst);
+                        match items.get(i) {
+                            Some(item) => done.push((i, f(&mut state, item))),
+                            None => return done,
+                        }
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("a scan thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, r)| r).collect()
+}
+
+/// How a scan through the archives of a player ended.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Scanned {
+    /// Every archive was scanned, or there are more archives to scan.
+    More,
+    /// The scan was interrupted, or stopped once enough games were found.
+    Stopped,
+    /// The game of since_game_id was reached, and every game after it was scanned.
+    ReachedSinceGame,
+}
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
 pub enum Pieces {
     Black,
     White,
 }
 
-#[derive(PartialEq, Debug)]
+/// Key used to collapse multiple games into the most recent one, e.g. to keep only the last of
+/// a series of rematches.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum DedupeKey {
+    Opponent,
+    Opening,
+    Day,
+}
+
+impl FromStr for DedupeKey {
+    type Err = ChessError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        match key {
+            "opponent" => Ok(DedupeKey::Opponent),
+            "opening" => Ok(DedupeKey::Opening),
+            "day" => Ok(DedupeKey::Day),
+            key => Err(ChessError::ValidationError(format!(
+                "unsupported dedupe key {}",
+                key
+            ))),
+        }
+    }
+}
+
+/// Dimension games are grouped by when summarizing a player's results.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum GroupKey {
+    Opponent,
+    Opening,
+    Speed,
+    Month,
+    Color,
+    Termination,
+}
+
+impl FromStr for GroupKey {
+    type Err = ChessError;
+
+    fn from_str(key: &str) -> Result<Self, Self::Err> {
+        match key {
+            "opponent" => Ok(GroupKey::Opponent),
+            "opening" => Ok(GroupKey::Opening),
+            "speed" => Ok(GroupKey::Speed),
+            "month" => Ok(GroupKey::Month),
+            "color" => Ok(GroupKey::Color),
+            "termination" => Ok(GroupKey::Termination),
+            key => Err(ChessError::ValidationError(format!(
+                "unsupported group key {}",
+                key
+            ))),
+        }
+    }
+}
+
+/// Number of games in a group, and the player's wins, losses, and draws among them. Games
+/// without a result, like aborted ones, count only towards the games.
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Tally {
+    pub games: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub draws: usize,
+}
+
+impl Tally {
+    pub fn add(&mut self, outcome: Option<Outcome>) {
+        self.games += 1;
+        match outcome {
+            Some(Outcome::Win) => self.wins += 1,
+            Some(Outcome::Loss) => self.losses += 1,
+            Some(Outcome::Draw) => self.draws += 1,
+            None => (),
+        }
+    }
+}
+
+impl From<Pieces> for Color {
+    fn from(pieces: Pieces) -> Self {
+        match pieces {
+            Pieces::White => Color::White,
+            Pieces::Black => Color::Black,
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
 pub enum Search {
     Player(String),
     ID(String),
@@ -30,7 +264,7 @@ impl Search {
     }
 }
 
-#[derive(PartialEq, Debug)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct GameFinder {
     pub search: Search,
     pub api: String,
@@ -38,7 +272,41 @@ pub struct GameFinder {
     pub year: Option<u32>,
     pub month: Option<u32>,
     pub day: Option<u32>,
-    pub opponent: Option<String>,
+    pub opponents: Vec<String>,
+    pub ecos: Vec<String>,
+    pub results: Vec<Outcome>,
+    pub last: Option<usize>,
+    pub progress: bool,
+    pub dedupe_by: Option<DedupeKey>,
+    pub dump_fixtures: Option<PathBuf>,
+    pub min_duration: Option<Duration>,
+    pub max_duration: Option<Duration>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub prefer_source: Option<String>,
+    pub cache_dir: Option<PathBuf>,
+    pub checkmate: Option<bool>,
+    pub stalemate: Option<bool>,
+    pub quiet_errors: bool,
+    pub speed: Option<Speed>,
+    pub variant: Option<String>,
+    pub exact_opponent: bool,
+    pub concurrency: usize,
+    pub random: Option<u64>,
+    pub since_game_id: Option<String>,
+    pub fail_fast: bool,
+    pub opponent_titles: Option<Vec<String>>,
+    pub exclude_provisional: bool,
+    pub unique: bool,
+    pub lichess_params: Vec<(String, String)>,
+    pub max_total_retries: Option<usize>,
+    pub limit: Option<usize>,
+    pub strict: bool,
+    pub min_rating: Option<u32>,
+    pub max_rating: Option<u32>,
+    pub min_rating_gain: Option<u32>,
+    pub max_rating_loss: Option<u32>,
+    pub time_class: Option<TimeClass>,
 }
 
 impl GameFinder {
@@ -50,7 +318,41 @@ impl GameFinder {
             year: None,
             month: None,
             day: None,
-            opponent: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: DEFAULT_CONCURRENCY,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
         }
     }
 
@@ -62,7 +364,41 @@ impl GameFinder {
             year: None,
             month: None,
             day: None,
-            opponent: None,
+            opponents: Vec::new(),
+            ecos: Vec::new(),
+            results: Vec::new(),
+            last: None,
+            progress: false,
+            dedupe_by: None,
+            dump_fixtures: None,
+            min_duration: None,
+            max_duration: None,
+            from: None,
+            to: None,
+            prefer_source: None,
+            cache_dir: None,
+            checkmate: None,
+            stalemate: None,
+            quiet_errors: false,
+            speed: None,
+            variant: None,
+            exact_opponent: false,
+            concurrency: DEFAULT_CONCURRENCY,
+            random: None,
+            since_game_id: None,
+            fail_fast: false,
+            opponent_titles: None,
+            exclude_provisional: false,
+            unique: false,
+            lichess_params: Vec::new(),
+            max_total_retries: None,
+            limit: None,
+            strict: false,
+            min_rating: None,
+            max_rating: None,
+            min_rating_gain: None,
+            max_rating_loss: None,
+            time_class: None,
         }
     }
 
@@ -106,125 +442,2266 @@ impl GameFinder {
         self
     }
 
-    pub fn oponent<'a>(&'a mut self, opponent: &str) -> &'a mut GameFinder {
+    /// Add an opponent to look for. Games against any of the opponents added are found.
+    pub fn opponent<'a>(&'a mut self, opponent: &str) -> &'a mut GameFinder {
         let mut opponent = opponent.to_owned();
-        opponent.make_ascii_lowercase();
-        self.opponent = Some(opponent);
+        if !self.exact_opponent {
+            opponent.make_ascii_lowercase();
+        }
+        self.opponents.push(opponent);
+        self
+    }
+
+    #[deprecated(note = "renamed to opponent")]
+    pub fn oponent<'a>(&'a mut self, opponent: &str) -> &'a mut GameFinder {
+        self.opponent(opponent)
+    }
+
+    /// Only find games against titled opponents.
+    pub fn titled<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.opponent_titles.get_or_insert_with(Vec::new);
+        self
+    }
+
+    /// Add a title, like GM, for opponents to have. Games against opponents with any of the
+    /// titles added are found.
+    pub fn opponent_title<'a>(&'a mut self, title: &str) -> &'a mut GameFinder {
+        self.opponent_titles
+            .get_or_insert_with(Vec::new)
+            .push(title.to_uppercase());
+        self
+    }
+
+    /// Add a query parameter to the requests of the lichess games endpoint, for the parameters
+    /// without a search option of their own.
+    pub fn lichess_param<'a>(&'a mut self, key: &str, value: &str) -> &'a mut GameFinder {
+        self.lichess_params.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Only find the n most recent games that match the search with find_all_by_player. Other
+    /// searches find a single game anyway.
+    pub fn limit<'a>(&'a mut self, n: usize) -> &'a mut GameFinder {
+        self.limit = Some(n);
+        self
+    }
+
+    /// Fail when a game found by player is not one they played, instead of logging it.
+    pub fn strict<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.strict = true;
+        self
+    }
+
+    /// Cap the retries of failed requests over the whole run, after which failures surface
+    /// immediately instead of being retried.
+    pub fn max_total_retries<'a>(&'a mut self, retries: usize) -> &'a mut GameFinder {
+        self.max_total_retries = Some(retries);
+        self
+    }
+
+    /// Require exactly one game to match a search by player, scanning every game of the player
+    /// instead of stopping at the first match.
+    pub fn unique<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.unique = true;
+        self
+    }
+
+    /// Only find games where the opponent was rated at least min. Games against opponents
+    /// without a rating, like lichess's anonymous players, are left out.
+    pub fn min_rating<'a>(&'a mut self, min: u32) -> &'a mut GameFinder {
+        self.min_rating = Some(min);
+        self
+    }
+
+    /// Only find games where the opponent was rated at most max. Games against opponents
+    /// without a rating, like lichess's anonymous players, are left out.
+    pub fn max_rating<'a>(&'a mut self, max: u32) -> &'a mut GameFinder {
+        self.max_rating = Some(max);
+        self
+    }
+
+    /// Only find games where the searched player gained at least points rating points. Games
+    /// without a rating change, like unrated ones, are left out.
+    pub fn min_rating_gain<'a>(&'a mut self, points: u32) -> &'a mut GameFinder {
+        self.min_rating_gain = Some(points);
+        self
+    }
+
+    /// Only find games where the searched player lost at least points rating points. Games
+    /// without a rating change, like unrated ones, are left out.
+    pub fn max_rating_loss<'a>(&'a mut self, points: u32) -> &'a mut GameFinder {
+        self.max_rating_loss = Some(points);
+        self
+    }
+
+    /// Leave out games where either player's rating was provisional.
+    pub fn exclude_provisional<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.exclude_provisional = true;
+        self
+    }
+
+    /// Match opponents with the exact case they are added with, e.g. to tell lichess's
+    /// "Anonymous" opponents apart from a user named "anonymous". Applies to the opponents added
+    /// after it.
+    pub fn exact_opponent<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.exact_opponent = true;
+        self
+    }
+
+    /// Request up to n chess.com archives at the same time when scanning them. The clients
+    /// making them share their pacing, and rate limited requests are retried after waiting.
+    /// lichess.org months are always requested one at a time, as lichess asks clients to.
+    pub fn concurrency<'a>(&'a mut self, n: usize) -> &'a mut GameFinder {
+        self.concurrency = n.max(1);
+        self
+    }
+
+    /// Add an ECO code, or the start of one like "B2", to look for. Games with any of the ECO
+    /// codes added are found.
+    pub fn eco<'a>(&'a mut self, eco: &str) -> &'a mut GameFinder {
+        self.ecos.push(eco.to_uppercase());
+        self
+    }
+
+    /// Add an outcome for the player to look for. Games with any of the outcomes added are
+    /// found.
+    pub fn result<'a>(&'a mut self, outcome: Outcome) -> &'a mut GameFinder {
+        self.results.push(outcome);
+        self
+    }
+
+    /// Only find games that did, or did not, finish with a checkmate.
+    pub fn checkmate<'a>(&'a mut self, checkmate: bool) -> &'a mut GameFinder {
+        self.checkmate = Some(checkmate);
+        self
+    }
+
+    /// Only find games that did, or did not, finish with a stalemate.
+    pub fn stalemate<'a>(&'a mut self, stalemate: bool) -> &'a mut GameFinder {
+        self.stalemate = Some(stalemate);
+        self
+    }
+
+    /// Fail a search of both APIs with --prefer-source when either of them fails, instead of
+    /// returning the games of the one that succeeded.
+    pub fn fail_fast<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.fail_fast = true;
+        self
+    }
+
+    /// Find a game picked at random among the games that match the search, instead of the
+    /// latest one. The same seed picks the same game out of the same games.
+    pub fn random<'a>(&'a mut self, seed: u64) -> &'a mut GameFinder {
+        self.random = Some(seed);
+        self
+    }
+
+    /// Only find the games played after the game with the given ID or URL, stopping the scan
+    /// once it's reached.
+    pub fn since_game_id<'a>(&'a mut self, id: &str) -> &'a mut GameFinder {
+        self.since_game_id = Some(id.to_string());
+        self
+    }
+
+    pub fn last<'a>(&'a mut self, n: usize) -> &'a mut GameFinder {
+        self.last = Some(n);
+        self
+    }
+
+    /// Report the progress of archive scans, with an estimate of the time remaining.
+    pub fn progress<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.progress = true;
         self
     }
 
+    /// Keep only the most recent game for each value of key when collecting multiple games.
+    pub fn dedupe_by<'a>(&'a mut self, key: DedupeKey) -> &'a mut GameFinder {
+        self.dedupe_by = Some(key);
+        self
+    }
+
+    /// Only find games that lasted at least duration, from start to end.
+    pub fn min_duration<'a>(&'a mut self, duration: Duration) -> &'a mut GameFinder {
+        self.min_duration = Some(duration);
+        self
+    }
+
+    /// Only find games that lasted at most duration, from start to end.
+    pub fn max_duration<'a>(&'a mut self, duration: Duration) -> &'a mut GameFinder {
+        self.max_duration = Some(duration);
+        self
+    }
+
+    /// Only find games that ended on date or later.
+    pub fn from<'a>(&'a mut self, date: NaiveDate) -> &'a mut GameFinder {
+        self.from = Some(date);
+        self
+    }
+
+    /// Only find games that ended on date or earlier.
+    pub fn to<'a>(&'a mut self, date: NaiveDate) -> &'a mut GameFinder {
+        self.to = Some(date);
+        self
+    }
+
+    /// Only find games that ended in the ISO week, Monday to Sunday, of date.
+    pub fn week_of<'a>(&'a mut self, date: NaiveDate) -> &'a mut GameFinder {
+        let monday = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+        self.from = Some(monday);
+        self.to = Some(monday + Duration::days(6));
+        self
+    }
+
+    /// Search the player's games on both APIs and merge them. When the same game is found on
+    /// both, e.g. because it was imported from one into the other, the copy from api is kept.
+    pub fn prefer_source<'a>(&'a mut self, api: &str) -> &'a mut GameFinder {
+        self.prefer_source = Some(api.to_owned());
+        self
+    }
+
+    /// Cache what is learned from searches under dir, like the months in which the player
+    /// played each opponent, so that repeated searches skip requests.
+    pub fn cache_dir<'a>(&'a mut self, dir: &str) -> &'a mut GameFinder {
+        self.cache_dir = Some(PathBuf::from(dir));
+        self
+    }
+
+    /// Don't warn about each game skipped because it could not be parsed.
+    pub fn quiet_errors<'a>(&'a mut self) -> &'a mut GameFinder {
+        self.quiet_errors = true;
+        self
+    }
+
+    /// Only find games of the given speed. lichess filters them before sending them.
+    pub fn speed<'a>(&'a mut self, speed: Speed) -> &'a mut GameFinder {
+        self.speed = Some(speed);
+        self
+    }
+
+    /// Only find games of the given time class, told by the base time on the clock alone for
+    /// chess.com games and by the speed lichess gives them for lichess games.
+    pub fn time_class<'a>(&'a mut self, time_class: TimeClass) -> &'a mut GameFinder {
+        self.time_class = Some(time_class);
+        self
+    }
+
+    /// Only find games of the given variant, named as lichess names them, e.g. "chess960".
+    /// lichess filters them before sending them.
+    pub fn variant<'a>(&'a mut self, variant: &str) -> &'a mut GameFinder {
+        self.variant = Some(variant.to_string());
+        self
+    }
+
+    /// Save the responses of every request into dir, to be used as test fixtures.
+    pub fn dump_fixtures<'a>(&'a mut self, dir: &str) -> &'a mut GameFinder {
+        self.dump_fixtures = Some(PathBuf::from(dir));
+        self
+    }
+
+    /// Check that the search can match any game before making requests. Ranges with their
+    /// bounds reversed are rejected, as they would silently scan nothing.
+    pub fn validate(&self) -> Result<(), ChessError> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(ChessError::ValidationError(format!(
+                    "from date {} is after to date {}",
+                    from, to
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            if min > max {
+                return Err(ChessError::ValidationError(
+                    "minimum duration is longer than maximum duration".to_string(),
+                ));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_rating, self.max_rating) {
+            if min > max {
+                return Err(ChessError::ValidationError(format!(
+                    "minimum rating {} is higher than maximum rating {}",
+                    min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Warnings about a search that is valid but can't find any game yet, like one for a future
+    /// year.
+    pub fn warnings(&self, now: DateTime<Utc>) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let this_month = (now.year() as u32, now.month());
+        if let Some(year) = self.year {
+            if year > this_month.0 {
+                warnings.push(format!("{} is in the future, no games can exist yet", year));
+            } else if let Some(month) = self.month {
+                if (year, month) > this_month {
+                    warnings.push(format!(
+                        "{}/{} is in the future, no games can exist yet",
+                        month, year
+                    ));
+                }
+            }
+        }
+        if let Some(from) = self.from {
+            if from > now.date().naive_utc() {
+                warnings.push(format!(
+                    "from date {} is in the future, no games can exist yet",
+                    from
+                ));
+            }
+        }
+        if self.opponent_titles.is_some()
+            && (self.api == "chess.com" || self.prefer_source.is_some())
+        {
+            warnings.push(
+                "chess.com archives don't include titles, no chess.com game will be found against a titled opponent"
+                    .to_string(),
+            );
+        }
+        if (self.min_rating_gain.is_some() || self.max_rating_loss.is_some())
+            && (self.api == "chess.com" || self.prefer_source.is_some())
+        {
+            warnings.push(
+                "chess.com archives don't include rating changes, no chess.com game will be found by rating gain or loss"
+                    .to_string(),
+            );
+        }
+        warnings
+    }
+
+    fn client(&self) -> Result<ChessClient, ChessError> {
+        let mut client = ChessClient::new(10, &self.api)?;
+        if self.quiet_errors {
+            client = client.quiet_errors();
+        }
+        if self.api == "lichess.org" {
+            if let Some(perf_type) = lichess_perf_type(self.speed, self.variant.as_deref()) {
+                client = client.perf_type(perf_type);
+            }
+            client = client.lichess_params(self.lichess_params.clone());
+        }
+        if self.api == "lichess.org" && (self.from.is_some() || self.to.is_some()) {
+            client = client.games_between(
+                self.from
+                    .map(|from| Utc.from_utc_date(&from).and_hms(0, 0, 0)),
+                self.to
+                    .map(|to| Utc.from_utc_date(&to).and_hms_milli(23, 59, 59, 999)),
+            );
+        }
+        if let Some(retries) = self.max_total_retries {
+            client = client.max_total_retries(retries);
+        }
+        if let Some(dir) = &self.cache_dir {
+            client = client.cache_responses(dir.join("responses"));
+        }
+        match &self.dump_fixtures {
+            Some(dir) => Ok(client.dump_fixtures(dir.clone())),
+            None => Ok(client),
+        }
+    }
+
     pub fn find_by_id(&self) -> Result<Game, ChessError> {
-        let client = ChessClient::new(10, &self.api)?;
+        let client = self.client()?;
         let id = self.search.get_value();
         log::info!("Getting game by id");
         let game = client.get_game(&id)?;
         Ok(game)
     }
 
+    /// Find the latest game of the player that matches the search. The game is checked to be
+    /// one the player played, failing with strict and logging otherwise.
     pub fn find_by_player(&self) -> Result<Game, ChessError> {
-        let client = ChessClient::new(10, &self.api)?;
+        let mut game = self.find_latest_by_player()?;
+        self.check_played_by_player(&mut game)?;
+        Ok(game)
+    }
+
+    /// A safety net for bugs in the filters: whether the searched player is white or black.
+    fn check_played_by_player(&self, game: &mut impl ChessGame) -> Result<(), FinderError> {
+        let player = self.search.get_value();
+        let (white, black) = (game.white().name(), game.black().name());
+        if white.eq_ignore_ascii_case(player) || black.eq_ignore_ascii_case(player) {
+            return Ok(());
+        }
+        let error = FinderError::NotPlayersGame {
+            player: player.to_string(),
+            white,
+            black,
+        };
+        if self.strict {
+            Err(error)
+        } else {
+            log::debug!("{}", error);
+            Ok(())
+        }
+    }
+
+    fn find_latest_by_player(&self) -> Result<Game, ChessError> {
+        if let Some(seed) = self.random {
+            return self.find_random_by_player(seed);
+        }
+        if self.unique {
+            return only_game(self.collect_by_player(None)?);
+        }
+        let client = self.client()?;
         let player = self.search.get_value();
         match self.api.as_str() {
             "chess.com" => {
                 log::info!("Getting game archives");
                 let game_archives = client.get_user_game_archives(&player)?;
                 let archives: Vec<(u32, u32)> = self.year_month_archives(game_archives);
+                let mut progress = self.progress_of("Scanning archives", archives.len());
+                let mut index = self.load_index();
 
                 log::info!("Looking for game, iterating through archives.");
-                for date in archives.iter() {
-                    let (year, month) = date;
-                    log::info!("At {:?}/{:?}", month, year);
-
-                    match client.get_user_month_games(&player, *year as i32, *month)? {
-                        Games::ChessDotCom(mut v) => {
-                            v.sort_by_key(|g| g.end_time());
-                            v.reverse();
-                            for mut game in v.into_iter() {
-                                if self.check_game_found(&mut game) {
-                                    return Ok(Game::ChessDotCom(game));
-                                }
+                for batch in archives.chunks(self.concurrency) {
+                    if interrupted() {
+                        break;
+                    }
+                    let months =
+                        self.batch_month_games(&client, player, batch, &mut index, &mut progress)?;
+                    for games in months.into_iter().flatten() {
+                        let mut v = chess_dot_com_games(games)?;
+                        v.sort_by_key(|g| g.end_time());
+                        v.reverse();
+                        for mut game in v.into_iter() {
+                            if self.check_game_found(&mut game) {
+                                self.save_index(&index);
+                                return Ok(Game::ChessDotCom(game));
                             }
                         }
-                        _ => panic!("Should never happen"),
                     }
                 }
+                self.save_index(&index);
             }
             "lichess.org" => {
                 log::info!("Getting user games");
-                let game = client.get_last_user_game(&player)?;
-                return Ok(game);
+                let mut games = self.collect_by_player(Some(1))?;
+                return Ok(games.remove(0));
             }
-            a => panic!("Unsupported API: {}", a),
+            a => return Err(FinderError::UnsupportedApi(a.to_string()).into()),
         };
 
         Err(ChessError::GameNotFoundError)
     }
 
-    fn year_month_archives(&self, game_archives: GameArchives) -> Vec<(u32, u32)> {
-        let mut archives = game_archives
-            .archives
-            .iter()
-            .map(|s| Url::parse(s))
-            .filter_map(Result::ok)
-            .map(|u| {
-                let mut segments = u.path_segments().unwrap();
-                segments.next();
-                segments.next();
-                segments.next();
-                segments.next();
-
-                let year = segments.next().unwrap().parse::<u32>().unwrap();
-                let month = segments.next().unwrap().parse::<u32>().unwrap();
-
-                (year, month)
-            })
-            .filter(|&(y, m)| match self.year {
-                Some(year) => match self.month {
-                    Some(month) => year == y && month == m,
-                    None => year == y,
-                },
-                None => match self.month {
-                    Some(month) => month == m,
-                    None => true,
-                },
-            })
-            .collect::<Vec<(u32, u32)>>();
-        archives.reverse();
-        archives
+    /// Find every game of the player that matches the search, or the most recent limit games,
+    /// oldest first. Every archive that matches the date filters is scanned until limit games
+    /// are found, unlike find_by_player, which stops at the latest.
+    pub fn find_all_by_player(&self) -> Result<Vec<Game>, ChessError> {
+        Ok(oldest_first(self.collect_by_player(self.limit)?))
     }
 
-    fn check_game_found(&self, g: &mut impl DisplayableChessGame) -> bool {
-        self.players_had_correct_colors(g) && self.played_on_expected_day(g)
+    /// Find the last N games of a player that match the search, newest first. N is taken
+    /// from last, defaulting to 1, or to every game played after it with since_game_id.
+    pub fn find_last_by_player(&self) -> Result<Vec<Game>, ChessError> {
+        if self.since_game_id.is_some() {
+            return self.collect_by_player(self.last);
+        }
+        let n = self.last.unwrap_or(1);
+        match &self.prefer_source {
+            Some(preferred) => self.collect_from_both(preferred, n),
+            None => self.collect_by_player(Some(n)),
+        }
     }
 
-    fn played_on_expected_day(&self, g: &mut impl DisplayableChessGame) -> bool {
-        match self.day {
-            Some(d) => g.end_time().day() == d,
-            None => true,
+    /// Collect the last n games of the player from both APIs, newest first. Games found on both
+    /// are recognized by their players, day, and result, and only the copy from the preferred API
+    /// is kept.
+    fn collect_from_both(&self, preferred: &str, n: usize) -> Result<Vec<Game>, ChessError> {
+        let other = match preferred {
+            "chess.com" => "lichess.org",
+            _ => "chess.com",
+        };
+        self.merge_from(&[preferred, other], n, |finder| {
+            finder.collect_by_player(Some(n))
+        })
+    }
+
+    /// Merge the last n games collected from each API with collect, newest first. With
+    /// fail_fast, an error of any API is returned. Otherwise, the games of the APIs that
+    /// succeeded are returned, with a warning for each API that failed, and an error only if
+    /// they all failed.
+    fn merge_from<F>(&self, apis: &[&str], n: usize, collect: F) -> Result<Vec<Game>, ChessError>
+    where
+        F: Fn(&GameFinder) -> Result<Vec<Game>, ChessError>,
+    {
+        let mut merged = Vec::new();
+        let mut seen = HashSet::new();
+        let mut failed = Vec::new();
+        for api in apis.iter() {
+            let mut finder = self.clone();
+            finder.api = api.to_string();
+            let games = match collect(&finder) {
+                Ok(games) => games,
+                Err(ChessError::GameNotFoundError) => Vec::new(),
+                Err(e) if self.fail_fast => return Err(e),
+                Err(e) => {
+                    eprintln!("Warning: searching {} failed, skipping it: {}", api, e);
+                    failed.push(e);
+                    continue;
+                }
+            };
+            for mut game in games.into_iter() {
+                if seen.insert(GameIdentity::of(&mut game)) {
+                    merged.push(game);
+                }
+            }
+        }
+
+        if failed.len() == apis.len() {
+            return Err(failed.remove(0));
+        }
+        merged.sort_by_key(|g| g.end_time());
+        merged.reverse();
+        merged.truncate(n);
+        if merged.is_empty() {
+            Err(ChessError::GameNotFoundError)
+        } else {
+            Ok(merged)
         }
     }
 
-    fn players_had_correct_colors(&self, g: &mut impl DisplayableChessGame) -> bool {
+    /// Collect the games of a player that match the search, newest first, stopping once max
+    /// games are found. Without max, every archive that matches the date filters is scanned.
+    /// With dedupe_by, only the most recent game of each key is collected, before max applies.
+    pub fn collect_by_player(&self, max: Option<usize>) -> Result<Vec<Game>, ChessError> {
+        let mut found = Vec::new();
+        self.scan_by_player(max, collector(&mut found, max))?;
+
+        if found.is_empty() {
+            Err(ChessError::GameNotFoundError)
+        } else {
+            Ok(found)
+        }
+    }
+
+    /// Pick a game uniformly at random among the games of the player that match the search.
+    /// Every archive that matches the date filters is scanned once, keeping a single game at a
+    /// time, so the pick is reproducible for a given seed as long as the games don't change.
+    pub fn find_random_by_player(&self, seed: u64) -> Result<Game, ChessError> {
+        let mut sample = Reservoir::new(1, seed);
+        self.scan_by_player(None, |game| {
+            sample.offer(game);
+            true
+        })?;
+        sample
+            .into_items()
+            .pop()
+            .ok_or(ChessError::GameNotFoundError)
+    }
+
+    /// Pass the games of a player that match the search to visit, newest first, until visit
+    /// returns false or the game of since_game_id is reached. max is how many games a caller may
+    /// stop at, which the lichess.org API is asked for upfront. Returns how many games were
+    /// visited.
+    fn scan_by_player<F>(&self, max: Option<usize>, mut visit: F) -> Result<usize, ChessError>
+    where
+        F: FnMut(Game) -> bool,
+    {
+        let client = self.client()?;
         let player = self.search.get_value();
+        let mut visited = 0;
+        let mut seen = HashSet::new();
+        let mut scanned = Scanned::More;
+
+        match self.api.as_str() {
+            "chess.com" => {
+                log::info!("Getting game archives");
+                let game_archives = client.get_user_game_archives(player)?;
+                let archives: Vec<(u32, u32)> = self.year_month_archives(game_archives);
+                let mut progress = self.progress_of("Scanning archives", archives.len());
+                let mut index = self.load_index();
 
-        match &self.pieces {
-            Some(pieces) => match pieces {
-                Pieces::Black => match &self.opponent {
-                    Some(o) => {
-                        &g.black().name().to_lowercase() == player
-                            && &g.white().name().to_lowercase() == o
+                log::info!("Collecting games, iterating through archives.");
+                'archives: for batch in archives.chunks(self.concurrency) {
+                    if interrupted() {
+                        log::warn!("Scan interrupted, returning {} games", visited);
+                        scanned = Scanned::Stopped;
+                        break;
                     }
-                    None => &g.black().name().to_lowercase() == player,
-                },
-                Pieces::White => match &self.opponent {
-                    Some(o) => {
-                        &g.white().name().to_lowercase() == player
-                            && &g.black().name().to_lowercase() == o
+                    let months =
+                        self.batch_month_games(&client, player, batch, &mut index, &mut progress)?;
+                    for games in months.into_iter().flatten() {
+                        let mut v = chess_dot_com_games(games)?;
+                        v.sort_by_key(|g| g.end_time());
+                        v.reverse();
+                        scanned = self.visit_archive(
+                            v,
+                            Game::ChessDotCom,
+                            &mut seen,
+                            &mut visited,
+                            &mut visit,
+                        );
+                        if scanned != Scanned::More {
+                            break 'archives;
+                        }
                     }
-                    None => &g.white().name().to_lowercase() == player,
-                },
-            },
-            None => true,
-        }
+                }
+                self.save_index(&index);
+            }
+            "lichess.org" if self.year.is_some() => {
+                // Large scans are split into one request per month, which the client paces to
+                // stay under the lichess rate limit
+                let windows = self.month_windows(Utc::now());
+                let mut progress = self.progress_of("Scanning months", windows.len());
+                let mut index = self.load_index();
+                log::info!(
+                    "Collecting games, iterating through {} months.",
+                    windows.len()
+                );
+                for (i, (year, month)) in windows.iter().enumerate() {
+                    if interrupted() {
+                        log::warn!("Scan interrupted, returning {} games", visited);
+                        scanned = Scanned::Stopped;
+                        break;
+                    }
+                    log::info!("At {:?}/{:?} ({}/{})", month, year, i + 1, windows.len());
+
+                    let games = self.timed(&mut progress, || {
+                        self.month_games(&client, player, (*year, *month), &mut index)
+                    })?;
+                    if let Some(games) = games {
+                        scanned = self.visit_archive(
+                            lichess_games(games)?,
+                            Game::LichessDotOrg,
+                            &mut seen,
+                            &mut visited,
+                            &mut visit,
+                        );
+                        if scanned != Scanned::More {
+                            break;
+                        }
+                    }
+                }
+                self.save_index(&index);
+            }
+            "lichess.org" => {
+                log::info!("Getting user games");
+                let games =
+                    lichess_games(client.get_last_user_games(player, self.lichess_max(max))?)?;
+                scanned = self.visit_archive(
+                    games,
+                    Game::LichessDotOrg,
+                    &mut seen,
+                    &mut visited,
+                    &mut visit,
+                );
+            }
+            a => return Err(FinderError::UnsupportedApi(a.to_string()).into()),
+        };
+
+        if let (Some(id), Scanned::More) = (&self.since_game_id, scanned) {
+            log::warn!(
+                "Game {} was not found, returning every game scanned instead",
+                id
+            );
+        }
+        Ok(visited)
+    }
+
+    /// How many of the player's latest games to ask the lichess.org API for when a caller may
+    /// stop at max. Duplicates and the games left out by filters the API doesn't apply can't be
+    /// known upfront, so every game is requested when deduping or filtering here.
+    fn lichess_max(&self, max: Option<usize>) -> Option<usize> {
+        if self.dedupe_by.is_some() || self.filters_after_lichess() {
+            None
+        } else {
+            max
+        }
+    }
+
+    /// Whether any filter of the search is only applied to lichess.org games once they are
+    /// received, instead of by the request. Dates, and speed or variant when they make a perf
+    /// type, are filtered by the request.
+    fn filters_after_lichess(&self) -> bool {
+        let filtered_by_perf_type = match (self.speed, self.variant.as_deref()) {
+            (None, None) => true,
+            (Some(Speed::Unknown), _) => false,
+            (Some(_), None) | (Some(_), Some("standard")) => true,
+            (None, Some(variant)) => variant != "standard",
+            (Some(_), Some(_)) => false,
+        };
+        !filtered_by_perf_type
+            || self.pieces.is_some()
+            || self.day.is_some()
+            || self.min_duration.is_some()
+            || self.max_duration.is_some()
+            || !self.opponents.is_empty()
+            || self.opponent_titles.is_some()
+            || self.min_rating.is_some()
+            || self.max_rating.is_some()
+            || self.min_rating_gain.is_some()
+            || self.max_rating_loss.is_some()
+            || self.exclude_provisional
+            || !self.ecos.is_empty()
+            || !self.results.is_empty()
+            || self.checkmate.is_some()
+            || self.stalemate.is_some()
+            || self.time_class.is_some()
+    }
+
+    /// Pass the games of an archive, newest first, that match the search to visit, counting them
+    /// in visited. Games already seen with dedupe_by are skipped.
+    fn visit_archive<G, F>(
+        &self,
+        games: Vec<G>,
+        wrap: fn(G) -> Game,
+        seen: &mut HashSet<String>,
+        visited: &mut usize,
+        visit: &mut F,
+    ) -> Scanned
+    where
+        G: DisplayableChessGame,
+        F: FnMut(Game) -> bool,
+    {
+        for mut game in games.into_iter() {
+            if self.is_since_game(&game) {
+                return Scanned::ReachedSinceGame;
+            }
+            if self.check_game_found(&mut game) && self.first_of_key(seen, &mut game) {
+                *visited += 1;
+                if !visit(wrap(game)) {
+                    return Scanned::Stopped;
+                }
+            }
+        }
+        Scanned::More
+    }
+
+    /// Whether the game is the one of since_game_id, which may be given as an ID or a game URL.
+    fn is_since_game(&self, g: &impl ChessGame) -> bool {
+        let last_segment = |url: &str| {
+            url.trim_end_matches('/')
+                .rsplit('/')
+                .next()
+                .map(String::from)
+        };
+        match &self.since_game_id {
+            Some(id) => last_segment(id.trim()) == last_segment(&g.url()),
+            None => false,
+        }
+    }
+
+    /// Write every game of the player into dir, one PGN file per month, oldest first. Returns
+    /// how many games were written. The last month written is recorded in a cursor file in dir,
+    /// and with resume, the months a previous export already wrote are skipped. The current month
+    /// is never recorded, as more games may still be played in it.
+    pub fn export_by_player(&self, dir: &Path, resume: bool) -> Result<usize, ChessError> {
+        let client = self.client()?;
+        let player = self.search.get_value();
+        let now = Utc::now();
+        let current = current_month(&now);
+
+        fs::create_dir_all(dir)?;
+        let cursor_path = ExportCursor::path(dir);
+        let mut cursor = if resume {
+            ExportCursor::load(&cursor_path)
+        } else {
+            ExportCursor::default()
+        };
+
+        let months = self
+            .export_months(&client, now)?
+            .into_iter()
+            .filter(|month| !cursor.is_done(*month))
+            .collect::<Vec<(u32, u32)>>();
+        let mut progress = self.progress_of("Exporting months", months.len());
+        let mut written = 0;
+
+        log::info!("Exporting {} months", months.len());
+        for (year, month) in months.into_iter() {
+            if interrupted() {
+                eprintln!("Export interrupted, run it again with --resume to continue");
+                break;
+            }
+
+            let games = self.timed(&mut progress, || {
+                client.get_user_month_games(player, year as i32, month)
+            })?;
+            let mut games: Vec<Game> = match games {
+                Games::ChessDotCom(v) => v.into_iter().map(Game::ChessDotCom).collect(),
+                Games::LichessDotOrg(v) => v.into_iter().map(Game::LichessDotOrg).collect(),
+            };
+            games.sort_by_key(|g| g.end_time());
+
+            if !games.is_empty() {
+                let pgns = games.iter_mut().map(|g| g.pgn()).collect::<Vec<String>>();
+                let path = dir.join(month_file_name(player, (year, month)));
+                log::info!("Writing {} games to {:?}", games.len(), path);
+                fs::write(path, pgns.join("\n\n") + "\n")?;
+                written += games.len();
+            }
+
+            if (year, month) < current {
+                cursor.complete((year, month));
+                cursor.save(&cursor_path)?;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Months with games to export, oldest first: every chess.com archive, or every month since
+    /// lichess opened.
+    fn export_months(
+        &self,
+        client: &ChessClient,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<(u32, u32)>, ChessError> {
+        match self.api.as_str() {
+            "chess.com" => {
+                let player = self.search.get_value();
+                let mut archives = self.year_month_archives(client.get_user_game_archives(player)?);
+                archives.reverse();
+                Ok(archives)
+            }
+            "lichess.org" => {
+                let current = current_month(&now);
+                Ok((LICHESS_FIRST_YEAR..=current.0)
+                    .flat_map(|year| (1..=12).map(move |month| (year, month)))
+                    .filter(|month| *month <= current)
+                    .collect())
+            }
+            a => Err(FinderError::UnsupportedApi(a.to_string()).into()),
+        }
+    }
+
+    /// The username of a player of the api with the casing they chose, as shown in their
+    /// profile.
+    pub fn display_username(&self, api: &str, username: &str) -> Result<String, ChessError> {
+        let mut finder = self.clone();
+        finder.api = api.to_string();
+        Ok(finder.client()?.get_display_username(username)?)
+    }
+
+    /// Ratings of the player over time, oldest first, optionally only of games of the given
+    /// speed. chess.com ratings are taken from the player's games, while lichess ratings come
+    /// from its rating history endpoint, skipping variants unless a speed is given.
+    pub fn rating_history(
+        &self,
+        speed: Option<Speed>,
+    ) -> Result<Vec<(NaiveDate, u32)>, ChessError> {
+        let player = self.search.get_value().to_lowercase();
+        let mut points = Vec::new();
+
+        match self.api.as_str() {
+            "chess.com" => {
+                let mut games = self.collect_by_player(None)?;
+                games.reverse();
+                for game in games.iter_mut() {
+                    if let Some(s) = speed {
+                        if s != game.speed() {
+                            continue;
+                        }
+                    }
+                    let rating = if game.white().name().to_lowercase() == player {
+                        game.white().rating()
+                    } else {
+                        game.black().rating()
+                    };
+                    if let Some(rating) = rating {
+                        points.push((game.end_time().date().naive_utc(), rating));
+                    }
+                }
+            }
+            "lichess.org" => {
+                let client = self.client()?;
+                for history in client.get_rating_history(&player)? {
+                    let kept = match speed {
+                        Some(s) => history.speed() == s,
+                        None => history.speed() != Speed::Unknown,
+                    };
+                    if kept {
+                        points.extend(history.points());
+                    }
+                }
+                points.sort();
+            }
+            a => return Err(FinderError::UnsupportedApi(a.to_string()).into()),
+        };
+
+        if points.is_empty() {
+            Err(ChessError::GameNotFoundError)
+        } else {
+            Ok(points)
+        }
+    }
+
+    /// Collect the games of the player that match the search (all of them, or the last N with
+    /// last) and tally their results by the given key.
+    pub fn group_by_player(&self, key: GroupKey) -> Result<BTreeMap<String, Tally>, ChessError> {
+        let mut games = self.collect_by_player(self.last)?;
+        Ok(self.group(&mut games, key))
+    }
+
+    /// Tally the player's results in the games by the given key. Games without an opening, like
+    /// lichess games too short to reach one, are left out of the opening groups.
+    fn group(&self, games: &mut [Game], key: GroupKey) -> BTreeMap<String, Tally> {
+        let player = self.search.get_value().to_lowercase();
+        let mut groups: BTreeMap<String, Tally> = BTreeMap::new();
+        for game in games.iter_mut() {
+            let group = match key {
+                GroupKey::Opponent => self.opponent_of(game),
+                GroupKey::Opening => match game.opening().or_else(|| game.eco()) {
+                    Some(opening) => opening,
+                    None => continue,
+                },
+                GroupKey::Speed => game.speed().to_string(),
+                GroupKey::Month => game.end_time().format("%Y-%m").to_string(),
+                GroupKey::Color => {
+                    if game.white().name().to_lowercase() == player {
+                        Color::White.to_string()
+                    } else {
+                        Color::Black.to_string()
+                    }
+                }
+                GroupKey::Termination => game.termination().to_string(),
+            };
+            groups
+                .entry(group)
+                .or_default()
+                .add(game.result_for(&player));
+        }
+        groups
+    }
+
+    /// Import the game searched by ID into lichess.org, from the PGN generated for it, as the
+    /// user of the OAuth token if one is given.
+    pub fn import_to_lichess(&self, token: Option<&str>) -> Result<ImportedGame, ChessError> {
+        let mut game = self.find_by_id()?;
+        let client = ChessClient::new(10, "lichess.org")?;
+        Ok(client.import_game(&game.pgn(), token)?)
+    }
+
+    /// The URLs of the player's monthly archives as chess.com lists them, before parsing.
+    pub fn find_archive_urls(&self) -> Result<Vec<String>, ChessError> {
+        if self.api != "chess.com" {
+            return Err(FinderError::UnsupportedApi(self.api.clone()).into());
+        }
+        let client = self.client()?;
+        let player = self.search.get_value();
+        log::info!("Getting game archives");
+        Ok(client.get_user_game_archives(player)?.archives)
+    }
+
+    /// Find the daily games the player is currently playing.
+    pub fn find_ongoing_by_player(&self) -> Result<Vec<OngoingGame>, ChessError> {
+        let client = self.client()?;
+        let player = self.search.get_value();
+        log::info!("Getting ongoing games");
+        let games = client.get_user_ongoing_games(player)?;
+
+        if games.is_empty() {
+            Err(ChessError::GameNotFoundError)
+        } else {
+            Ok(games)
+        }
+    }
+
+    /// Tournaments the player registered for, is playing, or played, in that order. Only
+    /// chess.com lists a player's tournaments.
+    pub fn find_tournaments_by_player(&self) -> Result<Vec<PlayerTournament>, ChessError> {
+        let client = self.client()?;
+        let player = self.search.get_value();
+        log::info!("Getting tournaments");
+        Ok(client.get_player_tournaments(player)?.all())
+    }
+
+    /// Whether the player is online and playing live chess (chess.com only).
+    pub fn find_status_of_player(&self) -> Result<PlayerStatus, ChessError> {
+        let client = self.client()?;
+        let player = self.search.get_value();
+        log::info!("Getting status");
+        Ok(client.get_player_status(player)?)
+    }
+
+    /// Fetch the raw response of the endpoint the search would use: the game for ID searches, or
+    /// the month of games (chess.com archives, lichess last game, if no month is set) for player
+    /// searches.
+    pub fn find_raw(&self) -> Result<String, ChessError> {
+        let client = self.client()?;
+        let kind = match &self.search {
+            Search::ID(id) => RawRequest::Game(id),
+            Search::Player(username) => match (self.year, self.month) {
+                (Some(year), Some(month)) => RawRequest::MonthGames {
+                    username,
+                    year: year as i32,
+                    month,
+                },
+                _ if self.api == "chess.com" => RawRequest::Archives(username),
+                _ => RawRequest::LastGames {
+                    username,
+                    max: Some(self.last.unwrap_or(1)),
+                },
+            },
+        };
+        Ok(client.get_raw(kind)?)
+    }
+
+    /// The opponent index of the searched player, if caching.
+    fn load_index(&self) -> Option<OpponentIndex> {
+        let dir = self.cache_dir.as_ref()?;
+        let path = OpponentIndex::path(dir, &self.api, self.search.get_value());
+        Some(OpponentIndex::load(&path))
+    }
+
+    fn save_index(&self, index: &Option<OpponentIndex>) {
+        if let (Some(dir), Some(index)) = (&self.cache_dir, index) {
+            let path = OpponentIndex::path(dir, &self.api, self.search.get_value());
+            if let Err(e) = index.save(&path) {
+                log::warn!("Could not save opponent cache {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Fetch the games of the player in a month, or None if the opponent index shows the month
+    /// has no games against the opponents searched. The opponents of months that are over are
+    /// recorded in the index.
+    fn month_games(
+        &self,
+        client: &ChessClient,
+        player: &str,
+        (year, month): (u32, u32),
+        index: &mut Option<OpponentIndex>,
+    ) -> Result<Option<Games>, ChessError> {
+        if self.can_skip_month(index, (year, month)) {
+            return Ok(None);
+        }
+
+        let mut games = client.get_user_month_games(player, year as i32, month)?;
+        self.index_month(index, (year, month), &mut games);
+        Ok(Some(games))
+    }
+
+    /// Like month_games, for a batch of months requested at the same time, each from its own
+    /// client paced together with client. The games are in the order of the months, and each month counts as a request in
+    /// the progress.
+    fn batch_month_games(
+        &self,
+        client: &ChessClient,
+        player: &str,
+        months: &[(u32, u32)],
+        index: &mut Option<OpponentIndex>,
+        progress: &mut Option<Progress>,
+    ) -> Result<Vec<Option<Games>>, ChessError> {
+        if months.len() == 1 {
+            log::info!("At {:?}/{:?}", months[0].1, months[0].0);
+            let games = self.timed(progress, || {
+                self.month_games(client, player, months[0], index)
+            })?;
+            return Ok(vec![games]);
+        }
+
+        let requested: Vec<(u32, u32)> = months
+            .iter()
+            .copied()
+            .filter(|month| !self.can_skip_month(index, *month))
+            .collect();
+        log::info!("At {:?}", requested);
+
+        let started = Instant::now();
+        let clients = (0..self.concurrency.min(requested.len()))
+            .map(|_| self.client().map(|c| c.share_pacer(client)))
+            .collect::<Result<Vec<ChessClient>, ChessError>>()?;
+        let responses = in_parallel(&requested, clients, |client, (year, month)| {
+            client.get_user_month_games(player, *year as i32, *month)
+        });
+        if let Some(p) = progress.as_mut() {
+            p.record_batch(months.len(), started.elapsed());
+        }
+
+        let mut fetched = HashMap::new();
+        for (month, games) in requested.into_iter().zip(responses) {
+            let mut games = games?;
+            self.index_month(index, month, &mut games);
+            fetched.insert(month, games);
+        }
+        Ok(months.iter().map(|month| fetched.remove(month)).collect())
+    }
+
+    /// Whether the index shows the player had no games against the opponents in the month.
+    fn can_skip_month(&self, index: &Option<OpponentIndex>, (year, month): (u32, u32)) -> bool {
+        match index {
+            Some(index) if index.can_skip((year, month), &self.opponents) => {
+                log::info!(
+                    "Skipping {}/{}, no games against the opponents",
+                    month,
+                    year
+                );
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record the opponents of a month of games in the index. The current month isn't recorded,
+    /// as more games may still be played in it.
+    fn index_month(
+        &self,
+        index: &mut Option<OpponentIndex>,
+        (year, month): (u32, u32),
+        games: &mut Games,
+    ) {
+        if let Some(index) = index.as_mut() {
+            if month_is_over((year, month), &Utc::now()) {
+                index.record((year, month), self.opponents_in(games));
+            }
+        }
+    }
+
+    /// Names, in lowercase, of the searched player's opponents in a month of games.
+    fn opponents_in(&self, games: &mut Games) -> Vec<String> {
+        match games {
+            Games::ChessDotCom(v) => v.iter_mut().map(|g| self.opponent_of(g)).collect(),
+            Games::LichessDotOrg(v) => v.iter_mut().map(|g| self.opponent_of(g)).collect(),
+        }
+    }
+
+    fn progress_of(&self, label: &str, total: usize) -> Option<Progress> {
+        if self.progress {
+            Some(Progress::new(label, total))
+        } else {
+            None
+        }
+    }
+
+    /// Run a request, recording how long it took in the progress if there is one.
+    fn timed<T, F: FnOnce() -> T>(&self, progress: &mut Option<Progress>, request: F) -> T {
+        if let Some(p) = progress.as_mut() {
+            p.start();
+        }
+        let result = request();
+        if let Some(p) = progress.as_mut() {
+            p.finish();
+        }
+        result
+    }
+
+    /// Months to request, newest first, when scanning by year on APIs without archives.
+    /// Months after now are skipped.
+    fn month_windows(&self, now: DateTime<Utc>) -> Vec<(u32, u32)> {
+        let year = match self.year {
+            Some(y) => y,
+            None => now.year() as u32,
+        };
+        (1..=12)
+            .rev()
+            .filter(|m| match self.month {
+                Some(month) => month == *m,
+                None => true,
+            })
+            .filter(|m| (year, *m) <= (now.year() as u32, now.month()))
+            .filter(|m| self.month_in_range((year, *m)))
+            .map(|m| (year, m))
+            .collect()
+    }
+
+    /// Whether any day of the month falls between from and to.
+    fn month_in_range(&self, (year, month): (u32, u32)) -> bool {
+        let after_from = match self.from {
+            Some(from) => (year, month) >= (from.year() as u32, from.month()),
+            None => true,
+        };
+        let before_to = match self.to {
+            Some(to) => (year, month) <= (to.year() as u32, to.month()),
+            None => true,
+        };
+        after_from && before_to
+    }
+
+    fn year_month_archives(&self, game_archives: GameArchives) -> Vec<(u32, u32)> {
+        let mut archives = game_archives
+            .months()
+            .into_iter()
+            .filter(|&(y, m)| match self.year {
+                Some(year) => match self.month {
+                    Some(month) => year == y && month == m,
+                    None => year == y,
+                },
+                None => match self.month {
+                    Some(month) => month == m,
+                    None => true,
+                },
+            })
+            .filter(|&month| self.month_in_range(month))
+            .collect::<Vec<(u32, u32)>>();
+        archives.reverse();
+        archives
+    }
+
+    /// The value of the dedupe key for a game. Games without one, e.g. without a known opening,
+    /// are never collapsed.
+    fn dedupe_key(&self, g: &mut impl ChessGame) -> Option<String> {
+        match self.dedupe_by? {
+            DedupeKey::Opponent => Some(self.opponent_of(g)),
+            DedupeKey::Opening => g.eco().or_else(|| g.opening()),
+            DedupeKey::Day => Some(g.end_time().date().to_string()),
+        }
+    }
+
+    /// Whether a game is the first, i.e. most recent, seen with its dedupe key.
+    fn first_of_key(&self, seen: &mut HashSet<String>, g: &mut impl ChessGame) -> bool {
+        match self.dedupe_key(g) {
+            Some(key) => seen.insert(key),
+            None => true,
+        }
+    }
+
+    fn check_game_found(&self, g: &mut impl DisplayableChessGame) -> bool {
+        self.players_had_correct_colors(g)
+            && self.played_on_expected_day(g)
+            && self.played_in_date_range(g)
+            && self.lasted_expected_duration(g)
+            && self.played_expected_opponent(g)
+            && self.played_titled_opponent(g)
+            && self.played_opponent_in_rating_range(g)
+            && self.had_expected_rating_change(g)
+            && self.had_established_ratings(g)
+            && self.played_expected_eco(g)
+            && self.had_expected_result(g)
+            && self.had_expected_ending(g)
+            && self.played_expected_speed(g)
+            && self.played_expected_time_class(g)
+            && self.played_expected_variant(g)
+    }
+
+    /// Name, in lowercase, of the searched player's opponent in a game.
+    fn opponent_of(&self, g: &mut impl ChessGame) -> String {
+        self.opponent_name_of(g).to_lowercase()
+    }
+
+    /// Name of the searched player's opponent in a game, as the API spells it.
+    fn opponent_name_of(&self, g: &mut impl ChessGame) -> String {
+        let player = self.search.get_value().to_lowercase();
+        let white = g.white().name();
+        let black = g.black().name();
+        if white.to_lowercase() == player {
+            black
+        } else {
+            white
+        }
+    }
+
+    fn played_expected_opponent(&self, g: &mut impl DisplayableChessGame) -> bool {
+        if self.opponents.is_empty() {
+            return true;
+        }
+        let opponent = if self.exact_opponent {
+            self.opponent_name_of(g)
+        } else {
+            self.opponent_of(g)
+        };
+        self.opponents.iter().any(|o| o == &opponent)
+    }
+
+    /// Whether the opponent had a title, or one of the titles searched. Games from chess.com
+    /// archives have no titles, so none of them are found.
+    fn played_titled_opponent(&self, g: &mut impl DisplayableChessGame) -> bool {
+        let titles = match &self.opponent_titles {
+            Some(titles) => titles,
+            None => return true,
+        };
+        let player = self.search.get_value().to_lowercase();
+        let white = g.white();
+        let title = if white.name().to_lowercase() == player {
+            g.black().title()
+        } else {
+            white.title()
+        };
+        match title {
+            Some(title) => titles.is_empty() || titles.contains(&title.to_uppercase()),
+            None => false,
+        }
+    }
+
+    fn played_opponent_in_rating_range(&self, g: &mut impl DisplayableChessGame) -> bool {
+        if self.min_rating.is_none() && self.max_rating.is_none() {
+            return true;
+        }
+        let player = self.search.get_value().to_lowercase();
+        let white = g.white();
+        let rating = if white.name().to_lowercase() == player {
+            g.black().rating()
+        } else {
+            white.rating()
+        };
+        let rating = match rating {
+            Some(rating) => rating,
+            None => return false,
+        };
+        let high_enough = match self.min_rating {
+            Some(min) => rating >= min,
+            None => true,
+        };
+        let low_enough = match self.max_rating {
+            Some(max) => rating <= max,
+            None => true,
+        };
+        high_enough && low_enough
+    }
+
+    fn had_expected_rating_change(&self, g: &mut impl DisplayableChessGame) -> bool {
+        if self.min_rating_gain.is_none() && self.max_rating_loss.is_none() {
+            return true;
+        }
+        let change = match g.rating_change(self.search.get_value()) {
+            Some(change) => change,
+            None => return false,
+        };
+        let gained_enough = match self.min_rating_gain {
+            Some(gain) => change >= gain as i32,
+            None => true,
+        };
+        let lost_enough = match self.max_rating_loss {
+            Some(loss) => change <= -(loss as i32),
+            None => true,
+        };
+        gained_enough && lost_enough
+    }
+
+    fn had_established_ratings(&self, g: &mut impl DisplayableChessGame) -> bool {
+        !self.exclude_provisional || !(g.white().is_provisional() || g.black().is_provisional())
+    }
+
+    fn played_expected_eco(&self, g: &mut impl DisplayableChessGame) -> bool {
+        if self.ecos.is_empty() {
+            return true;
+        }
+        match g.eco() {
+            Some(eco) => self.ecos.iter().any(|e| eco.starts_with(e.as_str())),
+            None => false,
+        }
+    }
+
+    fn had_expected_result(&self, g: &mut impl DisplayableChessGame) -> bool {
+        if self.results.is_empty() {
+            return true;
+        }
+        match g.result_for(self.search.get_value()) {
+            Some(outcome) => self.results.contains(&outcome),
+            None => false,
+        }
+    }
+
+    fn had_expected_ending(&self, g: &mut impl DisplayableChessGame) -> bool {
+        let checkmate = match self.checkmate {
+            Some(checkmate) => g.is_checkmate() == checkmate,
+            None => true,
+        };
+        let stalemate = match self.stalemate {
+            Some(stalemate) => g.is_stalemate() == stalemate,
+            None => true,
+        };
+        checkmate && stalemate
+    }
+
+    /// Whether the game was played at the expected speed. lichess already filters games by
+    /// speed, but chess.com games are only filtered here.
+    fn played_expected_speed(&self, g: &mut impl DisplayableChessGame) -> bool {
+        match self.speed {
+            Some(speed) => g.speed() == speed,
+            None => true,
+        }
+    }
+
+    fn played_expected_time_class(&self, g: &mut impl DisplayableChessGame) -> bool {
+        match self.time_class {
+            Some(time_class) => g.time_class() == Some(time_class),
+            None => true,
+        }
+    }
+
+    fn played_expected_variant(&self, g: &mut impl DisplayableChessGame) -> bool {
+        match &self.variant {
+            Some(variant) => g.variant().eq_ignore_ascii_case(variant),
+            None => true,
+        }
+    }
+
+    /// Whether the game lasted between min_duration and max_duration. Games without a start
+    /// time are excluded when either bound is set.
+    fn lasted_expected_duration(&self, g: &mut impl DisplayableChessGame) -> bool {
+        if self.min_duration.is_none() && self.max_duration.is_none() {
+            return true;
+        }
+
+        let duration = match g.start_time() {
+            Some(start) => g.end_time() - start,
+            None => return false,
+        };
+        let long_enough = match self.min_duration {
+            Some(min) => duration >= min,
+            None => true,
+        };
+        let short_enough = match self.max_duration {
+            Some(max) => duration <= max,
+            None => true,
+        };
+        long_enough && short_enough
+    }
+
+    fn played_on_expected_day(&self, g: &mut impl DisplayableChessGame) -> bool {
+        match self.day {
+            Some(d) => g.end_time().day() == d,
+            None => true,
+        }
+    }
+
+    fn played_in_date_range(&self, g: &mut impl DisplayableChessGame) -> bool {
+        let date = g.end_time().date().naive_utc();
+        let after_from = match self.from {
+            Some(from) => date >= from,
+            None => true,
+        };
+        let before_to = match self.to {
+            Some(to) => date <= to,
+            None => true,
+        };
+        after_from && before_to
+    }
+
+    fn players_had_correct_colors(&self, g: &mut impl DisplayableChessGame) -> bool {
+        let player = self.search.get_value();
+
+        match self.pieces {
+            Some(pieces) => &name_of(g, Color::from(pieces)) == player,
+            None => true,
+        }
+    }
+}
+
+/// Name, in lowercase, of the player of a game with the given color.
+fn name_of(g: &mut impl ChessGame, color: Color) -> String {
+    match color {
+        Color::White => g.white().name().to_lowercase(),
+        Color::Black => g.black().name().to_lowercase(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use serde_json::json;
+
+    fn lichess_game(white: &str, black: &str, eco: &str, last_move_at: i64) -> Game {
+        Game::LichessDotOrg(fixtures::lichess_game_with(json!({
+            "createdAt": last_move_at,
+            "lastMoveAt": last_move_at,
+            "winner": null,
+            "players": fixtures::lichess_players(white, black),
+            "opening": {"eco": eco, "name": "", "ply": 2},
+            "pgn": "",
+            "moves": "e4 e5"
+        })))
+    }
+
+    #[test]
+    fn test_dedupe_keeps_first_of_each_key() {
+        let day = 86_400_000;
+        let mut games = [
+            lichess_game("alice", "bob", "B20", 3 * day),
+            lichess_game("carol", "alice", "B20", 2 * day + 1),
+            lichess_game("bob", "alice", "C60", 2 * day),
+            lichess_game("alice", "carol", "C60", day),
+        ];
+
+        let mut kept = |key: DedupeKey| {
+            let mut finder = GameFinder::by_player("Alice", "lichess.org");
+            finder.dedupe_by(key);
+            let mut seen = HashSet::new();
+            games
+                .iter_mut()
+                .map(|g| finder.first_of_key(&mut seen, g))
+                .collect::<Vec<bool>>()
+        };
+
+        assert_eq!(kept(DedupeKey::Opponent), vec![true, true, false, false]);
+        assert_eq!(kept(DedupeKey::Opening), vec![true, false, true, false]);
+        assert_eq!(kept(DedupeKey::Day), vec![true, true, false, true]);
+    }
+
+    #[test]
+    fn test_group_tallies_results() {
+        let day = 86_400_000;
+        let mut games = vec![
+            lichess_game("alice", "bob", "B20", 40 * day),
+            lichess_game("bob", "alice", "C60", 2 * day),
+            lichess_game("alice", "carol", "C60", day),
+        ];
+        if let Game::LichessDotOrg(g) = &mut games[0] {
+            g.winner = Some(Color::White);
+        }
+        if let Game::LichessDotOrg(g) = &mut games[1] {
+            g.winner = Some(Color::White);
+        }
+        let finder = GameFinder::by_player("Alice", "lichess.org");
+
+        let by_opponent = finder.group(&mut games, GroupKey::Opponent);
+        assert_eq!(
+            by_opponent.keys().collect::<Vec<&String>>(),
+            vec!["bob", "carol"]
+        );
+        assert_eq!(
+            by_opponent["bob"],
+            Tally {
+                games: 2,
+                wins: 1,
+                losses: 1,
+                draws: 0
+            }
+        );
+        assert_eq!(by_opponent["carol"].draws, 1);
+
+        let by_color = finder.group(&mut games, GroupKey::Color);
+        assert_eq!(by_color["white"].games, 2);
+        assert_eq!(by_color["black"].losses, 1);
+
+        let by_month = finder.group(&mut games, GroupKey::Month);
+        assert_eq!(
+            by_month.keys().collect::<Vec<&String>>(),
+            vec!["1970-01", "1970-02"]
+        );
+
+        let by_termination = finder.group(&mut games, GroupKey::Termination);
+        assert_eq!(
+            by_termination.keys().collect::<Vec<&String>>(),
+            vec!["Resignation"]
+        );
+    }
+
+    #[test]
+    fn test_games_without_an_opening() {
+        // lichess sends no opening for games too short to reach one
+        let short_game = || match lichess_game("alice", "bob", "", 0) {
+            Game::LichessDotOrg(mut g) => {
+                g.opening = None;
+                g.moves = "e4 e5 Qh5".to_string();
+                g.pgn = "1. e4 e5 2. Qh5 *".to_string();
+                Game::LichessDotOrg(g)
+            }
+            g => panic!("expected a lichess game, got {:?}", g),
+        };
+        let mut game = short_game();
+        assert_eq!(game.eco(), None);
+        assert_eq!(game.opening(), None);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_expected_eco(&mut game));
+        finder.eco("C20");
+        assert!(!finder.played_expected_eco(&mut game));
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.dedupe_by(DedupeKey::Opening);
+        let mut seen = HashSet::new();
+        assert!(finder.first_of_key(&mut seen, &mut game));
+        assert!(finder.first_of_key(&mut seen, &mut short_game()));
+
+        let mut games = vec![short_game(), lichess_game("alice", "bob", "B20", 0)];
+        let by_opening = finder.group(&mut games, GroupKey::Opening);
+        assert_eq!(
+            by_opening.keys().collect::<Vec<&String>>(),
+            vec!["Sicilian"]
+        );
+        assert_eq!(finder.group(&mut games, GroupKey::Opponent)["bob"].games, 2);
+    }
+
+    #[test]
+    fn test_played_expected_speed_and_variant() {
+        let mut game = lichess_game("alice", "bob", "B20", 0);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_expected_speed(&mut game));
+        assert!(finder.played_expected_variant(&mut game));
+        finder.speed(Speed::Blitz).variant("standard");
+        assert!(finder.check_game_found(&mut game));
+        finder.speed(Speed::Rapid);
+        assert!(!finder.played_expected_speed(&mut game));
+        finder.variant("chess960");
+        assert!(!finder.played_expected_variant(&mut game));
+    }
+
+    #[test]
+    fn test_exact_opponent() {
+        let mut game = lichess_game("alice", "Anonymous", "B20", 0);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.opponent("anonymous");
+        assert!(finder.played_expected_opponent(&mut game));
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.exact_opponent().opponent("anonymous");
+        assert!(!finder.played_expected_opponent(&mut game));
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.exact_opponent().opponent("Anonymous");
+        assert!(finder.played_expected_opponent(&mut game));
+    }
+
+    #[test]
+    fn test_played_titled_opponent() {
+        let mut untitled = lichess_game("alice", "bob", "B20", 0);
+        let mut titled = lichess_game("carol", "alice", "B20", 0);
+        if let Game::LichessDotOrg(g) = &mut titled {
+            g.players.white.user.as_mut().unwrap().title = Some("IM".to_string());
+        }
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_titled_opponent(&mut untitled));
+        finder.titled();
+        assert!(!finder.played_titled_opponent(&mut untitled));
+        assert!(finder.played_titled_opponent(&mut titled));
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.opponent_title("gm");
+        assert!(!finder.played_titled_opponent(&mut titled));
+        finder.opponent_title("im");
+        assert!(finder.played_titled_opponent(&mut titled));
+
+        // The searched player's own title doesn't count
+        let mut finder = GameFinder::by_player("carol", "lichess.org");
+        finder.titled();
+        assert!(!finder.played_titled_opponent(&mut titled));
+    }
+
+    #[test]
+    fn test_only_game() {
+        let alice = lichess_game("alice", "bob", "B20", 0);
+        let carol = lichess_game("carol", "alice", "B20", 0);
+        match only_game(vec![alice.clone(), carol]) {
+            Err(ChessError::AmbiguousMatch { count }) => assert_eq!(count, 2),
+            r => panic!("expected an ambiguous match, got {:?}", r),
+        }
+        assert_eq!(only_game(vec![alice]).unwrap().white().name(), "alice");
+        assert!(matches!(
+            only_game(Vec::new()),
+            Err(ChessError::GameNotFoundError)
+        ));
+    }
+
+    #[test]
+    fn test_played_opponent_in_rating_range() {
+        let rated = |rating: &str| {
+            let mut game = lichess_game("alice", "bob", "B20", 0);
+            if let Game::LichessDotOrg(g) = &mut game {
+                g.players.black = serde_json::from_str(&format!(
+                    r#"{{"user": {{"name": "bob", "id": "bob"}}{}}}"#,
+                    rating
+                ))
+                .unwrap();
+                g.players.white.rating = Some(1000);
+            }
+            game
+        };
+        let mut inside = rated(r#", "rating": 2100"#);
+        let mut below = rated(r#", "rating": 1800"#);
+        let mut unrated = rated("");
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_opponent_in_rating_range(&mut unrated));
+        finder.min_rating(2000).max_rating(2200);
+        assert!(finder.played_opponent_in_rating_range(&mut inside));
+        assert!(!finder.played_opponent_in_rating_range(&mut below));
+        assert!(!finder.played_opponent_in_rating_range(&mut unrated));
+
+        // The searched player's own rating doesn't count
+        let mut finder = GameFinder::by_player("bob", "lichess.org");
+        finder.min_rating(2000);
+        assert!(!finder.played_opponent_in_rating_range(&mut inside));
+
+        finder.max_rating(1500);
+        assert!(finder.validate().is_err());
+    }
+
+    #[test]
+    fn test_had_expected_rating_change() {
+        let changed = |diff: Option<i32>| {
+            let mut game = lichess_game("alice", "bob", "B20", 0);
+            if let Game::LichessDotOrg(g) = &mut game {
+                g.players.white.rating_diff = diff;
+                g.players.black.rating_diff = diff.map(|d| -d);
+            }
+            game
+        };
+        let mut gained = changed(Some(12));
+        let mut lost = changed(Some(-9));
+        let mut unrated = changed(None);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.had_expected_rating_change(&mut unrated));
+        finder.min_rating_gain(10);
+        assert!(finder.had_expected_rating_change(&mut gained));
+        assert!(!finder.had_expected_rating_change(&mut lost));
+        assert!(!finder.had_expected_rating_change(&mut unrated));
+        finder.min_rating_gain(15);
+        assert!(!finder.had_expected_rating_change(&mut gained));
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.max_rating_loss(5);
+        assert!(finder.had_expected_rating_change(&mut lost));
+        assert!(!finder.had_expected_rating_change(&mut gained));
+        assert!(!finder.had_expected_rating_change(&mut unrated));
+
+        // The change is the searched player's, not their opponent's
+        let mut finder = GameFinder::by_player("bob", "lichess.org");
+        finder.max_rating_loss(10);
+        assert!(finder.had_expected_rating_change(&mut gained));
+    }
+
+    #[test]
+    fn test_exclude_provisional() {
+        let mut established = lichess_game("alice", "bob", "B20", 0);
+        let mut provisional = lichess_game("alice", "bob", "B20", 0);
+        if let Game::LichessDotOrg(g) = &mut provisional {
+            g.players.black = serde_json::from_str(
+                r#"{"user": {"name": "bob", "id": "bob"}, "rating": 1500, "provisional": true}"#,
+            )
+            .unwrap();
+        }
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.had_established_ratings(&mut provisional));
+        finder.exclude_provisional();
+        assert!(finder.had_established_ratings(&mut established));
+        assert!(!finder.had_established_ratings(&mut provisional));
+    }
+
+    #[test]
+    fn test_players_had_correct_colors() {
+        let mut game = lichess_game("alice", "bob", "B20", 0);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.players_had_correct_colors(&mut game));
+        finder.white();
+        assert!(finder.players_had_correct_colors(&mut game));
+        finder.black();
+        assert!(!finder.players_had_correct_colors(&mut game));
+
+        let mut finder = GameFinder::by_player("bob", "lichess.org");
+        finder.black();
+        assert!(finder.players_had_correct_colors(&mut game));
+        finder.white();
+        assert!(!finder.players_had_correct_colors(&mut game));
+    }
+
+    #[test]
+    fn test_any_opponent_matches() {
+        let mut game = lichess_game("alice", "bob", "B20", 0);
+
+        let mut finder = GameFinder::by_player("bob", "lichess.org");
+        assert!(finder.played_expected_opponent(&mut game));
+        finder.opponent("carol");
+        assert!(!finder.played_expected_opponent(&mut game));
+        finder.opponent("Alice");
+        assert!(finder.played_expected_opponent(&mut game));
+    }
+
+    #[test]
+    fn test_any_eco_matches() {
+        let mut sicilian = lichess_game("alice", "bob", "B20", 0);
+        let mut caro_kann = lichess_game("alice", "bob", "B12", 0);
+        let mut ruy_lopez = lichess_game("alice", "bob", "C60", 0);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_expected_eco(&mut ruy_lopez));
+        finder.eco("b2").eco("B12");
+        assert!(finder.played_expected_eco(&mut sicilian));
+        assert!(finder.played_expected_eco(&mut caro_kann));
+        assert!(!finder.played_expected_eco(&mut ruy_lopez));
+    }
+
+    #[test]
+    fn test_any_result_matches() {
+        let mut game = lichess_game("alice", "bob", "B20", 0);
+        if let Game::LichessDotOrg(g) = &mut game {
+            g.winner = Some(Color::White);
+        }
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.had_expected_result(&mut game));
+        finder.result(Outcome::Loss);
+        assert!(!finder.had_expected_result(&mut game));
+        finder.result(Outcome::Win);
+        assert!(finder.had_expected_result(&mut game));
+
+        let mut finder = GameFinder::by_player("bob", "lichess.org");
+        finder.result(Outcome::Win).result(Outcome::Draw);
+        assert!(!finder.had_expected_result(&mut game));
+    }
+
+    #[test]
+    fn test_had_expected_ending() {
+        let mut mate = lichess_game("alice", "bob", "B20", 0);
+        let mut stalemate = lichess_game("alice", "bob", "B20", 0);
+        if let Game::LichessDotOrg(g) = &mut mate {
+            g.status = "mate".to_string();
+        }
+        if let Game::LichessDotOrg(g) = &mut stalemate {
+            g.status = "stalemate".to_string();
+        }
+        let mut resigned = lichess_game("alice", "bob", "B20", 0);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.had_expected_ending(&mut resigned));
+        finder.checkmate(true).white();
+        assert!(finder.check_game_found(&mut mate));
+        assert!(!finder.check_game_found(&mut stalemate));
+        assert!(!finder.check_game_found(&mut resigned));
+        finder.black();
+        assert!(!finder.check_game_found(&mut mate));
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.stalemate(true);
+        assert!(!finder.had_expected_ending(&mut mate));
+        assert!(finder.had_expected_ending(&mut stalemate));
+        finder.stalemate(false);
+        assert!(finder.had_expected_ending(&mut resigned));
+    }
+
+    #[test]
+    fn test_played_expected_time_class() {
+        let mut blitz = lichess_game("alice", "bob", "B20", 0);
+        let mut correspondence = lichess_game("alice", "bob", "B20", 0);
+        if let Game::LichessDotOrg(g) = &mut correspondence {
+            g.speed = "correspondence".to_string();
+        }
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_expected_time_class(&mut correspondence));
+        finder.time_class(TimeClass::Blitz);
+        assert!(finder.played_expected_time_class(&mut blitz));
+        assert!(!finder.played_expected_time_class(&mut correspondence));
+        finder.time_class(TimeClass::Rapid);
+        assert!(!finder.played_expected_time_class(&mut blitz));
+    }
+
+    #[test]
+    fn test_lasted_expected_duration() {
+        let mut game = lichess_game("alice", "bob", "B20", 0);
+        if let Game::LichessDotOrg(g) = &mut game {
+            g.last_move_at = g.created_at + Duration::minutes(30);
+        }
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.lasted_expected_duration(&mut game));
+        finder.min_duration(Duration::minutes(10));
+        assert!(finder.lasted_expected_duration(&mut game));
+        finder.max_duration(Duration::minutes(20));
+        assert!(!finder.lasted_expected_duration(&mut game));
+        finder
+            .min_duration(Duration::hours(1))
+            .max_duration(Duration::hours(2));
+        assert!(!finder.lasted_expected_duration(&mut game));
+
+        // lichess sends timestamps in milliseconds, this game lasted 442 seconds
+        let mut json = match lichess_game("alice", "bob", "B20", 0) {
+            Game::LichessDotOrg(g) => serde_json::to_value(g).unwrap(),
+            _ => unreachable!(),
+        };
+        json["created_at"] = 1514505150384i64.into();
+        json["last_move_at"] = 1514505592843i64.into();
+        let mut game = Game::LichessDotOrg(serde_json::from_value(json).unwrap());
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder
+            .min_duration(Duration::minutes(7))
+            .max_duration(Duration::minutes(8));
+        assert!(finder.lasted_expected_duration(&mut game));
+    }
+
+    #[test]
+    fn test_validate_rejects_reversed_ranges() {
+        let mut finder = GameFinder::by_player("a_player", "chess.com");
+        finder
+            .from(NaiveDate::from_ymd(2021, 3, 1))
+            .to(NaiveDate::from_ymd(2021, 3, 1));
+        assert!(finder.validate().is_ok());
+
+        finder.to(NaiveDate::from_ymd(2021, 2, 28));
+        match finder.validate() {
+            Err(ChessError::ValidationError(reason)) => {
+                assert_eq!(reason, "from date 2021-03-01 is after to date 2021-02-28")
+            }
+            r => panic!("expected a validation error, got {:?}", r),
+        }
+
+        let mut finder = GameFinder::by_player("a_player", "chess.com");
+        finder
+            .min_duration(Duration::hours(1))
+            .max_duration(Duration::minutes(5));
+        assert!(finder.validate().is_err());
+    }
+
+    #[test]
+    fn test_warnings_for_future_dates() {
+        let now = Utc.ymd(2021, 3, 15).and_hms(0, 0, 0);
+        let mut finder = GameFinder::by_player("a_player", "chess.com");
+        finder.year(2021).month(3);
+        assert!(finder.warnings(now).is_empty());
+
+        finder.month(4);
+        assert_eq!(
+            finder.warnings(now),
+            vec!["4/2021 is in the future, no games can exist yet"]
+        );
+
+        finder.year(2022);
+        assert_eq!(
+            finder.warnings(now),
+            vec!["2022 is in the future, no games can exist yet"]
+        );
+    }
+
+    #[test]
+    fn test_warnings_for_titles_on_chess_com() {
+        let now = Utc.ymd(2021, 3, 15).and_hms(0, 0, 0);
+        let mut finder = GameFinder::by_player("a_player", "lichess.org");
+        finder.titled();
+        assert!(finder.warnings(now).is_empty());
+
+        let mut finder = GameFinder::by_player("a_player", "chess.com");
+        finder.opponent_title("GM");
+        assert_eq!(finder.warnings(now).len(), 1);
+    }
+
+    #[test]
+    fn test_warnings_for_rating_changes_on_chess_com() {
+        let now = Utc.ymd(2021, 3, 15).and_hms(0, 0, 0);
+        let mut finder = GameFinder::by_player("a_player", "lichess.org");
+        finder.min_rating_gain(10);
+        assert!(finder.warnings(now).is_empty());
+
+        let mut finder = GameFinder::by_player("a_player", "chess.com");
+        finder.max_rating_loss(10);
+        assert_eq!(
+            finder.warnings(now),
+            vec!["chess.com archives don't include rating changes, no chess.com game will be found by rating gain or loss"]
+        );
+    }
+
+    #[test]
+    fn test_played_in_date_range() {
+        let day = 86_400_000;
+        let mut game = lichess_game("alice", "bob", "B20", 10 * day);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        assert!(finder.played_in_date_range(&mut game));
+        finder.from(NaiveDate::from_ymd(1970, 1, 11));
+        assert!(finder.played_in_date_range(&mut game));
+        finder.to(NaiveDate::from_ymd(1970, 1, 11));
+        assert!(finder.played_in_date_range(&mut game));
+        finder.from(NaiveDate::from_ymd(1970, 1, 12));
+        assert!(!finder.played_in_date_range(&mut game));
+        assert!(!finder.month_in_range((1969, 12)));
+        assert!(finder.month_in_range((1970, 1)));
+    }
+
+    #[test]
+    fn test_week_spanning_months() {
+        let mut finder = GameFinder::by_player("alice", "chess.com");
+        finder.week_of(NaiveDate::from_ymd(2021, 1, 1));
+        assert_eq!(finder.from, Some(NaiveDate::from_ymd(2020, 12, 28)));
+        assert_eq!(finder.to, Some(NaiveDate::from_ymd(2021, 1, 3)));
+
+        finder.week_of(NaiveDate::from_ymd(2021, 5, 31));
+        assert_eq!(finder.from, Some(NaiveDate::from_ymd(2021, 5, 31)));
+        assert_eq!(finder.to, Some(NaiveDate::from_ymd(2021, 6, 6)));
+
+        let archives = GameArchives {
+            archives: ["2021/04", "2021/05", "2021/06", "2021/07"]
+                .iter()
+                .map(|m| format!("https://api.chess.com/pub/player/alice/games/{}", m))
+                .collect(),
+        };
+        assert_eq!(
+            finder.year_month_archives(archives),
+            vec![(2021, 6), (2021, 5)]
+        );
+
+        let day = 86_400_000;
+        let may_31 = 1_622_419_200_000;
+        let mut game = lichess_game("alice", "bob", "B20", may_31 - day);
+        assert!(!finder.played_in_date_range(&mut game));
+        let mut game = lichess_game("alice", "bob", "B20", may_31 + 6 * day);
+        assert!(finder.played_in_date_range(&mut game));
+        let mut game = lichess_game("alice", "bob", "B20", may_31 + 7 * day);
+        assert!(!finder.played_in_date_range(&mut game));
+    }
+
+    #[test]
+    fn test_month_windows() {
+        let now = Utc.ymd(2021, 3, 15).and_hms(0, 0, 0);
+        let mut finder = GameFinder::by_player("a_player", "lichess.org");
+        finder.year(2021);
+        assert_eq!(
+            finder.month_windows(now),
+            vec![(2021, 3), (2021, 2), (2021, 1)]
+        );
+
+        finder.year(2020).month(11);
+        assert_eq!(finder.month_windows(now), vec![(2020, 11)]);
+    }
+
+    #[test]
+    fn test_games_the_player_did_not_play_are_never_returned_when_strict() {
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        let mut game = lichess_game("carol", "dave", "B20", 10);
+        assert!(finder.check_played_by_player(&mut game).is_ok());
+
+        finder.strict();
+        assert_eq!(
+            finder.check_played_by_player(&mut game),
+            Err(FinderError::NotPlayersGame {
+                player: "alice".to_string(),
+                white: "carol".to_string(),
+                black: "dave".to_string(),
+            })
+        );
+        let mut game = lichess_game("carol", "Alice", "B20", 10);
+        assert!(finder.check_played_by_player(&mut game).is_ok());
+    }
+
+    #[test]
+    fn test_limit_keeps_the_most_recent_games() {
+        // A mock archive of a lichess month, newest first
+        let archive: Vec<lichessdotorg::Game> = (0..4)
+            .map(|i| match lichess_game("alice", "bob", "B20", 10 - i) {
+                Game::LichessDotOrg(mut g) => {
+                    g.id = format!("game{}", 4 - i);
+                    g
+                }
+                g => panic!("expected a lichess game, got {:?}", g),
+            })
+            .collect();
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.limit(2);
+        let mut found = Vec::new();
+        let (mut seen, mut visited) = (HashSet::new(), 0);
+        let scanned = finder.visit_archive(
+            archive,
+            Game::LichessDotOrg,
+            &mut seen,
+            &mut visited,
+            &mut collector(&mut found, finder.limit),
+        );
+
+        assert_eq!(scanned, Scanned::Stopped);
+        let urls: Vec<String> = oldest_first(found).iter().map(|g| g.url()).collect();
+        assert_eq!(
+            urls,
+            vec!["https://lichess.org/game3", "https://lichess.org/game4"]
+        );
+    }
+
+    #[test]
+    fn test_lichess_max_is_only_requested_without_filters_after_the_request() {
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder
+            .from(NaiveDate::from_ymd(2021, 1, 1))
+            .speed(Speed::Blitz);
+        assert_eq!(finder.lichess_max(Some(5)), Some(5));
+        finder.variant("chess960");
+        assert_eq!(finder.lichess_max(Some(5)), None);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.exclude_provisional();
+        assert_eq!(finder.lichess_max(Some(5)), None);
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.white();
+        assert_eq!(finder.lichess_max(Some(5)), None);
+    }
+
+    #[test]
+    fn test_latest_game_is_the_latest_that_matches() {
+        // A mock archive of a lichess month, newest first, where the newest game is provisional
+        let archive: Vec<lichessdotorg::Game> = ["provisional", "established"]
+            .iter()
+            .enumerate()
+            .map(
+                |(i, id)| match lichess_game("alice", "bob", "B20", 10 - i as i64) {
+                    Game::LichessDotOrg(mut g) => {
+                        g.id = id.to_string();
+                        g.players.black.provisional = *id == "provisional";
+                        g
+                    }
+                    g => panic!("expected a lichess game, got {:?}", g),
+                },
+            )
+            .collect();
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.exclude_provisional();
+        let mut found = Vec::new();
+        let (mut seen, mut visited) = (HashSet::new(), 0);
+        finder.visit_archive(
+            archive,
+            Game::LichessDotOrg,
+            &mut seen,
+            &mut visited,
+            &mut collector(&mut found, Some(1)),
+        );
+
+        let urls: Vec<String> = found.iter().map(|g| g.url()).collect();
+        assert_eq!(urls, vec!["https://lichess.org/established"]);
+        assert_eq!(finder.lichess_max(Some(1)), None);
+    }
+
+    #[test]
+    fn test_scan_stops_at_since_game() {
+        // A mock archive of a lichess month, newest first
+        let archive = || {
+            ["newest", "newer", "since", "older"]
+                .iter()
+                .enumerate()
+                .map(
+                    |(i, id)| match lichess_game("alice", "bob", "B20", 10 - i as i64) {
+                        Game::LichessDotOrg(mut g) => {
+                            g.id = id.to_string();
+                            g
+                        }
+                        g => panic!("expected a lichess game, got {:?}", g),
+                    },
+                )
+                .collect::<Vec<lichessdotorg::Game>>()
+        };
+        let scan = |finder: &GameFinder| {
+            let (mut seen, mut visited, mut ids) = (HashSet::new(), 0, Vec::new());
+            let scanned = finder.visit_archive(
+                archive(),
+                Game::LichessDotOrg,
+                &mut seen,
+                &mut visited,
+                &mut |g: Game| {
+                    ids.push(g.url());
+                    true
+                },
+            );
+            (scanned, visited, ids)
+        };
+
+        let mut finder = GameFinder::by_player("alice", "lichess.org");
+        finder.since_game_id("https://lichess.org/since/");
+        assert_eq!(
+            scan(&finder),
+            (
+                Scanned::ReachedSinceGame,
+                2,
+                vec![
+                    "https://lichess.org/newest".to_string(),
+                    "https://lichess.org/newer".to_string()
+                ]
+            )
+        );
+
+        finder.since_game_id("missing");
+        let (scanned, visited, _) = scan(&finder);
+        assert_eq!((scanned, visited), (Scanned::More, 4));
+    }
+
+    #[test]
+    fn test_merge_keeps_the_games_of_the_api_that_succeeded() {
+        let day = 86_400_000;
+        // chess.com is down, while lichess.org has games
+        let collect = |finder: &GameFinder| match finder.api.as_str() {
+            "chess.com" => Err(ChessError::ValidationError("chess.com is down".to_string())),
+            _ => Ok(vec![
+                lichess_game("alice", "bob", "B20", 2 * day),
+                lichess_game("alice", "carol", "C60", day),
+            ]),
+        };
+        let apis = ["chess.com", "lichess.org"];
+
+        let mut finder = GameFinder::by_player("alice", "chess.com");
+        let games = finder.merge_from(&apis, 5, collect).unwrap();
+        assert_eq!(games.len(), 2);
+
+        finder.fail_fast();
+        match finder.merge_from(&apis, 5, collect) {
+            Err(ChessError::ValidationError(reason)) => assert_eq!(reason, "chess.com is down"),
+            r => panic!("expected the chess.com error, got {:?}", r),
+        }
+
+        let finder = GameFinder::by_player("alice", "chess.com");
+        assert!(finder.merge_from(&apis[..1], 5, collect).is_err());
+    }
+
+    #[test]
+    fn test_games_of_the_wrong_api_are_an_error() {
+        assert_eq!(
+            chess_dot_com_games(Games::LichessDotOrg(Vec::new())).unwrap_err(),
+            FinderError::UnexpectedGames {
+                expected: "chess.com",
+                got: "lichess.org"
+            }
+        );
+        assert!(lichess_games(Games::LichessDotOrg(Vec::new())).is_ok());
+
+        let mut finder = GameFinder::by_player("a_player", "chess.org");
+        let client = ChessClient::new(10, "lichess.org").unwrap();
+        match finder.export_months(&client, Utc::now()) {
+            Err(ChessError::FinderError(FinderError::UnsupportedApi(api))) => {
+                assert_eq!(api, "chess.org")
+            }
+            r => panic!("expected an unsupported API error, got {:?}", r),
+        }
+        finder.api = "lichess.org".to_string();
+        assert!(finder.export_months(&client, Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn test_in_parallel_bounds_requests_in_flight() {
+        use std::io::{Read, Write};
+        use std::net::TcpListener;
+        use std::sync::Arc;
+
+        let requests = 12;
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let most_in_flight = Arc::new(AtomicUsize::new(0));
+        let (current, most) = (in_flight.clone(), most_in_flight.clone());
+        let server = thread::spawn(move || {
+            let handlers: Vec<_> = listener
+                .incoming()
+                .take(requests)
+                .map(|stream| {
+                    let (current, most) = (current.clone(), most.clone());
+                    thread::spawn(move || {
+                        let mut stream = stream.unwrap();
+                        let now = current.fetch_add(1, Ordering::SeqCst) + 1;
+                        most.fetch_max(now, Ordering::SeqCst);
+                        let read = stream.read(&mut [0; 4096]).unwrap();
+                        assert!(read > 0, "the request was empty");
+                        thread::sleep(std::time::Duration::from_millis(50));
+                        current.fetch_sub(1, Ordering::SeqCst);
+                        stream
+                            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok")
+                            .unwrap();
+                    })
+                })
+                .collect();
+            for handler in handlers {
+                handler.join().unwrap();
+            }
+        });
+
+        let months: Vec<u32> = (1..=requests as u32).collect();
+        let clients = vec![reqwest::blocking::Client::new(); 3];
+        let bodies = in_parallel(&months, clients, |client, month| {
+            client
+                .get(&format!("http://{}/games/2020/{}", address, month))
+                .send()
+                .and_then(|r| r.text())
+                .unwrap()
+        });
+        server.join().unwrap();
+
+        assert_eq!(bodies, vec!["ok"; requests]);
+        let most = most_in_flight.load(Ordering::SeqCst);
+        assert!(most <= 3, "{} requests were in flight", most);
+        assert!(most > 1, "requests were not made at the same time");
     }
 }
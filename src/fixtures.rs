@@ -0,0 +1,58 @@
+use serde_json::{json, Value};
+
+use crate::api::lichessdotorg;
+
+/// A lichess.org game as the API sends it, timestamps in milliseconds included: Lance5500 (2389)
+/// beat TryingHard87 (2498) by resignation in a rated blitz game, after 1. d4 d5.
+pub fn lichess_json() -> Value {
+    json!({
+        "id": "q7ZvsdUF",
+        "rated": true,
+        "variant": "standard",
+        "speed": "blitz",
+        "perf": "blitz",
+        "createdAt": 1514505150384i64,
+        "lastMoveAt": 1514505592843i64,
+        "status": "resign",
+        "winner": "white",
+        "players": {
+            "white": {"user": {"name": "Lance5500", "id": "lance5500"}, "rating": 2389},
+            "black": {"user": {"name": "TryingHard87", "id": "tryinghard87"}, "rating": 2498}
+        },
+        "pgn": "[Event \"Rated Blitz game\"]\n[Site \"https://lichess.org/q7ZvsdUF\"]\n[White \"Lance5500\"]\n[Black \"TryingHard87\"]\n[Result \"1-0\"]\n[WhiteElo \"2389\"]\n[ECO \"D00\"]\n\n1. d4 d5 1-0",
+        "clock": {"initial": 300, "increment": 3, "totalTime": 420},
+        "moves": "d4 d5"
+    })
+}
+
+/// The game of lichess_json.
+pub fn lichess_game() -> lichessdotorg::Game {
+    lichess_game_with(json!({}))
+}
+
+/// The game of lichess_json, with the fields of changes replacing its own.
+pub fn lichess_game_with(changes: Value) -> lichessdotorg::Game {
+    let mut game = lichess_json();
+    for (field, value) in changes.as_object().expect("changes are a JSON object") {
+        game[field] = value.clone();
+    }
+    serde_json::from_value(game).unwrap()
+}
+
+/// The players field of a game between two unrated players.
+pub fn lichess_players(white: &str, black: &str) -> Value {
+    json!({
+        "white": {"user": {"name": white, "id": white.to_lowercase()}},
+        "black": {"user": {"name": black, "id": black.to_lowercase()}}
+    })
+}
+
+/// A line of the NDJSON stream of lichess.org games: the game of lichess_json with the given ID,
+/// created at created_at milliseconds and lasting 10 minutes.
+pub fn lichess_line(id: &str, created_at: i64) -> String {
+    let mut game = lichess_json();
+    game["id"] = id.into();
+    game["createdAt"] = created_at.into();
+    game["lastMoveAt"] = (created_at + 600_000).into();
+    game.to_string() + "\n"
+}
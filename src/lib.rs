@@ -2,9 +2,21 @@
 extern crate prettytable;
 
 pub mod api;
+pub mod cache;
 pub mod cli;
 pub mod client;
+pub mod database;
 pub mod displayer;
 pub mod error;
+pub mod export;
 pub mod finder;
+#[cfg(test)]
+mod fixtures;
+pub mod openings;
+pub mod progress;
+pub mod report;
+pub mod sample;
+pub mod template;
 pub mod utils;
+
+pub use utils::MoveDecodeError;
@@ -0,0 +1,123 @@
+use log;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use shakmaty::{fen, san::SanPlus, Chess, Position};
+
+/// Opening lines, one name and its SAN moves from the starting position per row. Without the
+/// opening-tree feature the tree is empty and no opening is named by its moves.
+#[cfg(feature = "opening-tree")]
+static OPENINGS_TSV: &str = include_str!("../data/openings.tsv");
+#[cfg(not(feature = "opening-tree"))]
+static OPENINGS_TSV: &str = "name\tmoves\n";
+
+/// How many plies of a game are looked up in the opening tree.
+pub const OPENING_TREE_PLIES: usize = 24;
+
+/// Play SAN moves, returning the position after each one, until a move is illegal.
+fn positions<'a, I>(moves: I) -> Vec<Chess>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut position = Chess::default();
+    let mut positions = Vec::new();
+    for san in moves {
+        let m = match SanPlus::from_ascii(san.as_bytes())
+            .ok()
+            .and_then(|san_plus| san_plus.san.to_move(&position).ok())
+        {
+            Some(m) => m,
+            None => break,
+        };
+        position.play_unchecked(&m);
+        positions.push(position.clone());
+    }
+    positions
+}
+
+/// The opening names by the EPD of the position their line ends in, so that lines reached by
+/// transposition are named too.
+fn tree() -> &'static HashMap<String, &'static str> {
+    static TREE: OnceLock<HashMap<String, &'static str>> = OnceLock::new();
+    TREE.get_or_init(|| {
+        let mut tree = HashMap::new();
+        for row in OPENINGS_TSV
+            .lines()
+            .skip(1)
+            .filter(|l| !l.trim().is_empty())
+        {
+            let (name, line) = match row.split_once('\t') {
+                Some((name, line)) => (name.trim(), line),
+                None => continue,
+            };
+            let played = positions(line.split_whitespace());
+            match played.last() {
+                Some(position) if played.len() == line.split_whitespace().count() => {
+                    tree.entry(fen::epd(position)).or_insert(name);
+                }
+                _ => log::warn!("Skipping opening {:?}, its moves are illegal", name),
+            }
+        }
+        tree
+    })
+}
+
+/// Name the opening of a game from its SAN moves, played from the starting position: the name
+/// of the last of its first OPENING_TREE_PLIES positions found in the opening tree.
+pub fn opening_of_moves(moves: &[String]) -> Option<&'static str> {
+    let tree = tree();
+    positions(moves.iter().take(OPENING_TREE_PLIES).map(String::as_str))
+        .iter()
+        .rev()
+        .find_map(|position| tree.get(&fen::epd(position)).copied())
+}
+
+#[cfg(all(test, feature = "opening-tree"))]
+mod tests {
+    use super::*;
+
+    fn moves(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_opening_tree_lines_are_legal() {
+        for row in OPENINGS_TSV.lines().skip(1) {
+            let (name, line) = row.split_once('\t').unwrap();
+            assert_eq!(
+                positions(line.split_whitespace()).len(),
+                line.split_whitespace().count(),
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_opening_of_moves() {
+        assert_eq!(
+            opening_of_moves(&moves("e4 c5 Nf3 d6 d4 cxd4 Nxd4 Nf6 Nc3 a6 Be3 e5")),
+            Some("Sicilian Defense: Najdorf Variation")
+        );
+        assert_eq!(
+            opening_of_moves(&moves("e4 e5 Qh5")),
+            Some("King's Pawn Game: Open Game")
+        );
+        assert_eq!(opening_of_moves(&moves("a3")), None);
+        assert_eq!(opening_of_moves(&moves("e4 e4")), Some("King's Pawn Game"));
+    }
+
+    #[test]
+    fn test_opening_of_moves_by_transposition() {
+        // An English move order with no direct ECO line that transposes into the Declined
+        assert_eq!(
+            opening_of_moves(&moves("c4 e6 d4 d5 Nf3 h6")),
+            Some("Queen's Gambit Declined")
+        );
+        // The Pirc reached from a Queen's Pawn move order
+        assert_eq!(
+            opening_of_moves(&moves("d4 Nf6 Nc3 d6 e4 g6 f4")),
+            Some("Pirc Defense")
+        );
+    }
+}
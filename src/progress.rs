@@ -0,0 +1,113 @@
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+/// Progress of a scan through a known number of requests, with an estimate of the time
+/// remaining based on the average time of the requests completed so far.
+#[derive(Debug)]
+pub struct Progress {
+    label: String,
+    total: usize,
+    done: usize,
+    spent: Duration,
+    started: Option<Instant>,
+    rendered: bool,
+}
+
+impl Progress {
+    pub fn new(label: &str, total: usize) -> Self {
+        Progress {
+            label: label.to_owned(),
+            total,
+            done: 0,
+            spent: Duration::from_secs(0),
+            started: None,
+            rendered: false,
+        }
+    }
+
+    /// Mark the start of a request.
+    pub fn start(&mut self) {
+        self.started = Some(Instant::now());
+    }
+
+    /// Mark the end of the request started last, and print the updated progress.
+    pub fn finish(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.record(started.elapsed());
+            self.report();
+        }
+    }
+
+    /// Count n requests made at the same time, which together took the given time, and print the
+    /// updated progress.
+    pub fn record_batch(&mut self, n: usize, took: Duration) {
+        for _ in 0..n {
+            self.record(took / n as u32);
+        }
+        self.report();
+    }
+
+    /// Count a completed request that took the given time.
+    pub fn record(&mut self, took: Duration) {
+        self.done += 1;
+        self.spent += took;
+    }
+
+    /// Estimated time left to complete the remaining requests. None until a request completes.
+    pub fn eta(&self) -> Option<Duration> {
+        if self.done == 0 {
+            return None;
+        }
+        let remaining = self.total.saturating_sub(self.done) as u32;
+        Some(self.spent / self.done as u32 * remaining)
+    }
+
+    pub fn render(&self) -> String {
+        let eta = match self.eta() {
+            Some(eta) => {
+                let secs = eta.as_secs();
+                format!("{}m{:02}s", secs / 60, secs % 60)
+            }
+            None => "unknown".to_string(),
+        };
+        format!("{}: {}/{}, ETA {}", self.label, self.done, self.total, eta)
+    }
+
+    /// Print the progress to stderr, overwriting the previous report.
+    pub fn report(&mut self) {
+        eprint!("\r{}", self.render());
+        io::stderr().flush().ok();
+        self.rendered = true;
+    }
+}
+
+impl Drop for Progress {
+    /// End the progress line, so that it is not overwritten by the output.
+    fn drop(&mut self) {
+        if self.rendered {
+            eprintln!();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eta_from_average_request_time() {
+        let mut progress = Progress::new("Scanning archives", 10);
+        assert_eq!(progress.eta(), None);
+        assert_eq!(progress.render(), "Scanning archives: 0/10, ETA unknown");
+
+        progress.record(Duration::from_secs(2));
+        progress.record(Duration::from_secs(4));
+        assert_eq!(progress.eta(), Some(Duration::from_secs(24)));
+        assert_eq!(progress.render(), "Scanning archives: 2/10, ETA 0m24s");
+
+        for _ in 0..8 {
+            progress.record(Duration::from_secs(30));
+        }
+        assert_eq!(progress.eta(), Some(Duration::from_secs(0)));
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{BTreeMap, HashMap};
+
+use chrono::NaiveDate;
+use prettytable::Table;
+
+use crate::api::{ChessGame, ChessPlayer};
+use crate::finder::{GroupKey, Pieces, Tally};
+
+/// Tally of the openings a player chose, split by the color they played with.
+#[derive(Debug)]
+pub struct Repertoire {
+    player: String,
+    plies: Option<usize>,
+    counts: HashMap<(Pieces, String), usize>,
+}
+
+impl Repertoire {
+    /// Create a repertoire for a player. Openings are identified by their ECO name, or by their
+    /// first plies moves if plies is set.
+    pub fn new(player: &str, plies: Option<usize>) -> Self {
+        Repertoire {
+            player: player.to_lowercase(),
+            plies,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Count a game in the repertoire. Games the player didn't play are ignored.
+    pub fn add(&mut self, game: &mut impl ChessGame) {
+        let pieces = if game.white().name().to_lowercase() == self.player {
+            Pieces::White
+        } else if game.black().name().to_lowercase() == self.player {
+            Pieces::Black
+        } else {
+            return;
+        };
+
+        let opening = match self.plies {
+            Some(plies) => {
+                // Moves are decoded once per game and only the requested plies are kept
+                let moves = game.moves();
+                moves[..plies.min(moves.len())].join(" ")
+            }
+            None => match (game.eco(), game.opening()) {
+                (Some(eco), Some(name)) => format!("{} {}", eco, name),
+                (None, Some(name)) => name,
+                (Some(eco), None) => eco,
+                (None, None) => "Unknown".to_string(),
+            },
+        };
+
+        *self.counts.entry((pieces, opening)).or_insert(0) += 1;
+    }
+
+    /// Openings played with the given pieces and how many times, most played first.
+    pub fn ranked(&self, pieces: Pieces) -> Vec<(&str, usize)> {
+        let mut ranked = self
+            .counts
+            .iter()
+            .filter(|((p, _), _)| *p == pieces)
+            .map(|((_, opening), count)| (opening.as_str(), *count))
+            .collect::<Vec<(&str, usize)>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+    }
+
+    pub fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        table.set_titles(row!["Color", "Opening", "Games", "Share"]);
+
+        for (pieces, color) in [(Pieces::White, "White ♔"), (Pieces::Black, "Black ♚")].iter() {
+            let ranked = self.ranked(*pieces);
+            let total: usize = ranked.iter().map(|(_, count)| count).sum();
+            for (opening, count) in ranked {
+                table.add_row(row![
+                    color,
+                    opening,
+                    count,
+                    format!("{:.0}%", 100.0 * count as f32 / total as f32),
+                ]);
+            }
+        }
+        table
+    }
+}
+
+/// Groups of games with their record, most played first. Months are listed in order instead.
+pub fn ranked_groups(groups: &BTreeMap<String, Tally>, key: GroupKey) -> Vec<(&str, Tally)> {
+    let mut ranked = groups
+        .iter()
+        .map(|(group, tally)| (group.as_str(), *tally))
+        .collect::<Vec<(&str, Tally)>>();
+    if key != GroupKey::Month {
+        ranked.sort_by(|a, b| b.1.games.cmp(&a.1.games).then(a.0.cmp(b.0)));
+    }
+    ranked
+}
+
+pub fn group_table(groups: &BTreeMap<String, Tally>, key: GroupKey) -> Table {
+    let title = match key {
+        GroupKey::Opponent => "Opponent",
+        GroupKey::Opening => "Opening",
+        GroupKey::Speed => "Speed",
+        GroupKey::Month => "Month",
+        GroupKey::Color => "Color",
+        GroupKey::Termination => "Termination",
+    };
+    let mut table = Table::new();
+    table.set_titles(row![title, "Games", "Wins", "Losses", "Draws", "Score"]);
+
+    for (group, tally) in ranked_groups(groups, key) {
+        // Wins count as a point and draws as half, out of the games with a result
+        let decided = tally.wins + tally.losses + tally.draws;
+        let score = if decided > 0 {
+            format!(
+                "{:.0}%",
+                100.0 * (tally.wins as f32 + 0.5 * tally.draws as f32) / decided as f32
+            )
+        } else {
+            "-".to_string()
+        };
+        table.add_row(row![
+            group,
+            tally.games,
+            tally.wins,
+            tally.losses,
+            tally.draws,
+            score
+        ]);
+    }
+    table
+}
+
+/// Render a rating history as CSV, with a date and rating column.
+pub fn rating_history_csv(points: &[(NaiveDate, u32)]) -> String {
+    let mut csv = String::from("date,rating\n");
+    for (date, rating) in points {
+        csv.push_str(&format!("{},{}\n", date.format("%Y-%m-%d"), rating));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::lichessdotorg;
+    use crate::fixtures;
+    use serde_json::{json, Value};
+
+    fn lichess_game(
+        white: &str,
+        black: &str,
+        moves: &str,
+        eco: Option<&str>,
+    ) -> lichessdotorg::Game {
+        let opening = match eco {
+            Some(eco) => json!({"eco": eco, "name": "", "ply": 2}),
+            None => Value::Null,
+        };
+        fixtures::lichess_game_with(json!({
+            "winner": null,
+            "players": fixtures::lichess_players(white, black),
+            "opening": opening,
+            "pgn": "",
+            "moves": moves
+        }))
+    }
+
+    #[test]
+    fn test_repertoire_by_eco() {
+        let mut repertoire = Repertoire::new("Alice", None);
+        repertoire.add(&mut lichess_game("alice", "bob", "e4 c5", Some("B20")));
+        repertoire.add(&mut lichess_game("alice", "carol", "e4 c5", Some("B20")));
+        repertoire.add(&mut lichess_game(
+            "alice",
+            "bob",
+            "e4 e5 Nf3 Nc6 Bb5",
+            Some("C60"),
+        ));
+        repertoire.add(&mut lichess_game("bob", "alice", "d4 d5", None));
+        repertoire.add(&mut lichess_game("bob", "carol", "d4 d5", None));
+
+        assert_eq!(
+            repertoire.ranked(Pieces::White),
+            vec![("B20 Sicilian", 2), ("C60 Ruy Lopez", 1)]
+        );
+        assert_eq!(repertoire.ranked(Pieces::Black), vec![("Unknown", 1)]);
+    }
+
+    #[test]
+    fn test_ranked_groups() {
+        let tally = |games| Tally {
+            games,
+            wins: games,
+            losses: 0,
+            draws: 0,
+        };
+        let mut groups = BTreeMap::new();
+        groups.insert("2021-01".to_string(), tally(1));
+        groups.insert("2021-02".to_string(), tally(3));
+        groups.insert("2021-03".to_string(), tally(2));
+
+        let order = |key| {
+            ranked_groups(&groups, key)
+                .iter()
+                .map(|(group, _)| group.to_string())
+                .collect::<Vec<String>>()
+        };
+        assert_eq!(
+            order(GroupKey::Month),
+            vec!["2021-01", "2021-02", "2021-03"]
+        );
+        assert_eq!(
+            order(GroupKey::Opponent),
+            vec!["2021-02", "2021-03", "2021-01"]
+        );
+    }
+
+    #[test]
+    fn test_rating_history_csv() {
+        let points = vec![
+            (NaiveDate::from_ymd(2020, 9, 1), 1500),
+            (NaiveDate::from_ymd(2020, 9, 2), 1512),
+        ];
+        assert_eq!(
+            rating_history_csv(&points),
+            "date,rating\n2020-09-01,1500\n2020-09-02,1512\n"
+        );
+    }
+
+    #[test]
+    fn test_repertoire_by_plies() {
+        let mut repertoire = Repertoire::new("alice", Some(2));
+        repertoire.add(&mut lichess_game("alice", "bob", "e4 c5 Nf3", None));
+        repertoire.add(&mut lichess_game("alice", "bob", "e4 c5 Nc3", None));
+        repertoire.add(&mut lichess_game("bob", "alice", "d4", None));
+
+        assert_eq!(repertoire.ranked(Pieces::White), vec![("e4 c5", 2)]);
+        assert_eq!(repertoire.ranked(Pieces::Black), vec![("d4", 1)]);
+    }
+}
@@ -0,0 +1,127 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Seed for a sample when none is given, taken from the clock.
+pub fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+/// A small seeded generator (splitmix64). Samples only need to be uniform and reproducible for
+/// a given seed, which a generator of our own keeps stable across dependency upgrades.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniform number in 0..bound, rejecting the values that would bias the low numbers.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % bound;
+        loop {
+            let n = self.next_u64();
+            if n < zone {
+                return n % bound;
+            }
+        }
+    }
+}
+
+/// Keeps a uniformly random sample of up to size items of a stream of unknown length, seeing
+/// each item once (reservoir sampling).
+#[derive(Debug)]
+pub struct Reservoir<T> {
+    size: usize,
+    seen: u64,
+    items: Vec<T>,
+    rng: SplitMix64,
+}
+
+impl<T> Reservoir<T> {
+    pub fn new(size: usize, seed: u64) -> Self {
+        Reservoir {
+            size,
+            seen: 0,
+            items: Vec::with_capacity(size),
+            rng: SplitMix64::new(seed),
+        }
+    }
+
+    /// Consider the next item of the stream for the sample.
+    pub fn offer(&mut self, item: T) {
+        self.seen += 1;
+        if self.items.len() < self.size {
+            self.items.push(item);
+        } else {
+            let i = self.rng.below(self.seen) as usize;
+            if i < self.size {
+                self.items[i] = item;
+            }
+        }
+    }
+
+    /// How many items were offered.
+    pub fn seen(&self) -> u64 {
+        self.seen
+    }
+
+    /// The sampled items, in the order they were kept.
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(size: usize, seed: u64) -> Vec<u32> {
+        let mut reservoir = Reservoir::new(size, seed);
+        for i in 0..1000 {
+            reservoir.offer(i);
+        }
+        reservoir.into_items()
+    }
+
+    #[test]
+    fn test_sample_with_seed_is_deterministic() {
+        assert_eq!(sample(1, 42), sample(1, 42));
+        assert_eq!(sample(5, 7), sample(5, 7));
+        assert_ne!(sample(5, 7), sample(5, 8));
+    }
+
+    #[test]
+    fn test_sample_keeps_every_item_of_short_streams() {
+        let mut reservoir = Reservoir::new(3, 1);
+        reservoir.offer("a");
+        reservoir.offer("b");
+        assert_eq!(reservoir.seen(), 2);
+        assert_eq!(reservoir.into_items(), vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_single_sample_is_roughly_uniform() {
+        let mut counts = [0; 4];
+        for seed in 0..4000 {
+            let mut reservoir = Reservoir::new(1, seed);
+            for i in 0..4 {
+                reservoir.offer(i);
+            }
+            counts[reservoir.into_items()[0]] += 1;
+        }
+        assert!(counts.iter().all(|&c| c > 800 && c < 1200), "{:?}", counts);
+    }
+}
@@ -0,0 +1,180 @@
+use std::fs;
+use std::path::Path;
+
+use crate::api::{ChessPlayer, DisplayableChessGame};
+use crate::error::ChessError;
+
+/// Placeholders a template can use, each written in braces, e.g. {white}.
+pub const TEMPLATE_TOKENS: &[&str] = &[
+    "white",
+    "black",
+    "white_rating",
+    "black_rating",
+    "result",
+    "date",
+    "speed",
+    "variant",
+    "eco",
+    "opening",
+    "url",
+    "moves",
+    "pgn",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Text(String),
+    Token(&'static str),
+}
+
+/// A layout games are rendered with, one at a time: text with placeholders in braces that are
+/// replaced with the values of each game. Literal braces are written doubled, {{ and }}.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Template {
+    /// Parse a template, failing on unknown placeholders and unbalanced braces.
+    pub fn parse(template: &str) -> Result<Self, ChessError> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => {
+                                return Err(ChessError::ValidationError(format!(
+                                    "unclosed placeholder {{{} in template",
+                                    name
+                                )))
+                            }
+                        }
+                    }
+                    let token = TEMPLATE_TOKENS
+                        .iter()
+                        .find(|&&t| t == name.trim())
+                        .ok_or_else(|| {
+                            ChessError::ValidationError(format!(
+                                "unknown placeholder {{{}}} in template, valid placeholders are: {}",
+                                name,
+                                TEMPLATE_TOKENS.join(", ")
+                            ))
+                        })?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Token(token));
+                }
+                '}' => {
+                    return Err(ChessError::ValidationError(
+                        "unmatched } in template, write }} for a literal brace".to_string(),
+                    ))
+                }
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template { parts })
+    }
+
+    /// Read and parse a template from a file.
+    pub fn from_file(path: &Path) -> Result<Self, ChessError> {
+        let template = fs::read_to_string(path).map_err(|e| {
+            ChessError::ValidationError(format!(
+                "could not read template {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        Template::parse(&template)
+    }
+
+    /// Render a game with the template.
+    pub fn render(&self, game: &mut impl DisplayableChessGame) -> String {
+        let mut rendered = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => rendered.push_str(text),
+                Part::Token(token) => rendered.push_str(&value_of(game, token)),
+            }
+        }
+        rendered
+    }
+}
+
+/// Value of a placeholder for a game. Values a game doesn't have are left empty.
+fn value_of(game: &mut impl DisplayableChessGame, token: &str) -> String {
+    let rating = |r: Option<u32>| r.map_or(String::new(), |r| r.to_string());
+    match token {
+        "white" => game.white().name(),
+        "black" => game.black().name(),
+        "white_rating" => rating(game.white().rating()),
+        "black_rating" => rating(game.black().rating()),
+        "result" => game.tags().remove("Result").unwrap_or_default(),
+        "date" => game.end_time().format("%Y-%m-%d").to_string(),
+        "speed" => game.speed().to_string(),
+        "variant" => game.variant(),
+        "eco" => game.eco().unwrap_or_default(),
+        "opening" => game.opening().unwrap_or_default(),
+        "url" => game.url(),
+        "moves" => game.moves().join(" "),
+        "pgn" => game.pgn(),
+        _ => unreachable!("placeholders are validated when parsing"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::lichessdotorg;
+    use crate::fixtures;
+    use serde_json::json;
+
+    fn lichess_game() -> lichessdotorg::Game {
+        fixtures::lichess_game_with(json!({
+            "opening": {"eco": "D00", "name": "Queen's Pawn Game", "ply": 2}
+        }))
+    }
+
+    #[test]
+    fn test_render_multi_line_template() {
+        let template = Template::parse(
+            "# {white} ({white_rating}) vs {black} ({black_rating})\n\
+             Played {date}, {speed}: {result}\n\
+             {eco} {opening}\n\
+             Moves: {moves} {{not a placeholder}}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            template.render(&mut lichess_game()),
+            "# Lance5500 (2389) vs TryingHard87 (2498)\n\
+             Played 2017-12-28, blitz: 1-0\n\
+             D00 Queen's Pawn Game\n\
+             Moves: d4 d5 {not a placeholder}\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_placeholders() {
+        assert!(Template::parse("{white} vs {blakc}").is_err());
+        assert!(Template::parse("{white").is_err());
+        assert!(Template::parse("white}").is_err());
+        assert!(Template::parse("{{white}}").is_ok());
+    }
+}
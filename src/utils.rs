@@ -1,18 +1,202 @@
 use log;
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
 
-use shakmaty::{san::SanPlus, Color, Move, Position, Role, Square};
+use std::fmt;
+
+use chrono::NaiveDate;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use shakmaty::{
+    fen::{self, Fen},
+    san::SanPlus,
+    uci::Uci,
+    CastlingMode, Chess, Color, Move, Position, Role, Square,
+};
+use thiserror::Error;
 
 const ASCII: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789!?";
 
-pub fn next_move<P: Position>(moves: &mut Vec<char>, position: &mut P) -> Option<String> {
-    if moves.is_empty() {
-        return None;
+include!(concat!(env!("OUT_DIR"), "/eco.rs"));
+
+/// Look up the opening name of an ECO code (e.g. "B20") in the embedded ECO table.
+pub fn eco_name(eco: &str) -> Option<&'static str> {
+    let eco = eco.trim().to_ascii_uppercase();
+    ECO_NAMES
+        .binary_search_by(|&(code, _)| code.cmp(eco.as_str()))
+        .ok()
+        .map(|i| ECO_NAMES[i].1)
+}
+
+/// Find the value of a tag pair (e.g. [ECO "B20"]) in a PGN string.
+pub fn pgn_tag(pgn: &str, tag: &str) -> Option<String> {
+    let prefix = format!("[{} \"", tag);
+    pgn.lines()
+        .map(str::trim)
+        .find(|l| l.starts_with(&prefix))
+        .and_then(|l| l[prefix.len()..].strip_suffix("\"]"))
+        .map(|v| v.to_string())
+}
+
+/// Parse the tag pairs of a PGN string into a map of tag names to values, unescaping quotes and
+/// backslashes in the values. Lines that aren't tag pairs are skipped.
+pub fn pgn_tags(pgn: &str) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+    for line in pgn.lines().map(str::trim) {
+        let pair = match line
+            .strip_prefix('[')
+            .and_then(|l| l.strip_suffix(']'))
+            .and_then(|l| l.split_once(' '))
+        {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let value = match pair
+            .1
+            .trim()
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+        {
+            Some(value) => value,
+            None => continue,
+        };
+        let mut unescaped = String::with_capacity(value.len());
+        let mut chars = value.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => unescaped.extend(chars.next()),
+                c => unescaped.push(c),
+            }
+        }
+        tags.insert(pair.0.to_string(), unescaped);
+    }
+    tags
+}
+
+/// Tags of the Seven Tag Roster, in the order required by the PGN standard, each with the value
+/// the standard uses when it's unknown.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// Rewrite a PGN with only the tags of the Seven Tag Roster, followed by its movetext. Tags that
+/// are missing or empty are given the standard's unknown value, so that the result is accepted
+/// by strict PGN databases.
+pub fn minimal_pgn(pgn: &str) -> String {
+    let mut minimal = String::new();
+    for (tag, unknown) in SEVEN_TAG_ROSTER.iter() {
+        let value = pgn_tag(pgn, tag)
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or_else(|| unknown.to_string());
+        minimal.push_str(&format!("[{} \"{}\"]\n", tag, value));
+    }
+    minimal.push('\n');
+
+    let movetext = pgn
+        .lines()
+        .skip_while(|l| l.trim_start().starts_with('[') || l.trim().is_empty())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    minimal.push_str(movetext.trim_end());
+    minimal
+}
+
+/// Reasons a move of a chess.com encoded move list can't be decoded.
+#[derive(Error, Debug, PartialEq)]
+pub enum MoveDecodeError {
+    #[error("unknown character {0:?} in move list")]
+    UnknownCharacter(char),
+    #[error("no piece to move on {0}")]
+    EmptySquare(Square),
+    #[error("king moves two squares but not to a castling square")]
+    IllegalCastle,
+    #[error("illegal move {0}")]
+    IllegalMove(String),
+    #[error("promotion to a square off the board")]
+    OffBoardPromotion,
+    #[error("{0}")]
+    InvalidSetup(String),
+}
+
+/// A move of a chess.com encoded move list, with what its decoding found out about it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedMove {
+    /// The pair of characters the move was encoded as.
+    pub encoded: String,
+    pub san: String,
+    pub from: Square,
+    pub to: Square,
+    pub capture: Option<Role>,
+    pub promotion: Option<Role>,
+    pub castle: bool,
+    pub en_passant: bool,
+}
+
+impl fmt::Display for DecodedMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut notes = Vec::new();
+        if self.castle {
+            notes.push("castle".to_string());
+        }
+        if self.en_passant {
+            notes.push("en passant".to_string());
+        } else if let Some(role) = self.capture {
+            notes.push(format!("captures {:?}", role).to_lowercase());
+        }
+        if let Some(role) = self.promotion {
+            notes.push(format!("promotes to {:?}", role).to_lowercase());
+        }
+        let line = format!(
+            "{}  {}-{}  {:<8}{}",
+            self.encoded,
+            self.from,
+            self.to,
+            self.san,
+            notes.join(", ")
+        );
+        write!(f, "{}", line.trim_end())
     }
+}
+
+/// Decode the next move of a chess.com encoded move list, where each move is a pair of
+/// characters for its start and end squares, and play it in position. Returns None once there
+/// are no moves left.
+pub fn next_move<P: Position>(
+    moves: &mut Vec<char>,
+    position: &mut P,
+) -> Result<Option<String>, MoveDecodeError> {
+    Ok(decode_next_move(moves, position)?.map(|m| m.san))
+}
 
-    let start = moves.pop().unwrap();
-    let end = moves.pop().unwrap();
+/// Like next_move, returning the squares of the move and what was detected while decoding it,
+/// to troubleshoot the decoding of games.
+pub fn decode_next_move<P: Position>(
+    moves: &mut Vec<char>,
+    position: &mut P,
+) -> Result<Option<DecodedMove>, MoveDecodeError> {
+    let start = match moves.pop() {
+        Some(c) => c,
+        None => return Ok(None),
+    };
+    let end = match moves.pop() {
+        Some(c) => c,
+        None => {
+            return Err(MoveDecodeError::IllegalMove(format!(
+                "{} without an end square",
+                start
+            )))
+        }
+    };
 
-    let index_start = ASCII.find(start).unwrap();
+    let index_start = ASCII
+        .find(start)
+        .ok_or(MoveDecodeError::UnknownCharacter(start))?;
     let promo_left = match position.turn() {
         Color::Black => index_start as i8 - 9,
         Color::White => index_start as i8 + 7,
@@ -27,23 +211,27 @@ pub fn next_move<P: Position>(moves: &mut Vec<char>, position: &mut P) -> Option
     };
 
     let (index_end, promotion) = match ASCII.find(end) {
-        Some(i) => (i, None),
+        Some(i) => (i as i8, None),
         None => match end {
-            '}' => (promo_right as usize, Some(Role::Queen)),
-            ')' => (promo_right as usize, Some(Role::Knight)),
-            ']' => (promo_right as usize, Some(Role::Rook)),
-            '$' => (promo_right as usize, Some(Role::Bishop)),
-            '~' => (promo_center as usize, Some(Role::Queen)),
-            '^' => (promo_center as usize, Some(Role::Knight)),
-            '_' => (promo_center as usize, Some(Role::Rook)),
-            '#' => (promo_center as usize, Some(Role::Bishop)),
-            '{' => (promo_left as usize, Some(Role::Queen)),
-            '(' => (promo_left as usize, Some(Role::Knight)),
-            '[' => (promo_left as usize, Some(Role::Rook)),
-            '@' => (promo_left as usize, Some(Role::Bishop)),
-            _ => panic!("well crap"),
+            '}' => (promo_right, Some(Role::Queen)),
+            ')' => (promo_right, Some(Role::Knight)),
+            ']' => (promo_right, Some(Role::Rook)),
+            '$' => (promo_right, Some(Role::Bishop)),
+            '~' => (promo_center, Some(Role::Queen)),
+            '^' => (promo_center, Some(Role::Knight)),
+            '_' => (promo_center, Some(Role::Rook)),
+            '#' => (promo_center, Some(Role::Bishop)),
+            '{' => (promo_left, Some(Role::Queen)),
+            '(' => (promo_left, Some(Role::Knight)),
+            '[' => (promo_left, Some(Role::Rook)),
+            '@' => (promo_left, Some(Role::Bishop)),
+            c => return Err(MoveDecodeError::UnknownCharacter(c)),
         },
     };
+    if !(0..64).contains(&index_end) {
+        return Err(MoveDecodeError::OffBoardPromotion);
+    }
+    let index_start = index_start as i8;
 
     let square_start = Square::new(index_start as u32);
     let square_end = Square::new(index_end as u32);
@@ -53,22 +241,24 @@ pub fn next_move<P: Position>(moves: &mut Vec<char>, position: &mut P) -> Option
         Some(piece) => Some(piece.role),
         None => None,
     };
-    let piece_start = position.board().piece_at(square_start).unwrap().role;
+    let piece_start = position
+        .board()
+        .piece_at(square_start)
+        .ok_or(MoveDecodeError::EmptySquare(square_start))?
+        .role;
 
     let current_color = position.turn();
 
     let m = match piece_start {
         Role::King => {
-            if i8::abs(index_start as i8 - index_end as i8) > 1
-                && square_end.rank() == square_start.rank()
-            {
+            if i8::abs(index_start - index_end) > 1 && square_end.rank() == square_start.rank() {
                 // Only instance when king moves more than 1 square is castle
-                let rook_square = match (current_color, index_start as i8 - index_end as i8) {
+                let rook_square = match (current_color, index_start - index_end) {
                     (Color::Black, -2) => Square::new(63),
                     (Color::Black, 2) => Square::new(56),
                     (Color::White, -2) => Square::new(7),
                     (Color::White, 2) => Square::new(0),
-                    _ => panic!("well crap"),
+                    _ => return Err(MoveDecodeError::IllegalCastle),
                 };
                 Move::Castle {
                     king: square_start,
@@ -85,7 +275,7 @@ pub fn next_move<P: Position>(moves: &mut Vec<char>, position: &mut P) -> Option
             }
         }
         Role::Pawn => {
-            if i8::abs(index_start as i8 - index_end as i8) % 8 != 0 {
+            if i8::abs(index_start - index_end) % 8 != 0 {
                 // Pawn did not move forward, it must have captured something
                 if piece_end_role.is_none() {
                     // Holy Hell!
@@ -123,29 +313,804 @@ pub fn next_move<P: Position>(moves: &mut Vec<char>, position: &mut P) -> Option
     };
     log::debug!("Move: {:?}", m);
 
+    if !position.is_legal(&m) {
+        return Err(MoveDecodeError::IllegalMove(format!(
+            "from {} to {}",
+            square_start, square_end
+        )));
+    }
+
+    let (capture, castle, en_passant) = match m {
+        Move::Castle { .. } => (None, true, false),
+        Move::EnPassant { .. } => (Some(Role::Pawn), false, true),
+        Move::Normal { capture, .. } => (capture, false, false),
+        Move::Put { .. } => (None, false, false),
+    };
     let sanplus = SanPlus::from_move_and_play_unchecked(position, &m);
-    Some(format!("{}", sanplus))
+    Ok(Some(DecodedMove {
+        encoded: format!("{}{}", start, end),
+        san: format!("{}", sanplus),
+        from: square_start,
+        to: square_end,
+        capture,
+        promotion,
+        castle,
+        en_passant,
+    }))
+}
+
+/// Split a PGN string into its tag pairs, with the blank lines that follow them, and its
+/// movetext.
+fn split_tags(pgn: &str) -> (&str, &str) {
+    let start = pgn
+        .split_inclusive('\n')
+        .take_while(|l| l.trim_start().starts_with('[') || l.trim().is_empty())
+        .map(str::len)
+        .sum::<usize>();
+    pgn.split_at(start)
+}
+
+/// Remove every annotation from the movetext of a PGN string: comments, including clock and
+/// evaluation commands, NAGs, and the !? suffixes of moves. Black's move numbers that only
+/// followed a comment are removed too, leaving bare SAN moves, variations, and the result.
+pub fn strip_annotations(pgn: &str) -> String {
+    let (tags, movetext) = split_tags(pgn);
+
+    let mut tokens: Vec<String> = Vec::new();
+    let mut token = String::new();
+    let mut comment_depth = 0;
+    let mut line_comment = false;
+    for c in movetext.chars() {
+        match c {
+            '{' if !line_comment => {
+                push_bare_token(&mut tokens, &mut token);
+                comment_depth += 1;
+            }
+            '}' if comment_depth > 0 => comment_depth -= 1,
+            _ if comment_depth > 0 => (),
+            ';' if !line_comment => {
+                push_bare_token(&mut tokens, &mut token);
+                line_comment = true;
+            }
+            '\n' if line_comment => line_comment = false,
+            _ if line_comment => (),
+            '(' | ')' => {
+                push_bare_token(&mut tokens, &mut token);
+                tokens.push(c.to_string());
+            }
+            c if c.is_whitespace() => push_bare_token(&mut tokens, &mut token),
+            c => token.push(c),
+        }
+    }
+    push_bare_token(&mut tokens, &mut token);
+
+    let mut stripped = tags.to_string();
+    for (i, t) in tokens.iter().enumerate() {
+        if i > 0 && t != ")" && tokens[i - 1] != "(" {
+            stripped.push(' ');
+        }
+        stripped.push_str(t);
+    }
+    stripped
+}
+
+/// Push a movetext token without its annotations, splitting move numbers from the moves they
+/// are attached to, e.g. "1.e4!". NAGs are dropped, as are black's move numbers that follow
+/// white's move, as they are only needed after a comment or at the start of a variation.
+fn push_bare_token(tokens: &mut Vec<String>, token: &mut String) {
+    let t = std::mem::take(token);
+    let number_end = t
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(t.len());
+    let (number, m) = if t[..number_end].ends_with('.') {
+        t.split_at(number_end)
+    } else {
+        ("", t.as_str())
+    };
+
+    if !number.is_empty() {
+        let follows_a_move = match tokens.last() {
+            Some(last) => last != "(" && !last.ends_with('.'),
+            None => false,
+        };
+        if !(number.ends_with("...") && follows_a_move) {
+            tokens.push(number.to_string());
+        }
+    }
+    let m = m.trim_end_matches(['!', '?']);
+    if !m.is_empty() && !m.starts_with('$') {
+        tokens.push(m.to_string());
+    }
+}
+
+/// Cut the movetext of a PGN string after the given number of full moves, ending it with the
+/// "*" result of a game in progress, which the Result tag is set to as well. Comments of the
+/// moves kept are kept too. PGN strings with fewer moves are returned unchanged.
+pub fn truncate_pgn(pgn: &str, full_moves: u32) -> String {
+    let (tags, movetext) = split_tags(pgn);
+
+    let mut cut = None;
+    let mut token_start = None;
+    let mut comment = false;
+    let mut line_comment = false;
+    let mut variation_depth = 0;
+    for (i, c) in movetext.char_indices() {
+        match c {
+            '{' if !comment && !line_comment => comment = true,
+            '}' if comment => comment = false,
+            ';' if !comment => line_comment = true,
+            '\n' if line_comment => line_comment = false,
+            '(' if !comment && !line_comment => variation_depth += 1,
+            ')' if !comment && !line_comment && variation_depth > 0 => variation_depth -= 1,
+            _ if comment || line_comment || variation_depth > 0 => continue,
+            c if c.is_ascii_digit() => {
+                token_start.get_or_insert(i);
+                continue;
+            }
+            '.' => {
+                if let Some(number) = token_start.and_then(|s| movetext[s..i].parse::<u32>().ok()) {
+                    if number > full_moves {
+                        cut = token_start;
+                        break;
+                    }
+                }
+            }
+            _ => (),
+        }
+        token_start = None;
+    }
+
+    let cut = match cut {
+        Some(cut) => cut,
+        None => return pgn.to_string(),
+    };
+    let tags = tags
+        .lines()
+        .map(|l| {
+            if l.trim_start().starts_with("[Result ") {
+                "[Result \"*\"]"
+            } else {
+                l
+            }
+        })
+        .map(|l| format!("{}\n", l))
+        .collect::<String>();
+    format!("{}{} *", tags, movetext[..cut].trim_end())
+}
+
+/// Extract the SAN moves from a PGN string, skipping tag pairs, move numbers, comments,
+/// variations, NAGs, and the game result.
+pub fn pgn_moves(pgn: &str) -> Vec<String> {
+    pgn_moves_with_comments(pgn)
+        .into_iter()
+        .map(|(m, _)| m)
+        .collect()
+}
+
+/// Extract the SAN moves from a PGN string, each with the text of the comments that follow it
+/// in the main line. Comments before the first move and inside variations are skipped.
+pub fn pgn_moves_with_comments(pgn: &str) -> Vec<(String, String)> {
+    let movetext = pgn
+        .lines()
+        .filter(|l| !l.trim_start().starts_with('['))
+        .collect::<Vec<&str>>()
+        .join("\n");
+
+    let mut moves: Vec<(String, String)> = Vec::new();
+    let mut token = String::new();
+    let mut comment = false;
+    let mut line_comment = false;
+    let mut variation_depth = 0;
+    for c in movetext.chars() {
+        match c {
+            '{' if !comment && !line_comment => {
+                push_move_token(&mut moves, &mut token);
+                comment = true;
+            }
+            '}' if comment => comment = false,
+            ';' if !comment => {
+                push_move_token(&mut moves, &mut token);
+                line_comment = true;
+            }
+            '\n' if line_comment => line_comment = false,
+            '(' if !comment && !line_comment => {
+                push_move_token(&mut moves, &mut token);
+                variation_depth += 1;
+            }
+            ')' if !comment && !line_comment && variation_depth > 0 => variation_depth -= 1,
+            c if comment => {
+                if variation_depth == 0 {
+                    if let Some((_, text)) = moves.last_mut() {
+                        text.push(c);
+                    }
+                }
+            }
+            _ if line_comment || variation_depth > 0 => (),
+            c if c.is_whitespace() => push_move_token(&mut moves, &mut token),
+            c => token.push(c),
+        }
+    }
+    push_move_token(&mut moves, &mut token);
+    moves
+}
+
+/// Push the movetext token as a move, unless it's only a move number, a NAG, or the game result.
+fn push_move_token(moves: &mut Vec<(String, String)>, token: &mut String) {
+    let t = std::mem::take(token);
+    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&t.as_str()) {
+        return;
+    }
+    let m = t.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+    if !m.is_empty() && !m.starts_with('$') {
+        moves.push((m.to_string(), String::new()));
+    }
+}
+
+/// Parse the clock time, in seconds, of a "[%clk H:MM:SS]" command in a PGN comment. Seconds
+/// may have a decimal part.
+pub fn clock_seconds(comment: &str) -> Option<f64> {
+    let start = comment.find("[%clk ")? + "[%clk ".len();
+    let end = start + comment[start..].find(']')?;
+    let mut parts = comment[start..end].trim().rsplit(':');
+
+    let secs = parts.next()?.parse::<f64>().ok()?;
+    let minutes = parts.next().map_or(Ok(0), |m| m.parse::<u32>()).ok()?;
+    let hours = parts.next().map_or(Ok(0), |h| h.parse::<u32>()).ok()?;
+    Some(f64::from(hours * 3600 + minutes * 60) + secs)
+}
+
+/// Number a list of SAN moves and follow each one with its annotation in brackets, e.g.
+/// "1. e4 [+0.2] e5 [+0.3]". Moves without an annotation are left bare.
+pub fn annotate_moves(moves: &[&str], annotations: &[String]) -> String {
+    let mut annotated = String::new();
+
+    for (ply, m) in moves.iter().enumerate() {
+        if ply % 2 == 0 {
+            if ply > 0 {
+                annotated.push('\n');
+            }
+            annotated.push_str(&format!("{}. ", ply / 2 + 1));
+        } else {
+            annotated.push(' ');
+        }
+        annotated.push_str(m);
+        if let Some(a) = annotations.get(ply) {
+            annotated.push_str(&format!(" [{}]", a));
+        }
+    }
+    annotated
+}
+
+/// Number a list of SAN moves as PGN movetext, followed by the game result, e.g.
+/// "1. e4 e5 2. Nf3 1-0".
+pub fn movetext(moves: &[String], result: &str) -> String {
+    let mut text = String::new();
+    for (ply, m) in moves.iter().enumerate() {
+        if ply % 2 == 0 {
+            text.push_str(&format!("{}. ", ply / 2 + 1));
+        }
+        text.push_str(m);
+        text.push(' ');
+    }
+    text.push_str(result);
+    text
+}
+
+/// Encode a string as Latin-1 (ISO-8859-1), replacing any character that can't be represented
+/// with '?'. Latin-1 bytes are the first 256 Unicode code points.
+pub fn encode_latin1(s: &str) -> Vec<u8> {
+    s.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+/// How a game ended, with the vocabularies of both APIs mapped onto the same reasons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    Checkmate,
+    Resignation,
+    Timeout,
+    Stalemate,
+    DrawAgreed,
+    Repetition,
+    InsufficientMaterial,
+    FiftyMove,
+    Abandoned,
+    RulesInfraction,
+    #[default]
+    Unknown,
+}
+
+impl fmt::Display for Termination {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match self {
+            Termination::Checkmate => "Checkmate",
+            Termination::Resignation => "Resignation",
+            Termination::Timeout => "Timeout",
+            Termination::Stalemate => "Stalemate",
+            Termination::DrawAgreed => "Draw agreed",
+            Termination::Repetition => "Repetition",
+            Termination::InsufficientMaterial => "Insufficient material",
+            Termination::FiftyMove => "Fifty-move rule",
+            Termination::Abandoned => "Abandoned",
+            Termination::RulesInfraction => "Rules infraction",
+            Termination::Unknown => "Unknown",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// Termination of a chess.com game from the result codes of its players, e.g. "win" and
+/// "resigned". The code of the player who didn't win tells how the game ended.
+pub fn chess_com_termination(white: &str, black: &str) -> Termination {
+    let code = if white == "win" { black } else { white };
+    match code {
+        "checkmated" => Termination::Checkmate,
+        "resigned" => Termination::Resignation,
+        "timeout" => Termination::Timeout,
+        "abandoned" => Termination::Abandoned,
+        "stalemate" => Termination::Stalemate,
+        "agreed" => Termination::DrawAgreed,
+        "repetition" => Termination::Repetition,
+        "insufficient" | "timevsinsufficient" => Termination::InsufficientMaterial,
+        "50move" => Termination::FiftyMove,
+        // Wins by the rules of variants, and losses without a reason
+        _ => Termination::Unknown,
+    }
+}
+
+/// Termination of a chess.com live game from its result message, e.g. "Alice won by
+/// resignation" or "Game drawn by agreement".
+pub fn chess_com_message_termination(message: &str) -> Termination {
+    let message = message.to_lowercase();
+    let reasons = [
+        ("checkmate", Termination::Checkmate),
+        ("resignation", Termination::Resignation),
+        ("insufficient material", Termination::InsufficientMaterial),
+        ("on time", Termination::Timeout),
+        ("abandoned", Termination::Abandoned),
+        ("stalemate", Termination::Stalemate),
+        ("agreement", Termination::DrawAgreed),
+        ("repetition", Termination::Repetition),
+        ("50-move", Termination::FiftyMove),
+    ];
+    reasons
+        .iter()
+        .find(|(phrase, _)| message.contains(phrase))
+        .map_or(Termination::Unknown, |&(_, termination)| termination)
+}
+
+/// Termination of a lichess game from its status. lichess reports draws by agreement and
+/// claimed draws alike as "draw", and a player who left the game as "timeout", unlike running
+/// out of time, "outoftime".
+pub fn lichess_termination(status: &str) -> Termination {
+    match status {
+        "mate" => Termination::Checkmate,
+        "resign" => Termination::Resignation,
+        "outoftime" => Termination::Timeout,
+        "stalemate" => Termination::Stalemate,
+        "draw" => Termination::DrawAgreed,
+        "timeout" | "aborted" | "noStart" => Termination::Abandoned,
+        "cheat" => Termination::RulesInfraction,
+        _ => Termination::Unknown,
+    }
+}
+
+/// Parse a duration given in seconds, either as a plain number or with an h, m, or s unit
+/// suffix, e.g. "3600", "90s", "5m", or "1h".
+pub fn parse_duration(s: &str) -> Result<chrono::Duration, String> {
+    let s = s.trim();
+    let (number, multiplier) = match s.chars().last() {
+        Some('h') => (&s[..s.len() - 1], 3600),
+        Some('m') => (&s[..s.len() - 1], 60),
+        Some('s') => (&s[..s.len() - 1], 1),
+        _ => (s, 1),
+    };
+    match number.parse::<i64>() {
+        Ok(n) if n >= 0 => Ok(chrono::Duration::seconds(n * multiplier)),
+        _ => Err(format!(
+            "invalid duration {:?}, expected seconds or a number followed by h, m, or s",
+            s
+        )),
+    }
+}
+
+/// Format a duration for people to read, from its largest unit down to seconds, e.g. "14m 32s"
+/// or "1h 0m 5s". Negative durations are formatted as none.
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let units = [
+        (seconds / 86_400, "d"),
+        (seconds % 86_400 / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .skip_while(|(n, unit)| *n == 0 && *unit != "s")
+        .map(|(n, unit)| format!("{}{}", n, unit))
+        .collect();
+    parts.join(" ")
+}
+
+/// Parse a calendar date in YYYY-MM-DD format.
+pub fn parse_date(s: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+        .map_err(|_| format!("invalid date {:?}, expected YYYY-MM-DD", s))
+}
+
+/// Convert SAN moves played from position to UCI, i.e. the start and end squares of each move
+/// followed by the promotion piece, e.g. e2e4 or e7e8q.
+pub fn san_to_uci<P: Position>(
+    mut position: P,
+    moves: &[String],
+) -> Result<Vec<String>, MoveDecodeError> {
+    let mut uci = Vec::with_capacity(moves.len());
+    for san in moves.iter() {
+        let m = SanPlus::from_ascii(san.as_bytes())
+            .ok()
+            .and_then(|san_plus| san_plus.san.to_move(&position).ok())
+            .ok_or_else(|| MoveDecodeError::IllegalMove(san.to_string()))?;
+        uci.push(Uci::from_standard(&m).to_string());
+        position.play_unchecked(&m);
+    }
+    Ok(uci)
+}
+
+/// FEN of the position after the first ply moves, given as SAN, are played from position. Every
+/// move is played when ply is None or the game is shorter.
+pub fn fen_after<P: Position>(
+    mut position: P,
+    moves: &[String],
+    ply: Option<usize>,
+) -> Result<String, MoveDecodeError> {
+    for san in moves.iter().take(ply.unwrap_or(moves.len())) {
+        let m = SanPlus::from_ascii(san.as_bytes())
+            .ok()
+            .and_then(|san_plus| san_plus.san.to_move(&position).ok())
+            .ok_or_else(|| MoveDecodeError::IllegalMove(san.to_string()))?;
+        position.play_unchecked(&m);
+    }
+    Ok(fen::fen(&position))
+}
+
+/// URL of the lichess analysis board at the position, which takes the FEN in its path with
+/// underscores instead of spaces.
+pub fn lichess_analysis_url(fen: &str) -> String {
+    format!("https://lichess.org/analysis/{}", fen.replace(' ', "_"))
+}
+
+/// URL of the chess.com analysis board at the position.
+pub fn chess_com_analysis_url(fen: &str) -> String {
+    Url::parse_with_params("https://www.chess.com/analysis", &[("fen", fen)])
+        .expect("the analysis URL is valid")
+        .to_string()
+}
+
+/// Check that a FEN describes a legal standard chess position.
+pub fn parse_fen(s: &str) -> Result<Fen, String> {
+    let fen: Fen = s
+        .trim()
+        .parse()
+        .map_err(|e| format!("invalid FEN {:?}: {}", s, e))?;
+    fen.position::<Chess>(CastlingMode::Standard)
+        .map_err(|e| format!("illegal position in FEN {:?}: {}", s, e))?;
+    Ok(fen)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use shakmaty::{fen::Fen, CastlingMode, Chess};
+
+    #[test]
+    fn test_pgn_moves_with_comments() {
+        let pgn = "[Event \"Live Chess\"]\n\n{Start} 1. e4 {[%clk 0:02:59.9]} 1... e5 {[%clk 0:02:58.1]} (1... c5 {Sicilian}) 2. Nf3 $1 1-0";
+        assert_eq!(
+            pgn_moves_with_comments(pgn),
+            vec![
+                ("e4".to_string(), "[%clk 0:02:59.9]".to_string()),
+                ("e5".to_string(), "[%clk 0:02:58.1]".to_string()),
+                ("Nf3".to_string(), String::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_clock_seconds() {
+        assert_eq!(clock_seconds("[%clk 0:02:59.9]"), Some(179.9));
+        assert_eq!(clock_seconds("[%eval 0.2] [%clk 1:00:05]"), Some(3605.0));
+        assert_eq!(clock_seconds("[%eval 0.2]"), None);
+        assert_eq!(clock_seconds("[%clk soon]"), None);
+    }
+
+    #[test]
+    fn test_minimal_pgn() {
+        let pgn = "[Event \"Live Chess\"]\n[Site \"Chess.com\"]\n[Date \"2021.02.05\"]\n[White \"Alice\"]\n[Black \"Bob\"]\n[Result \"1-0\"]\n[CurrentPosition \"\"]\n[ECO \"C20\"]\n[Termination \"\"]\n\n1. e4 {[%clk 0:02:59.9]} 1... e5 1-0\n";
+        let minimal = minimal_pgn(pgn);
+
+        let tags: Vec<&str> = minimal.lines().filter(|l| l.starts_with('[')).collect();
+        assert_eq!(
+            tags,
+            vec![
+                "[Event \"Live Chess\"]",
+                "[Site \"Chess.com\"]",
+                "[Date \"2021.02.05\"]",
+                "[Round \"?\"]",
+                "[White \"Alice\"]",
+                "[Black \"Bob\"]",
+                "[Result \"1-0\"]",
+            ]
+        );
+        assert!(minimal.ends_with("]\n\n1. e4 {[%clk 0:02:59.9]} 1... e5 1-0"));
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(
+            parse_date("2021-03-01"),
+            Ok(NaiveDate::from_ymd(2021, 3, 1))
+        );
+        assert!(parse_date("2021-02-30").is_err());
+        assert!(parse_date("01/03/2021").is_err());
+    }
+
+    #[test]
+    fn test_chess_com_termination() {
+        let codes = [
+            ("checkmated", Termination::Checkmate),
+            ("resigned", Termination::Resignation),
+            ("timeout", Termination::Timeout),
+            ("abandoned", Termination::Abandoned),
+            ("lose", Termination::Unknown),
+            ("kingofthehill", Termination::Unknown),
+            ("threecheck", Termination::Unknown),
+            ("bughousepartnerlose", Termination::Unknown),
+        ];
+        for (code, termination) in codes.iter() {
+            assert_eq!(chess_com_termination("win", code), *termination, "{}", code);
+            assert_eq!(chess_com_termination(code, "win"), *termination, "{}", code);
+        }
+        let draws = [
+            ("stalemate", Termination::Stalemate),
+            ("agreed", Termination::DrawAgreed),
+            ("repetition", Termination::Repetition),
+            ("insufficient", Termination::InsufficientMaterial),
+            ("timevsinsufficient", Termination::InsufficientMaterial),
+            ("50move", Termination::FiftyMove),
+        ];
+        for (code, termination) in draws.iter() {
+            assert_eq!(chess_com_termination(code, code), *termination, "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_chess_com_message_termination() {
+        let messages = [
+            ("Alice won by checkmate", Termination::Checkmate),
+            ("Alice won by resignation", Termination::Resignation),
+            ("Bob won on time", Termination::Timeout),
+            ("Bob won - game abandoned", Termination::Abandoned),
+            ("Game drawn by stalemate", Termination::Stalemate),
+            ("Game drawn by agreement", Termination::DrawAgreed),
+            ("Game drawn by repetition", Termination::Repetition),
+            (
+                "Game drawn by insufficient material",
+                Termination::InsufficientMaterial,
+            ),
+            (
+                "Game drawn by timeout vs insufficient material",
+                Termination::InsufficientMaterial,
+            ),
+            ("Game drawn by 50-move rule", Termination::FiftyMove),
+            ("Alice won", Termination::Unknown),
+        ];
+        for (message, termination) in messages.iter() {
+            assert_eq!(
+                chess_com_message_termination(message),
+                *termination,
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_lichess_termination() {
+        let statuses = [
+            ("mate", Termination::Checkmate),
+            ("resign", Termination::Resignation),
+            ("outoftime", Termination::Timeout),
+            ("stalemate", Termination::Stalemate),
+            ("draw", Termination::DrawAgreed),
+            ("timeout", Termination::Abandoned),
+            ("aborted", Termination::Abandoned),
+            ("noStart", Termination::Abandoned),
+            ("cheat", Termination::RulesInfraction),
+            ("variantEnd", Termination::Unknown),
+            ("unknownFinish", Termination::Unknown),
+            ("started", Termination::Unknown),
+        ];
+        for (status, termination) in statuses.iter() {
+            assert_eq!(lichess_termination(status), *termination, "{}", status);
+        }
+        assert_eq!(
+            serde_json::to_string(&Termination::InsufficientMaterial).unwrap(),
+            "\"insufficient_material\""
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(chrono::Duration::seconds(872)), "14m 32s");
+        assert_eq!(format_duration(chrono::Duration::seconds(45)), "45s");
+        assert_eq!(format_duration(chrono::Duration::seconds(0)), "0s");
+        assert_eq!(format_duration(chrono::Duration::seconds(3605)), "1h 0m 5s");
+        assert_eq!(
+            format_duration(chrono::Duration::days(3) + chrono::Duration::minutes(2)),
+            "3d 0h 2m 0s"
+        );
+        assert_eq!(format_duration(chrono::Duration::seconds(-5)), "0s");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("3600"), Ok(chrono::Duration::hours(1)));
+        assert_eq!(parse_duration("90s"), Ok(chrono::Duration::seconds(90)));
+        assert_eq!(parse_duration("5m"), Ok(chrono::Duration::minutes(5)));
+        assert_eq!(parse_duration("1h"), Ok(chrono::Duration::hours(1)));
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5m").is_err());
+        assert!(parse_duration("5d").is_err());
+    }
+
+    #[test]
+    fn test_eco_name() {
+        assert_eq!(eco_name("B20"), Some("Sicilian"));
+        assert_eq!(eco_name("C60"), Some("Ruy Lopez"));
+        assert_eq!(eco_name("C42"), Some("Petrov Defense"));
+        assert_eq!(eco_name("D35"), Some("Queen's Gambit Declined"));
+        assert_eq!(eco_name("E60"), Some("King's Indian Defense"));
+        assert_eq!(eco_name("a00"), Some("Polish (Sokolsky) Opening"));
+        assert_eq!(eco_name("F00"), None);
+        assert_eq!(eco_name(""), None);
+    }
+
+    #[test]
+    fn test_pgn_tag() {
+        let pgn = "[Event \"Live Chess\"]\n[ECO \"B20\"]\n\n1. e4 c5 1-0";
+        assert_eq!(pgn_tag(pgn, "ECO"), Some("B20".to_string()));
+        assert_eq!(pgn_tag(pgn, "Event"), Some("Live Chess".to_string()));
+        assert_eq!(pgn_tag(pgn, "Round"), None);
+    }
+
+    #[test]
+    fn test_pgn_moves() {
+        let pgn = "[Event \"Live Chess\"]\n[Result \"1-0\"]\n\n1. e4 {[%clk 0:02:59.9]} 1... e5 2. Qh5 $2 (2. Nf3 Nc6) 2... Nc6 ; a comment\n3. Bc4 Nf6?? 4.Qxf7# 1-0";
+        assert_eq!(
+            pgn_moves(pgn),
+            vec!["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6??", "Qxf7#"]
+        );
+        assert_eq!(pgn_moves("[Event \"?\"]\n\n*"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_movetext() {
+        let moves = vec!["e4".to_string(), "e5".to_string(), "Nf3".to_string()];
+        assert_eq!(movetext(&moves, "1-0"), "1. e4 e5 2. Nf3 1-0");
+        assert_eq!(movetext(&[], "*"), "*");
+    }
+
+    #[test]
+    fn test_annotate_moves() {
+        let moves = vec!["e4", "e5", "Nf3"];
+        let evals = vec!["+0.2".to_string(), "+0.3".to_string()];
+        assert_eq!(
+            annotate_moves(&moves, &evals),
+            "1. e4 [+0.2] e5 [+0.3]\n2. Nf3"
+        );
+        assert_eq!(annotate_moves(&[], &evals), "");
+    }
+
+    #[test]
+    fn test_fen_after() {
+        let moves: Vec<String> = ["e4", "e5", "Nf3"].iter().map(|m| m.to_string()).collect();
+        assert_eq!(
+            fen_after(Chess::default(), &moves, Some(1)).unwrap(),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(
+            fen_after(Chess::default(), &moves, None).unwrap(),
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"
+        );
+        assert_eq!(
+            fen_after(Chess::default(), &moves, Some(10)).unwrap(),
+            fen_after(Chess::default(), &moves, None).unwrap()
+        );
+        assert_eq!(
+            fen_after(Chess::default(), &["Ke2".to_string()], None),
+            Err(MoveDecodeError::IllegalMove("Ke2".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_analysis_urls() {
+        let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        assert_eq!(
+            lichess_analysis_url(fen),
+            "https://lichess.org/analysis/rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR_b_KQkq_-_0_1"
+        );
+        assert_eq!(
+            chess_com_analysis_url(fen),
+            "https://www.chess.com/analysis?fen=rnbqkbnr%2Fpppppppp%2F8%2F8%2F4P3%2F8%2FPPPP1PPP%2FRNBQKBNR+b+KQkq+-+0+1"
+        );
+    }
+
+    #[test]
+    fn test_pgn_tags() {
+        let pgn = "[Event \"Rated \\\"Blitz\\\" game\"]\n[Site \"https://lichess.org/q7ZvsdUF\"]\n[WhiteElo \"2389\"]\n\n1. d4 d5 1-0";
+        let tags = pgn_tags(pgn);
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags["Event"], "Rated \"Blitz\" game");
+        assert_eq!(tags["Site"], "https://lichess.org/q7ZvsdUF");
+        assert_eq!(tags["WhiteElo"], "2389");
+        assert!(pgn_tags("1. d4 d5 1-0").is_empty());
+    }
+
+    #[test]
+    fn test_strip_annotations() {
+        let pgn = "[Event \"Rated Blitz game\"]\n\n1. e4 { [%eval 0.2] [%clk 0:03:00] } 1... e5!? $1 { [%clk 0:03:00] }{ adjacent } 2. Nf3 { outer { nested } } 2... Nc6?! $6 (2... d6 { Philidor } 3. d4) 3. Bb5 ; a line comment\n3... a6 1-0";
+        assert_eq!(
+            strip_annotations(pgn),
+            "[Event \"Rated Blitz game\"]\n\n1. e4 e5 2. Nf3 Nc6 (2... d6 3. d4) 3. Bb5 a6 1-0"
+        );
+
+        // Games starting with black to move keep their first move number
+        assert_eq!(
+            strip_annotations("12... Kh8 { only move } 13. Qh5 *"),
+            "12... Kh8 13. Qh5 *"
+        );
+        assert_eq!(
+            strip_annotations("1.e4! e5 2.Nf3 0-1"),
+            "1. e4 e5 2. Nf3 0-1"
+        );
+    }
+
+    #[test]
+    fn test_truncate_pgn() {
+        let pgn = "[Event \"Live Chess\"]\n[Result \"1-0\"]\n\n1. e4 {[%clk 0:03:00]} 1... e5 2. Bc4 (2. Nf3 Nc6 3. Bb5) 2... Nc6 3. Qh5 Nf6 4. Qxf7# 1-0";
+        assert_eq!(
+            truncate_pgn(pgn, 2),
+            "[Event \"Live Chess\"]\n[Result \"*\"]\n\n1. e4 {[%clk 0:03:00]} 1... e5 2. Bc4 (2. Nf3 Nc6 3. Bb5) 2... Nc6 *"
+        );
+        assert_eq!(truncate_pgn(pgn, 4), pgn);
+        assert_eq!(truncate_pgn("1. d4 d5 2. c4 1-0", 1), "1. d4 d5 *");
+    }
+
+    #[test]
+    fn test_encode_latin1() {
+        assert_eq!(encode_latin1("Magnus"), b"Magnus".to_vec());
+        assert_eq!(encode_latin1("Jesús"), vec![b'J', b'e', b's', 0xFA, b's']);
+        assert_eq!(encode_latin1("Ding 丁立人"), b"Ding ???".to_vec());
+        // Windows-1252 only characters would be C1 control codes in Latin-1
+        assert_eq!(encode_latin1("€5 — Œuvre"), b"?5 ? ?uvre".to_vec());
+        assert_eq!(encode_latin1("\u{ff}"), vec![0xFF]);
+    }
 
     #[test]
     fn test_next_move_e4_e5() {
         let mut moves: Vec<char> = vec!['K', '0', 'C', 'm'];
         let mut position = Chess::default();
 
-        let e4 = next_move(&mut moves, &mut position);
+        let e4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(e4, Some("e4".to_string()));
         assert_eq!(moves, vec!['K', '0']);
 
-        let e5 = next_move(&mut moves, &mut position);
+        let e5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(e5, Some("e5".to_string()));
         assert_eq!(moves, Vec::<char>::new());
 
-        let no_moves = next_move(&mut moves, &mut position);
+        let no_moves = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(no_moves, None);
     }
 
@@ -154,19 +1119,19 @@ mod tests {
         let mut moves: Vec<char> = vec!['J', 'C', 'J', 'Z', 'C', 'm'];
         let mut position = Chess::default();
 
-        let e4 = next_move(&mut moves, &mut position);
+        let e4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(e4, Some("e4".to_string()));
         assert_eq!(moves, vec!['J', 'C', 'J', 'Z']);
 
-        let d5 = next_move(&mut moves, &mut position);
+        let d5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(d5, Some("d5".to_string()));
         assert_eq!(moves, vec!['J', 'C']);
 
-        let exd5 = next_move(&mut moves, &mut position);
+        let exd5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(exd5, Some("exd5".to_string()));
         assert_eq!(moves, Vec::<char>::new());
 
-        let no_moves = next_move(&mut moves, &mut position);
+        let no_moves = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(no_moves, None);
     }
 
@@ -175,35 +1140,35 @@ mod tests {
         let mut moves: Vec<char> = vec!['B', '7', 'u', 'm', 'C', 'J', 'm', 'e', 'J', 'Z', 'C', 'm'];
         let mut position = Chess::default();
 
-        let e4 = next_move(&mut moves, &mut position);
+        let e4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(e4, Some("e4".to_string()));
         assert_eq!(
             moves,
             vec!['B', '7', 'u', 'm', 'C', 'J', 'm', 'e', 'J', 'Z']
         );
 
-        let d5 = next_move(&mut moves, &mut position);
+        let d5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(d5, Some("d5".to_string()));
         assert_eq!(moves, vec!['B', '7', 'u', 'm', 'C', 'J', 'm', 'e']);
 
-        let ke2 = next_move(&mut moves, &mut position);
+        let ke2 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(ke2, Some("Ke2".to_string()));
         assert_eq!(moves, vec!['B', '7', 'u', 'm', 'C', 'J']);
 
-        let dxe4 = next_move(&mut moves, &mut position);
+        let dxe4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(dxe4, Some("dxe4".to_string()));
         assert_eq!(moves, vec!['B', '7', 'u', 'm']);
 
-        let ke3 = next_move(&mut moves, &mut position);
+        let ke3 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(ke3, Some("Ke3".to_string()));
         assert_eq!(moves, vec!['B', '7']);
 
-        let qd4 = next_move(&mut moves, &mut position);
+        let qd4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(qd4, Some("Qd4+".to_string()));
         assert_eq!(moves, Vec::<char>::new());
         assert_eq!(position.is_check(), true);
 
-        let no_moves = next_move(&mut moves, &mut position);
+        let no_moves = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(no_moves, None);
     }
 
@@ -214,45 +1179,61 @@ mod tests {
         ];
         let mut position = Chess::default();
 
-        let e4 = next_move(&mut moves, &mut position);
+        let e4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(e4, Some("e4".to_string()));
         assert_eq!(
             moves,
             vec!['1', 'N', 'T', '!', 'A', 'f', 'Q', '5', 'N', 'd', 'K', '0']
         );
 
-        let e5 = next_move(&mut moves, &mut position);
+        let e5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(e5, Some("e5".to_string()));
         assert_eq!(
             moves,
             vec!['1', 'N', 'T', '!', 'A', 'f', 'Q', '5', 'N', 'd']
         );
 
-        let qh5 = next_move(&mut moves, &mut position);
+        let qh5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(qh5, Some("Qh5".to_string()));
         assert_eq!(moves, vec!['1', 'N', 'T', '!', 'A', 'f', 'Q', '5']);
 
-        let nc6 = next_move(&mut moves, &mut position);
+        let nc6 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(nc6, Some("Nc6".to_string()));
         assert_eq!(moves, vec!['1', 'N', 'T', '!', 'A', 'f']);
 
-        let bc4 = next_move(&mut moves, &mut position);
+        let bc4 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(bc4, Some("Bc4".to_string()));
         assert_eq!(moves, vec!['1', 'N', 'T', '!']);
 
-        let nf6 = next_move(&mut moves, &mut position);
+        let nf6 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(nf6, Some("Nf6".to_string()));
         assert_eq!(moves, vec!['1', 'N']);
 
-        let qxf7 = next_move(&mut moves, &mut position);
+        let qxf7 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(qxf7, Some("Qxf7#".to_string()));
         assert_eq!(moves, Vec::<char>::new());
         assert_eq!(position.is_checkmate(), true);
 
-        let no_moves = next_move(&mut moves, &mut position);
+        let no_moves = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(no_moves, None);
     }
 
+    #[test]
+    fn test_next_move_errors() {
+        let decode = |encoded: &str| {
+            let mut moves: Vec<char> = encoded.chars().rev().collect();
+            next_move(&mut moves, &mut Chess::default())
+        };
+
+        assert_eq!(decode("*K"), Err(MoveDecodeError::UnknownCharacter('*')));
+        assert_eq!(decode("m*"), Err(MoveDecodeError::UnknownCharacter('*')));
+        assert_eq!(decode("uC"), Err(MoveDecodeError::EmptySquare(Square::E3)));
+        assert_eq!(decode("eb"), Err(MoveDecodeError::IllegalCastle));
+        assert_eq!(decode("3}"), Err(MoveDecodeError::OffBoardPromotion));
+        assert!(matches!(decode("mK"), Err(MoveDecodeError::IllegalMove(_))));
+        assert!(matches!(decode("m"), Err(MoveDecodeError::IllegalMove(_))));
+    }
+
     #[test]
     fn test_next_move_castle_king_side() {
         let mut moves: Vec<char> = vec!['g', 'e'];
@@ -262,7 +1243,7 @@ mod tests {
             .position(CastlingMode::Standard)
             .unwrap();
 
-        let castle = next_move(&mut moves, &mut position);
+        let castle = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(castle, Some("O-O".to_string()));
     }
 
@@ -275,7 +1256,7 @@ mod tests {
             .position(CastlingMode::Standard)
             .unwrap();
 
-        let castle = next_move(&mut moves, &mut position);
+        let castle = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(castle, Some("O-O-O".to_string()));
     }
 
@@ -288,7 +1269,7 @@ mod tests {
             .position(CastlingMode::Standard)
             .unwrap();
 
-        let castle = next_move(&mut moves, &mut position);
+        let castle = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(castle, Some("exf1=Q#".to_string()));
     }
 
@@ -301,7 +1282,7 @@ mod tests {
             .position(CastlingMode::Standard)
             .unwrap();
 
-        let castle = next_move(&mut moves, &mut position);
+        let castle = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(castle, Some("e1=N".to_string()));
     }
 
@@ -314,15 +1295,53 @@ mod tests {
             .position(CastlingMode::Standard)
             .unwrap();
 
-        let d5 = next_move(&mut moves, &mut position);
+        let d5 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(d5, Some("d5".to_string()));
         assert_eq!(moves, vec!['R', 'K']);
 
-        let exd6 = next_move(&mut moves, &mut position);
+        let exd6 = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(exd6, Some("exd6".to_string()));
         assert_eq!(moves, Vec::<char>::new());
 
-        let no_moves = next_move(&mut moves, &mut position);
+        let no_moves = next_move(&mut moves, &mut position).unwrap();
         assert_eq!(no_moves, None);
     }
+
+    #[test]
+    fn test_parse_fen() {
+        assert!(parse_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").is_ok());
+        assert!(parse_fen("not a fen")
+            .unwrap_err()
+            .starts_with("invalid FEN"));
+        // Both kings missing
+        assert!(parse_fen("8/8/8/8/8/8/8/8 w - - 0 1")
+            .unwrap_err()
+            .starts_with("illegal position"));
+    }
+
+    #[test]
+    fn test_san_to_uci() {
+        let moves = ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"]
+            .iter()
+            .map(|m| m.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            san_to_uci(Chess::default(), &moves).unwrap().join(" "),
+            "e2e4 e7e5 f1c4 b8c6 d1h5 g8f6 h5f7"
+        );
+
+        let moves = vec!["O-O".to_string()];
+        let position: Chess = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1"
+            .parse::<Fen>()
+            .unwrap()
+            .position(CastlingMode::Standard)
+            .unwrap();
+        assert_eq!(san_to_uci(position, &moves).unwrap(), vec!["e1g1"]);
+
+        let moves = vec!["e4".to_string(), "e4".to_string()];
+        assert_eq!(
+            san_to_uci(Chess::default(), &moves),
+            Err(MoveDecodeError::IllegalMove("e4".to_string()))
+        );
+    }
 }